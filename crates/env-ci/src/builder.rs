@@ -24,6 +24,8 @@ impl CiEnvBuilder {
         job_url: None,
         build: None,
         build_url: None,
+        server_url: None,
+        api_url: None,
         is_ci: false,
         is_pull_request: false,
       },
@@ -85,11 +87,23 @@ impl CiEnvBuilder {
     self
   }
 
+  pub fn server_url(mut self, server_url: Option<String>) -> Self {
+    self.inner.server_url = server_url;
+    self
+  }
+
+  pub fn api_url(mut self, api_url: Option<String>) -> Self {
+    self.inner.api_url = api_url;
+    self
+  }
+
+  #[allow(clippy::wrong_self_convention)]
   pub fn is_ci(mut self, is_ci: bool) -> Self {
     self.inner.is_ci = is_ci;
     self
   }
 
+  #[allow(clippy::wrong_self_convention)]
   pub fn is_pull_request(mut self, is_pull_request: bool) -> Self {
     self.inner.is_pull_request = is_pull_request;
     self
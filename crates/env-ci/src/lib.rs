@@ -11,16 +11,18 @@ mod builder;
 mod services;
 mod util;
 
+use serde::Serialize;
 use std::{collections::HashMap, env};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum CiServices {
   Git,
   GitHub,
   GitLab,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize)]
 pub struct CiEnv {
   pub name: String,
   pub service: CiServices,
@@ -40,19 +42,22 @@ pub struct CiEnv {
   pub build: Option<String>,
   pub build_url: Option<String>,
   //
+  pub server_url: Option<String>,
+  pub api_url: Option<String>,
+  //
   pub is_ci: bool,
   pub is_pull_request: bool,
 }
 
 fn detect(env: &HashMap<String, String>) -> CiServices {
-  if services::github::detect(&env) {
+  if services::github::detect(env) {
     return CiServices::GitHub;
   }
-  if services::gitlab::detect(&env) {
+  if services::gitlab::detect(env) {
     return CiServices::GitLab;
   }
 
-  return CiServices::Git;
+  CiServices::Git
 }
 
 pub fn get() -> CiEnv {
@@ -10,14 +10,10 @@ use regex::Regex;
 use std::{collections::HashMap, fs};
 
 fn parse_branch(branch: &str) -> Option<String> {
-  if let Some(captures) = Regex::new(r"^(?:refs\/heads\/)?(?P<branch>.+)$")
+  Regex::new(r"^(?:refs\/heads\/)?(?P<branch>.+)$")
     .unwrap()
     .captures(branch)
-  {
-    Some(captures["branch"].to_string())
-  } else {
-    None
-  }
+    .map(|captures: regex::Captures| captures["branch"].to_string())
 }
 
 struct PullRequestEvent {
@@ -70,12 +66,12 @@ pub fn get(env: &HashMap<String, String>) -> CiEnv {
       github_event_name
         .as_ref()
         .filter(|&name| name == "pull_request_target")
-        .and_then(|_| pre_id.as_ref())
+        .and(pre_id.as_ref())
         .map(|pid: &String| format!("refs/pull/{}/merge", pid))
         .or_else(|| get_env_var(env, "GITHUB_REF"))
     });
 
-    name.and_then(|v: String| parse_branch(&v)).or_else(|| None)
+    name.and_then(|v: String| parse_branch(&v))
   };
 
   let pull_request: Option<String> = pre_id;
@@ -84,16 +80,33 @@ pub fn get(env: &HashMap<String, String>) -> CiEnv {
     .map(|v: String| v == "pull_request" || v == "pull_request_target")
     .unwrap_or(false);
 
-  let pull_request_branch: Option<String> = is_pull_request.then(|| branch.clone()).unwrap_or(None);
+  let pull_request_branch: Option<String> = if is_pull_request {
+    branch.clone()
+  } else {
+    None
+  };
+
+  let server_url: Option<String> = get_env_var(env, "GITHUB_SERVER_URL");
+  let slug: Option<String> = get_env_var(env, "GITHUB_REPOSITORY");
+  let build: Option<String> = get_env_var(env, "GITHUB_RUN_ID");
+  let build_url: Option<String> = match (&server_url, &slug, &build) {
+    (Some(server_url), Some(slug), Some(build)) => {
+      Some(format!("{}/{}/actions/runs/{}", server_url, slug, build))
+    }
+    _ => None,
+  };
 
   CiEnvBuilder::new("GitHub Actions", CiServices::GitHub)
-    .slug(get_env_var(env, "GITHUB_REPOSITORY"))
+    .slug(slug)
     .root(get_env_var(env, "GITHUB_WORKSPACE"))
     .commit(get_env_var(env, "GITHUB_SHA"))
     .branch(branch)
     .pull_request(pull_request)
     .pull_request_branch(pull_request_branch)
-    .build(get_env_var(env, "GITHUB_RUN_ID"))
+    .build(build)
+    .build_url(build_url)
+    .server_url(server_url)
+    .api_url(get_env_var(env, "GITHUB_API_URL"))
     .is_ci(true)
     .is_pull_request(is_pull_request)
     .get()
@@ -44,7 +44,72 @@ pub fn get(env: &HashMap<String, String>) -> CiEnv {
     .job_url(job_url)
     .build(build)
     .build_url(build_url)
+    .server_url(get_env_var(env, "CI_SERVER_URL"))
+    .api_url(get_env_var(env, "CI_API_V4_URL"))
     .is_ci(true)
     .is_pull_request(is_pull_request)
     .get()
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn get_env(variables: &[(&str, &str)]) -> HashMap<String, String> {
+    variables
+      .iter()
+      .map(|(name, value): &(&str, &str)| (name.to_string(), value.to_string()))
+      .collect()
+  }
+
+  #[test]
+  fn gets_the_urls_of_the_instance() {
+    let env: HashMap<String, String> = get_env(&[
+      ("GITLAB_CI", "true"),
+      ("CI_SERVER_URL", "https://gitlab.example.com"),
+      ("CI_API_V4_URL", "https://gitlab.example.com/api/v4"),
+      ("CI_PROJECT_URL", "https://gitlab.example.com/group/app"),
+      ("CI_JOB_ID", "12"),
+      ("CI_PIPELINE_ID", "34"),
+      ("CI_COMMIT_REF_NAME", "main"),
+    ]);
+
+    assert!(detect(&env));
+    let ci_env: CiEnv = get(&env);
+    assert_eq!(
+      ci_env.server_url.as_deref(),
+      Some("https://gitlab.example.com")
+    );
+    assert_eq!(
+      ci_env.api_url.as_deref(),
+      Some("https://gitlab.example.com/api/v4")
+    );
+    assert_eq!(
+      ci_env.job_url.as_deref(),
+      Some("https://gitlab.example.com/group/app/-/jobs/12")
+    );
+    assert_eq!(
+      ci_env.build_url.as_deref(),
+      Some("https://gitlab.example.com/group/app/pipelines/34")
+    );
+    assert_eq!(ci_env.branch.as_deref(), Some("main"));
+    assert!(!ci_env.is_pull_request);
+  }
+
+  #[test]
+  fn leaves_out_the_empty_urls() {
+    let env: HashMap<String, String> = get_env(&[
+      ("GITLAB_CI", "true"),
+      ("CI_SERVER_URL", ""),
+      ("CI_MERGE_REQUEST_ID", "5"),
+      ("CI_MERGE_REQUEST_TARGET_BRANCH_NAME", "main"),
+      ("CI_COMMIT_REF_NAME", "feature"),
+    ]);
+
+    let ci_env: CiEnv = get(&env);
+    assert_eq!(ci_env.server_url, None);
+    assert_eq!(ci_env.api_url, None);
+    assert_eq!(ci_env.branch.as_deref(), Some("main"));
+    assert!(ci_env.is_pull_request);
+  }
+}
@@ -4,7 +4,7 @@
 use std::{collections::HashMap, process::Command};
 
 pub fn env_var_exists(env: &HashMap<String, String>, variable: &str) -> bool {
-  env.get(variable).map_or(false, |v: &String| !v.is_empty())
+  env.get(variable).is_some_and(|v: &String| !v.is_empty())
 }
 
 pub fn get_env_var(env: &HashMap<String, String>, variable: &str) -> Option<String> {
@@ -43,7 +43,7 @@ pub fn get_branch() -> Option<String> {
 
         let output: Option<String> = std::str::from_utf8(&command.stdout)
           .ok()?
-          .replace(|v| v == '(' || v == ')', "")
+          .replace(['(', ')'], "")
           .split(", ")
           .find(|v: &&str| v.starts_with("origin/"))
           .and_then(|v: &str| v.strip_prefix("origin/"))
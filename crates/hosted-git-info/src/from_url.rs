@@ -2,19 +2,22 @@
 // Check the README file in the project root for more information.
 
 use crate::{
+  hosts::{HostType, Segments},
   parse::parse,
-  protocol::{Protocol, ProtocolType},
+  protocol::{self, Protocol, ProtocolType},
+  HostedGitInfo,
 };
-use std::{collections::HashMap, io::Error};
+use std::collections::HashMap;
 use url::Url;
 
 pub struct FromUrl {
   url: String,
   no_commitish: bool,
   no_git_plus: bool,
+  hosts: Vec<(String, HostType)>,
 }
 
-struct FromUrlBuilder {
+pub struct FromUrlBuilder {
   inner: FromUrl,
 }
 
@@ -25,6 +28,7 @@ impl FromUrlBuilder {
         url: url.to_string(),
         no_commitish: false,
         no_git_plus: false,
+        hosts: Vec::new(),
       },
     }
   }
@@ -39,6 +43,12 @@ impl FromUrlBuilder {
     self
   }
 
+  // Treats a domain, like the one of a GitHub Enterprise instance, as a self-hosted host
+  pub fn host(mut self, domain: &str, host_type: HostType) -> Self {
+    self.inner.hosts.push((domain.to_lowercase(), host_type));
+    self
+  }
+
   pub fn get(self) -> FromUrl {
     self.inner
   }
@@ -63,35 +73,33 @@ fn is_github_shorthand(url: &str) -> bool {
     return false;
   }
 
-  let at_only_after_hash: bool = at_idx
-    .and_then(|at_idx: usize| hash_idx.map(|hash_idx: usize| at_idx > hash_idx))
-    .unwrap_or(true);
+  let at_only_after_hash: bool =
+    at_idx.is_none_or(|at_idx: usize| hash_idx.is_some_and(|hash_idx: usize| at_idx > hash_idx));
   if !at_only_after_hash {
     return false;
   }
 
   let colon_only_after_hash: bool = colon_idx
-    .and_then(|colon_idx: usize| hash_idx.map(|hash_idx: usize| colon_idx > hash_idx))
-    .unwrap_or(true);
+    .is_none_or(|colon_idx: usize| hash_idx.is_some_and(|hash_idx: usize| colon_idx > hash_idx));
   if !colon_only_after_hash {
     return false;
   }
 
-  let second_slash_only_after_hash: bool = second_slash_idx
-    .and_then(|second_slash_idx: usize| hash_idx.map(|hash_idx: usize| second_slash_idx > hash_idx))
-    .unwrap_or(true);
+  let second_slash_only_after_hash: bool =
+    second_slash_idx.is_none_or(|second_slash_idx: usize| {
+      hash_idx.is_some_and(|hash_idx: usize| second_slash_idx > hash_idx)
+    });
   if !second_slash_only_after_hash {
     return false;
   }
 
   let space_only_after_hash: bool = space_idx
-    .and_then(|space_idx: usize| hash_idx.map(|hash_idx: usize| space_idx > hash_idx))
-    .unwrap_or(true);
+    .is_none_or(|space_idx: usize| hash_idx.is_some_and(|hash_idx: usize| space_idx > hash_idx));
   if !space_only_after_hash {
     return false;
   }
 
-  let has_slash: bool = first_slash_idx.map_or(false, |i: usize| i > 0);
+  let has_slash: bool = first_slash_idx.is_some_and(|i: usize| i > 0);
   if !has_slash {
     return false;
   }
@@ -105,3 +113,92 @@ fn is_github_shorthand(url: &str) -> bool {
 
   true
 }
+
+// Parses the 'user/project' path of the shortcuts like 'github:user/project#committish'
+fn extract_shortcut(url: &Url) -> Option<Segments> {
+  let path: &str = url.path().strip_prefix('/').unwrap_or(url.path());
+  let path: &str = match path.find('@') {
+    Some(at_idx) => &path[(at_idx + 1)..],
+    None => path,
+  };
+
+  let (user, project) = path.rsplit_once('/')?;
+  let project: &str = project.strip_suffix(".git").unwrap_or(project);
+  if user.is_empty() || project.is_empty() {
+    return None;
+  }
+
+  Some(Segments {
+    user: user.to_string(),
+    project: project.to_string(),
+    committish: url
+      .fragment()
+      .filter(|v: &&str| !v.is_empty())
+      .map(|v: &str| v.to_string()),
+  })
+}
+
+pub fn from_url(options: &FromUrl) -> Option<HostedGitInfo> {
+  let url: String = if is_github_shorthand(&options.url) {
+    format!("github:{}", options.url)
+  } else {
+    options.url.clone()
+  };
+
+  let shortcut: Option<HostType> = url
+    .find(':')
+    .and_then(|colon_idx: usize| HostType::from_shortcut(&url[..=colon_idx]));
+
+  let (host_type, segments, auth, domain): (HostType, Segments, Option<String>, Option<String>) =
+    match shortcut {
+      Some(host_type) => {
+        let parsed: Url = parse(&url, None).ok()?;
+        (host_type, extract_shortcut(&parsed)?, None, None)
+      }
+      None => {
+        let protocols: HashMap<ProtocolType, Protocol> = protocol::get_all();
+        let parsed: Url = parse(&url, Some(&protocols)).ok()?;
+
+        // The configured domain can have the port of the web interface, which SSH URLs don't use
+        let host_str: &str = parsed.host_str()?;
+        let self_hosted: Option<&(String, HostType)> = options
+          .hosts
+          .iter()
+          .find(|(domain, _): &&(String, HostType)| domain.split(':').next() == Some(host_str));
+        let (host_type, domain): (HostType, Option<String>) = match self_hosted {
+          Some((domain, host_type)) => (*host_type, Some(domain.clone())),
+          None => (HostType::from_domain(host_str)?, None),
+        };
+
+        let protocol_type: ProtocolType =
+          protocol::type_from_str(&format!("{}:", parsed.scheme()))?;
+        if !host_type.protocols().contains(&protocol_type) {
+          return None;
+        }
+
+        let auth: Option<String> = match (
+          protocols
+            .get(&protocol_type)
+            .is_some_and(|v: &Protocol| v.auth),
+          parsed.username(),
+          parsed.password(),
+        ) {
+          (true, username, Some(password)) => Some(format!("{}:{}", username, password)),
+          (true, username, None) if !username.is_empty() => Some(username.to_string()),
+          _ => None,
+        };
+
+        (host_type, host_type.extract(&parsed)?, auth, domain)
+      }
+    };
+
+  Some(HostedGitInfo {
+    host_type,
+    user: segments.user,
+    project: segments.project,
+    auth,
+    committish: segments.committish.filter(|_| !options.no_commitish),
+    domain,
+    no_git_plus: options.no_git_plus,
+  })
+}
@@ -0,0 +1,187 @@
+// Copyright (c) Toolbi Software. All rights reserved.
+// Check the README file in the project root for more information.
+
+use crate::protocol::ProtocolType;
+use url::Url;
+
+const HOSTS: [HostType; 5] = [
+  HostType::GitHub,
+  HostType::GitLab,
+  HostType::Bitbucket,
+  HostType::Sourcehut,
+  HostType::Gitea,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HostType {
+  GitHub,
+  GitLab,
+  Bitbucket,
+  Sourcehut,
+  // Forgejo instances too, which keep the same API
+  Gitea,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segments {
+  pub user: String,
+  pub project: String,
+  pub committish: Option<String>,
+}
+
+fn strip_git(project: &str) -> &str {
+  project.strip_suffix(".git").unwrap_or(project)
+}
+
+fn get_committish(url: &Url) -> Option<String> {
+  url
+    .fragment()
+    .filter(|v: &&str| !v.is_empty())
+    .map(|v: &str| v.to_string())
+}
+
+impl HostType {
+  pub fn all() -> &'static [HostType] {
+    &HOSTS
+  }
+
+  pub fn from_domain(domain: &str) -> Option<HostType> {
+    let domain: &str = domain.strip_prefix("www.").unwrap_or(domain);
+    HOSTS
+      .iter()
+      .find(|v: &&HostType| v.domain() == domain)
+      .copied()
+  }
+
+  pub fn from_shortcut(shortcut: &str) -> Option<HostType> {
+    HOSTS
+      .iter()
+      .find(|v: &&HostType| v.shortcut() == shortcut)
+      .copied()
+  }
+
+  pub fn domain(&self) -> &'static str {
+    match self {
+      HostType::GitHub => "github.com",
+      HostType::GitLab => "gitlab.com",
+      HostType::Bitbucket => "bitbucket.org",
+      HostType::Sourcehut => "git.sr.ht",
+      HostType::Gitea => "codeberg.org",
+    }
+  }
+
+  pub fn shortcut(&self) -> &'static str {
+    match self {
+      HostType::GitHub => "github:",
+      HostType::GitLab => "gitlab:",
+      HostType::Bitbucket => "bitbucket:",
+      HostType::Sourcehut => "sourcehut:",
+      HostType::Gitea => "codeberg:",
+    }
+  }
+
+  pub fn protocols(&self) -> &'static [ProtocolType] {
+    match self {
+      HostType::GitHub => &[
+        ProtocolType::Git,
+        ProtocolType::Http,
+        ProtocolType::GitPlusSsh,
+        ProtocolType::GitPlusHttps,
+        ProtocolType::Ssh,
+        ProtocolType::Https,
+      ],
+      _ => &[
+        ProtocolType::GitPlusSsh,
+        ProtocolType::GitPlusHttps,
+        ProtocolType::Ssh,
+        ProtocolType::Https,
+      ],
+    }
+  }
+
+  pub fn extract(&self, url: &Url) -> Option<Segments> {
+    let path: &str = url.path().strip_prefix('/').unwrap_or(url.path());
+
+    let (user, project, committish): (String, String, Option<String>) = match self {
+      HostType::GitHub => {
+        let parts: Vec<&str> = path.splitn(4, '/').collect();
+        let (user, project) = (*parts.first()?, *parts.get(1)?);
+        let committish: Option<String> = match parts.get(2) {
+          Some(&"tree") => parts.get(3).map(|v: &&str| v.to_string()),
+          Some(&"") | None => get_committish(url),
+          Some(_) => return None,
+        };
+        (user.to_string(), strip_git(project).to_string(), committish)
+      }
+      // GitLab projects can live inside subgroups
+      HostType::GitLab => {
+        if path.contains("/-/") || path.contains("/archive.tar.gz") {
+          return None;
+        }
+        let (user, project) = path.trim_end_matches('/').rsplit_once('/')?;
+        (
+          user.to_string(),
+          strip_git(project).to_string(),
+          get_committish(url),
+        )
+      }
+      HostType::Bitbucket | HostType::Sourcehut | HostType::Gitea => {
+        let parts: Vec<&str> = path.splitn(3, '/').collect();
+        let (user, project) = (*parts.first()?, *parts.get(1)?);
+        let aux: &str = match self {
+          HostType::Bitbucket => "get",
+          _ => "archive",
+        };
+        if parts
+          .get(2)
+          .is_some_and(|v: &&str| v.split('/').next() == Some(aux))
+        {
+          return None;
+        }
+        (
+          user.to_string(),
+          strip_git(project).to_string(),
+          get_committish(url),
+        )
+      }
+    };
+
+    if user.is_empty() || project.is_empty() {
+      return None;
+    }
+
+    Some(Segments {
+      user,
+      project,
+      committish,
+    })
+  }
+
+  // The path on the web interface, relative to the project URL, of a commit
+  pub fn commit_path(&self, hash: &str) -> String {
+    match self {
+      HostType::GitLab => format!("/-/commit/{}", hash),
+      HostType::Bitbucket => format!("/commits/{}", hash),
+      _ => format!("/commit/{}", hash),
+    }
+  }
+
+  pub fn issue_path(&self, number: &str) -> Option<String> {
+    match self {
+      HostType::GitHub | HostType::Bitbucket | HostType::Gitea => {
+        Some(format!("/issues/{}", number))
+      }
+      HostType::GitLab => Some(format!("/-/issues/{}", number)),
+      HostType::Sourcehut => None,
+    }
+  }
+
+  pub fn compare_path(&self, from: &str, to: &str) -> Option<String> {
+    match self {
+      HostType::GitHub | HostType::Gitea => Some(format!("/compare/{}...{}", from, to)),
+      HostType::GitLab => Some(format!("/-/compare/{}...{}", from, to)),
+      HostType::Bitbucket => Some(format!("/branches/compare/{}%0D{}", to, from)),
+      HostType::Sourcehut => None,
+    }
+  }
+}
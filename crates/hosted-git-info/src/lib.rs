@@ -0,0 +1,92 @@
+// Copyright (c) Toolbi Software. All rights reserved.
+// Check the README file in the project root for more information.
+
+mod from_url;
+mod hosts;
+mod parse;
+mod protocol;
+
+pub use from_url::{from_url, FromUrl, FromUrlBuilder};
+pub use hosts::HostType;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostedGitInfo {
+  pub host_type: HostType,
+  pub user: String,
+  pub project: String,
+  pub auth: Option<String>,
+  pub committish: Option<String>,
+  // The domain of self-hosted instances, like GitHub Enterprise
+  domain: Option<String>,
+  no_git_plus: bool,
+}
+
+impl HostedGitInfo {
+  pub fn domain(&self) -> &str {
+    self
+      .domain
+      .as_deref()
+      .unwrap_or_else(|| self.host_type.domain())
+  }
+
+  pub fn browse(&self) -> String {
+    format!("https://{}/{}/{}", self.domain(), self.user, self.project)
+  }
+
+  pub fn https(&self) -> String {
+    let auth: String = self
+      .auth
+      .as_ref()
+      .map(|v: &String| format!("{}@", v))
+      .unwrap_or_default();
+    let prefix: &str = if self.no_git_plus { "" } else { "git+" };
+
+    format!(
+      "{}https://{}{}/{}/{}.git",
+      prefix,
+      auth,
+      self.domain(),
+      self.user,
+      self.project
+    )
+  }
+
+  pub fn ssh_url(&self) -> String {
+    let prefix: &str = if self.no_git_plus { "" } else { "git+" };
+
+    format!(
+      "{}ssh://git@{}/{}/{}.git",
+      prefix,
+      self.domain(),
+      self.user,
+      self.project
+    )
+  }
+
+  pub fn commit(&self, hash: &str) -> String {
+    format!("{}{}", self.browse(), self.host_type.commit_path(hash))
+  }
+
+  pub fn issue(&self, number: &str) -> Option<String> {
+    self
+      .host_type
+      .issue_path(number)
+      .map(|v: String| format!("{}{}", self.browse(), v))
+  }
+
+  pub fn compare(&self, from: &str, to: &str) -> Option<String> {
+    self
+      .host_type
+      .compare_path(from, to)
+      .map(|v: String| format!("{}{}", self.browse(), v))
+  }
+}
+
+impl std::fmt::Display for HostedGitInfo {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match &self.committish {
+      Some(committish) => write!(f, "{}#{}", self.browse(), committish),
+      None => write!(f, "{}", self.browse()),
+    }
+  }
+}
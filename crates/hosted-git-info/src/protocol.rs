@@ -0,0 +1,45 @@
+// Copyright (c) Toolbi Software. All rights reserved.
+// Check the README file in the project root for more information.
+
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProtocolType {
+  Git,
+  GitPlusSsh,
+  GitPlusHttp,
+  GitPlusHttps,
+  Ssh,
+  Http,
+  Https,
+}
+
+#[derive(Debug, Clone)]
+pub struct Protocol {
+  pub auth: bool,
+}
+
+pub fn type_from_str(value: &str) -> Option<ProtocolType> {
+  match value {
+    "git:" => Some(ProtocolType::Git),
+    "git+ssh:" => Some(ProtocolType::GitPlusSsh),
+    "git+http:" => Some(ProtocolType::GitPlusHttp),
+    "git+https:" => Some(ProtocolType::GitPlusHttps),
+    "ssh:" => Some(ProtocolType::Ssh),
+    "http:" => Some(ProtocolType::Http),
+    "https:" => Some(ProtocolType::Https),
+    _ => None,
+  }
+}
+
+pub fn get_all() -> HashMap<ProtocolType, Protocol> {
+  HashMap::from([
+    (ProtocolType::Git, Protocol { auth: true }),
+    (ProtocolType::GitPlusSsh, Protocol { auth: false }),
+    (ProtocolType::GitPlusHttp, Protocol { auth: true }),
+    (ProtocolType::GitPlusHttps, Protocol { auth: true }),
+    (ProtocolType::Ssh, Protocol { auth: false }),
+    (ProtocolType::Http, Protocol { auth: true }),
+    (ProtocolType::Https, Protocol { auth: true }),
+  ])
+}
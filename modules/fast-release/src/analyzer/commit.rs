@@ -0,0 +1,275 @@
+// Copyright (c) Toolbi Software. All rights reserved.
+// Check the README file in the project root for more information.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+lazy_static! {
+  static ref HEADER_REGEX: Regex =
+    Regex::new(r"^(?P<type>\w[\w-]*)(?:\((?P<scope>[^()]*)\))?(?P<breaking>!)?: (?P<subject>.+)$")
+      .unwrap();
+  static ref GIT_REVERT_HEADER_REGEX: Regex = Regex::new(r#"^Revert "(?P<header>.+)"$"#).unwrap();
+  static ref REVERT_HASH_REGEX: Regex =
+    Regex::new(r"This reverts commit (?P<hash>[0-9a-fA-F]{7,40})").unwrap();
+  static ref FOOTER_REGEX: Regex =
+    Regex::new(r"^(?P<token>BREAKING CHANGE|BREAKING-CHANGE|[\w-]+)(?:: | #)(?P<value>.*)$")
+      .unwrap();
+}
+
+pub const BREAKING_CHANGE_TOKENS: [&str; 2] = ["BREAKING CHANGE", "BREAKING-CHANGE"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitFooter {
+  pub token: String,
+  pub value: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitRevert {
+  pub header: Option<String>,
+  pub hash: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConventionalCommit {
+  pub commit_type: String,
+  pub scope: Option<String>,
+  pub subject: String,
+  pub body: Option<String>,
+  pub footers: Vec<CommitFooter>,
+  pub breaking: bool,
+  pub breaking_notes: Vec<String>,
+  pub revert: Option<CommitRevert>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Commit {
+  pub hash: String,
+  pub message: String,
+  pub author_name: String,
+  pub author_email: String,
+  pub timestamp: i64,
+  pub conventional: Option<ConventionalCommit>,
+}
+
+impl Commit {
+  pub fn new(
+    hash: &str,
+    message: &str,
+    author_name: &str,
+    author_email: &str,
+    timestamp: i64,
+  ) -> Self {
+    Self {
+      hash: hash.to_string(),
+      message: message.to_string(),
+      author_name: author_name.to_string(),
+      author_email: author_email.to_string(),
+      timestamp,
+      conventional: parse(message),
+    }
+  }
+
+  pub fn short_hash(&self) -> &str {
+    &self.hash[..self.hash.len().min(7)]
+  }
+}
+
+//
+
+fn parse_footers(lines: &[&str]) -> Vec<CommitFooter> {
+  let mut footers: Vec<CommitFooter> = Vec::new();
+
+  for line in lines {
+    if let Some(captures) = FOOTER_REGEX.captures(line) {
+      footers.push(CommitFooter {
+        token: captures["token"].to_string(),
+        value: captures["value"].trim().to_string(),
+      });
+    } else if let Some(footer) = footers.last_mut() {
+      footer.value = format!("{}\n{}", footer.value, line.trim_end())
+        .trim()
+        .to_string();
+    }
+  }
+
+  footers
+}
+
+fn parse_revert(body: &str) -> Option<String> {
+  REVERT_HASH_REGEX
+    .captures(body)
+    .map(|v: regex::Captures| v["hash"].to_string())
+}
+
+pub fn parse(message: &str) -> Option<ConventionalCommit> {
+  let message: String = message.replace("\r\n", "\n");
+  let mut lines: std::str::Lines = message.lines();
+  let header: &str = lines.next()?.trim();
+  let rest: Vec<&str> = lines.collect();
+
+  // The body ends where the first footer paragraph starts
+  let paragraphs: Vec<Vec<&str>> = rest
+    .split(|v: &&str| v.trim().is_empty())
+    .filter(|v: &&[&str]| !v.is_empty())
+    .map(|v: &[&str]| v.to_vec())
+    .collect();
+  let footer_start: usize = paragraphs
+    .iter()
+    .position(|v: &Vec<&str>| FOOTER_REGEX.is_match(v[0]))
+    .unwrap_or(paragraphs.len());
+
+  let body: Option<String> = {
+    let value: String = paragraphs[..footer_start]
+      .iter()
+      .map(|v: &Vec<&str>| v.join("\n"))
+      .collect::<Vec<String>>()
+      .join("\n\n");
+    (!value.trim().is_empty()).then(|| value.trim().to_string())
+  };
+
+  let footer_lines: Vec<&str> = paragraphs[footer_start..]
+    .iter()
+    .flat_map(|v: &Vec<&str>| v.iter().copied())
+    .collect();
+  let footers: Vec<CommitFooter> = parse_footers(&footer_lines);
+
+  let breaking_notes: Vec<String> = footers
+    .iter()
+    .filter(|v: &&CommitFooter| BREAKING_CHANGE_TOKENS.contains(&v.token.as_str()))
+    .map(|v: &CommitFooter| v.value.clone())
+    .collect();
+
+  let revert_hash: Option<String> = body.as_deref().and_then(parse_revert);
+
+  if let Some(captures) = GIT_REVERT_HEADER_REGEX.captures(header) {
+    return Some(ConventionalCommit {
+      commit_type: "revert".into(),
+      scope: None,
+      subject: captures["header"].to_string(),
+      body,
+      footers,
+      breaking: false,
+      breaking_notes: Vec::new(),
+      revert: Some(CommitRevert {
+        header: Some(captures["header"].to_string()),
+        hash: revert_hash,
+      }),
+    });
+  }
+
+  let captures: regex::Captures = HEADER_REGEX.captures(header)?;
+  let commit_type: String = captures["type"].to_lowercase();
+  let subject: String = captures["subject"].trim().to_string();
+  let breaking: bool = captures.name("breaking").is_some() || !breaking_notes.is_empty();
+
+  let breaking_notes: Vec<String> = if breaking && breaking_notes.is_empty() {
+    // A '!' without a footer uses the subject as the description of the change
+    vec![subject.clone()]
+  } else {
+    breaking_notes
+  };
+
+  let revert: Option<CommitRevert> = (commit_type == "revert").then(|| CommitRevert {
+    header: Some(subject.clone()),
+    hash: revert_hash,
+  });
+
+  Some(ConventionalCommit {
+    commit_type,
+    scope: captures
+      .name("scope")
+      .map(|v: regex::Match| v.as_str().trim().to_string())
+      .filter(|v: &String| !v.is_empty()),
+    subject,
+    body,
+    footers,
+    breaking,
+    breaking_notes,
+    revert,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parses_the_header() {
+    let commit: ConventionalCommit = parse("feat(api): add the search").unwrap();
+
+    assert_eq!(commit.commit_type, "feat");
+    assert_eq!(commit.scope.as_deref(), Some("api"));
+    assert_eq!(commit.subject, "add the search");
+    assert_eq!(commit.body, None);
+    assert!(!commit.breaking);
+    assert!(commit.revert.is_none());
+  }
+
+  #[test]
+  fn ignores_the_messages_that_arent_conventional() {
+    assert!(parse("Add the search").is_none());
+    assert!(parse("feat:add the search").is_none());
+    assert!(parse("").is_none());
+  }
+
+  #[test]
+  fn separates_the_body_from_the_footers() {
+    let commit: ConventionalCommit = parse(
+      "fix: stop the crash\r\n\r\nThe cache was empty.\r\n\r\nIt's filled now.\r\n\r\nRefs #12\r\nReviewed-by: Tester\r\n  and Maintainer",
+    )
+    .unwrap();
+
+    assert_eq!(
+      commit.body.as_deref(),
+      Some("The cache was empty.\n\nIt's filled now.")
+    );
+    let footers: Vec<(&str, &str)> = commit
+      .footers
+      .iter()
+      .map(|v: &CommitFooter| (v.token.as_str(), v.value.as_str()))
+      .collect();
+    assert_eq!(
+      footers,
+      [("Refs", "12"), ("Reviewed-by", "Tester\n  and Maintainer")]
+    );
+  }
+
+  #[test]
+  fn parses_the_breaking_changes() {
+    let commit: ConventionalCommit =
+      parse("feat!: drop the old API\n\nBREAKING-CHANGE: use the new one").unwrap();
+    assert!(commit.breaking);
+    assert_eq!(commit.breaking_notes, ["use the new one"]);
+
+    let commit: ConventionalCommit = parse("refactor(core)!: rename the types").unwrap();
+    assert!(commit.breaking);
+    assert_eq!(commit.breaking_notes, ["rename the types"]);
+
+    let commit: ConventionalCommit =
+      parse("fix: change the format\n\nBREAKING CHANGE: the dates are in UTC").unwrap();
+    assert!(commit.breaking);
+    assert_eq!(commit.breaking_notes, ["the dates are in UTC"]);
+  }
+
+  #[test]
+  fn parses_the_reverts() {
+    let commit: ConventionalCommit = parse(
+      "Revert \"feat: add the search\"\n\nThis reverts commit 0123456789abcdef0123456789abcdef01234567.",
+    )
+    .unwrap();
+    assert_eq!(commit.commit_type, "revert");
+    assert_eq!(commit.subject, "feat: add the search");
+    let revert: CommitRevert = commit.revert.unwrap();
+    assert_eq!(revert.header.as_deref(), Some("feat: add the search"));
+    assert_eq!(
+      revert.hash.as_deref(),
+      Some("0123456789abcdef0123456789abcdef01234567")
+    );
+
+    let commit: ConventionalCommit =
+      parse("revert: add the search\n\nThis reverts commit 0123456.").unwrap();
+    assert_eq!(commit.revert.unwrap().hash.as_deref(), Some("0123456"));
+  }
+}
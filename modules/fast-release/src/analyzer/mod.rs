@@ -0,0 +1,240 @@
+// Copyright (c) Toolbi Software. All rights reserved.
+// Check the README file in the project root for more information.
+
+pub mod commit;
+pub mod rules;
+
+use crate::{
+  analyzer::{
+    commit::{Commit, ConventionalCommit},
+    rules::{ReleaseRule, ReleaseType},
+  },
+  error::FastReleaseError,
+  git,
+};
+use git2::{Diff, DiffOptions, Oid, Repository, Revwalk, Sort, Tree};
+use std::path::Path;
+use tracing::debug;
+
+// Merge commits are compared against their first parent, which is the branch they were merged into
+fn touches_path(
+  repo: &Repository,
+  commit: &git2::Commit,
+  path: &Path,
+) -> Result<bool, FastReleaseError> {
+  let tree: Tree = commit
+    .tree()
+    .map_err(|error: git2::Error| git::error("Failed to read a commit.", error))?;
+  let parent_tree: Option<Tree> = match commit.parent(0) {
+    Ok(parent) => Some(
+      parent
+        .tree()
+        .map_err(|error: git2::Error| git::error("Failed to read a commit.", error))?,
+    ),
+    Err(_) => None,
+  };
+
+  let mut options: DiffOptions = DiffOptions::new();
+  options.pathspec(path);
+
+  let diff: Diff = repo
+    .diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), Some(&mut options))
+    .map_err(|error: git2::Error| git::error("Failed to read the changes of a commit.", error))?;
+
+  Ok(diff.deltas().len() > 0)
+}
+
+// When a path is given, only the commits that change files inside it are returned. The path is
+// relative to the root of the repository.
+pub fn get_commits(
+  repo: &Repository,
+  head: &str,
+  since: Option<&str>,
+  path: Option<&Path>,
+) -> Result<Vec<Commit>, FastReleaseError> {
+  let mut revwalk: Revwalk = repo
+    .revwalk()
+    .map_err(|error: git2::Error| git::error("Failed to walk the commits.", error))?;
+  revwalk
+    .set_sorting(Sort::TOPOLOGICAL | Sort::TIME)
+    .and_then(|_| Oid::from_str(head))
+    .and_then(|v: Oid| revwalk.push(v))
+    .map_err(|error: git2::Error| git::error("Failed to walk the commits.", error))?;
+
+  if let Some(since) = since {
+    let oid: Oid = Oid::from_str(since)
+      .map_err(|error: git2::Error| git::error("Invalid commit hash.", error))?;
+    revwalk
+      .hide(oid)
+      .map_err(|error: git2::Error| git::error("Failed to walk the commits.", error))?;
+  }
+
+  let mut commits: Vec<Commit> = Vec::new();
+  for oid in revwalk {
+    let oid: Oid =
+      oid.map_err(|error: git2::Error| git::error("Failed to walk the commits.", error))?;
+    let commit: git2::Commit = repo
+      .find_commit(oid)
+      .map_err(|error: git2::Error| git::error("Failed to read a commit.", error))?;
+    if let Some(path) = path {
+      if !touches_path(repo, &commit, path)? {
+        continue;
+      }
+    }
+
+    let author: git2::Signature = commit.author();
+
+    commits.push(Commit::new(
+      &oid.to_string(),
+      commit.message().unwrap_or(""),
+      author.name().unwrap_or(""),
+      author.email().unwrap_or(""),
+      commit.time().seconds(),
+    ));
+  }
+
+  debug!(
+    message = format!("Found {} commits since the last release.", commits.len()),
+    category = "ANALYZER"
+  );
+
+  Ok(commits)
+}
+
+// A revert and the commit it reverts cancel each other out when both are part of the release
+pub fn filter_reverted(commits: &[Commit]) -> Vec<&Commit> {
+  let reverted: Vec<(&str, &str)> = commits
+    .iter()
+    .filter_map(|v: &Commit| {
+      let hash: &str = v.conventional.as_ref()?.revert.as_ref()?.hash.as_deref()?;
+      commits
+        .iter()
+        .any(|c: &Commit| c.hash.starts_with(hash))
+        .then_some((v.hash.as_str(), hash))
+    })
+    .collect();
+
+  commits
+    .iter()
+    .filter(|v: &&Commit| {
+      !reverted
+        .iter()
+        .any(|(revert, hash): &(&str, &str)| v.hash == *revert || v.hash.starts_with(hash))
+    })
+    .collect()
+}
+
+pub fn analyze(commits: &[Commit], rules: &[ReleaseRule]) -> ReleaseType {
+  let mut result: ReleaseType = ReleaseType::None;
+
+  for commit in filter_reverted(commits) {
+    let conventional: &ConventionalCommit = match &commit.conventional {
+      Some(conventional) => conventional,
+      None => {
+        debug!(
+          message = format!(
+            "Commit '{}' doesn't follow the Conventional Commits specification.",
+            commit.short_hash()
+          ),
+          category = "ANALYZER"
+        );
+        continue;
+      }
+    };
+
+    let release_type: ReleaseType = rules::get_release_type(conventional, rules);
+    debug!(
+      message = format!(
+        "Commit '{}' triggers a '{}' release.",
+        commit.short_hash(),
+        release_type
+      ),
+      category = "ANALYZER"
+    );
+
+    result = result.max(release_type);
+  }
+
+  result
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::TestRepo;
+
+  fn get_commit(hash: &str, message: &str) -> Commit {
+    Commit::new(hash, message, "Tester", "tester@example.com", 0)
+  }
+
+  #[test]
+  fn takes_the_highest_release_type() {
+    let commits: Vec<Commit> = vec![
+      get_commit("a", "fix: a"),
+      get_commit("b", "Update the readme"),
+      get_commit("c", "feat: b"),
+      get_commit("d", "docs: c"),
+    ];
+
+    assert_eq!(analyze(&commits, &[]), ReleaseType::Minor);
+    assert_eq!(analyze(&commits[1..2], &[]), ReleaseType::None);
+  }
+
+  #[test]
+  fn leaves_out_the_reverted_commits() {
+    let commits: Vec<Commit> = vec![
+      get_commit(
+        "b".repeat(40).as_str(),
+        "Revert \"feat: a\"\n\nThis reverts commit aaaaaaa.",
+      ),
+      get_commit("a".repeat(40).as_str(), "feat: a"),
+      get_commit("c".repeat(40).as_str(), "fix: b"),
+    ];
+
+    let remaining: Vec<&str> = filter_reverted(&commits)
+      .iter()
+      .map(|v: &&Commit| v.message.as_str())
+      .collect();
+    assert_eq!(remaining, ["fix: b"]);
+    assert_eq!(analyze(&commits, &[]), ReleaseType::Patch);
+
+    // A revert of a commit from an older release is a release of its own
+    assert_eq!(analyze(&commits[..1], &[]), ReleaseType::Patch);
+  }
+
+  #[test]
+  fn gets_the_commits_of_a_path() {
+    let test_repo: TestRepo = TestRepo::new();
+    test_repo.write("README.md", "");
+    let since: String = test_repo.commit("docs: add the readme");
+    test_repo.write("app/main.rs", "");
+    test_repo.commit("feat: add the app");
+    test_repo.write("lib/lib.rs", "");
+    test_repo.commit("feat: add the library");
+
+    let messages = |path: Option<&Path>, since: Option<&str>| -> Vec<String> {
+      get_commits(&test_repo.repo, &test_repo.head(), since, path)
+        .unwrap()
+        .into_iter()
+        .map(|v: Commit| v.message)
+        .collect()
+    };
+
+    assert_eq!(
+      messages(None, None),
+      [
+        "feat: add the library",
+        "feat: add the app",
+        "docs: add the readme"
+      ]
+    );
+    assert_eq!(
+      messages(None, Some(&since)),
+      ["feat: add the library", "feat: add the app"]
+    );
+    assert_eq!(
+      messages(Some(Path::new("app")), None),
+      ["feat: add the app"]
+    );
+  }
+}
@@ -0,0 +1,192 @@
+// Copyright (c) Toolbi Software. All rights reserved.
+// Check the README file in the project root for more information.
+
+use crate::analyzer::commit::ConventionalCommit;
+use glob::Pattern;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReleaseType {
+  None,
+  Patch,
+  Minor,
+  Major,
+}
+
+impl ReleaseType {
+  pub fn bump(&self, version: &Version) -> Option<Version> {
+    let mut version: Version = Version::new(version.major, version.minor, version.patch);
+
+    match self {
+      ReleaseType::None => return None,
+      ReleaseType::Patch => version.patch += 1,
+      ReleaseType::Minor => {
+        version.minor += 1;
+        version.patch = 0;
+      }
+      ReleaseType::Major => {
+        version.major += 1;
+        version.minor = 0;
+        version.patch = 0;
+      }
+    }
+
+    Some(version)
+  }
+}
+
+impl std::fmt::Display for ReleaseType {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let value: &str = match self {
+      ReleaseType::None => "none",
+      ReleaseType::Patch => "patch",
+      ReleaseType::Minor => "minor",
+      ReleaseType::Major => "major",
+    };
+
+    write!(f, "{}", value)
+  }
+}
+
+//
+
+#[derive(Debug, Clone)]
+pub struct ReleaseRule {
+  pub commit_type: Option<Pattern>,
+  pub scope: Option<Pattern>,
+  pub breaking: Option<bool>,
+  pub revert: Option<bool>,
+  pub release: ReleaseType,
+}
+
+impl ReleaseRule {
+  fn new(commit_type: Option<&str>, breaking: Option<bool>, release: ReleaseType) -> Self {
+    Self {
+      commit_type: commit_type.map(|v: &str| Pattern::new(v).unwrap()),
+      scope: None,
+      breaking,
+      revert: None,
+      release,
+    }
+  }
+
+  pub fn matches(&self, commit: &ConventionalCommit) -> bool {
+    let commit_type: bool = self
+      .commit_type
+      .as_ref()
+      .is_none_or(|v: &Pattern| v.matches(&commit.commit_type));
+    let scope: bool = self.scope.as_ref().is_none_or(|v: &Pattern| {
+      commit
+        .scope
+        .as_ref()
+        .is_some_and(|scope: &String| v.matches(scope))
+    });
+    let breaking: bool = self.breaking.is_none_or(|v: bool| v == commit.breaking);
+    let revert: bool = self
+      .revert
+      .is_none_or(|v: bool| v == commit.revert.is_some());
+
+    commit_type && scope && breaking && revert
+  }
+}
+
+pub fn default_rules() -> Vec<ReleaseRule> {
+  vec![
+    ReleaseRule::new(None, Some(true), ReleaseType::Major),
+    ReleaseRule::new(Some("feat"), None, ReleaseType::Minor),
+    ReleaseRule::new(Some("fix"), None, ReleaseType::Patch),
+    ReleaseRule::new(Some("perf"), None, ReleaseType::Patch),
+    ReleaseRule::new(Some("revert"), None, ReleaseType::Patch),
+  ]
+}
+
+fn highest_match(commit: &ConventionalCommit, rules: &[ReleaseRule]) -> Option<ReleaseType> {
+  rules
+    .iter()
+    .filter(|v: &&ReleaseRule| v.matches(commit))
+    .map(|v: &ReleaseRule| v.release)
+    .max()
+}
+
+// Custom rules take precedence over the default ones. If any of them matches a commit, the
+// default rules are ignored for that commit, which allows a custom rule to prevent a release.
+pub fn get_release_type(commit: &ConventionalCommit, rules: &[ReleaseRule]) -> ReleaseType {
+  highest_match(commit, rules)
+    .or_else(|| highest_match(commit, &default_rules()))
+    .unwrap_or(ReleaseType::None)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::analyzer::commit;
+
+  fn get_release_type_of(message: &str, rules: &[ReleaseRule]) -> ReleaseType {
+    get_release_type(&commit::parse(message).unwrap(), rules)
+  }
+
+  #[test]
+  fn bumps_the_version() {
+    let version: Version = Version::parse("1.2.3-beta.1").unwrap();
+
+    assert_eq!(ReleaseType::None.bump(&version), None);
+    assert_eq!(
+      ReleaseType::Patch.bump(&version),
+      Some(Version::new(1, 2, 4))
+    );
+    assert_eq!(
+      ReleaseType::Minor.bump(&version),
+      Some(Version::new(1, 3, 0))
+    );
+    assert_eq!(
+      ReleaseType::Major.bump(&version),
+      Some(Version::new(2, 0, 0))
+    );
+  }
+
+  #[test]
+  fn uses_the_default_rules() {
+    assert_eq!(get_release_type_of("feat: a", &[]), ReleaseType::Minor);
+    assert_eq!(get_release_type_of("fix: a", &[]), ReleaseType::Patch);
+    assert_eq!(get_release_type_of("perf: a", &[]), ReleaseType::Patch);
+    assert_eq!(get_release_type_of("revert: a", &[]), ReleaseType::Patch);
+    assert_eq!(get_release_type_of("docs: a", &[]), ReleaseType::None);
+    assert_eq!(get_release_type_of("docs!: a", &[]), ReleaseType::Major);
+  }
+
+  #[test]
+  fn prefers_the_custom_rules() {
+    let rules: Vec<ReleaseRule> = vec![
+      ReleaseRule {
+        commit_type: Some(Pattern::new("docs").unwrap()),
+        scope: Some(Pattern::new("readme*").unwrap()),
+        breaking: None,
+        revert: None,
+        release: ReleaseType::Patch,
+      },
+      ReleaseRule {
+        commit_type: Some(Pattern::new("feat").unwrap()),
+        scope: Some(Pattern::new("internal").unwrap()),
+        breaking: None,
+        revert: None,
+        release: ReleaseType::None,
+      },
+    ];
+
+    assert_eq!(
+      get_release_type_of("docs(readme-fr): a", &rules),
+      ReleaseType::Patch
+    );
+    assert_eq!(get_release_type_of("docs: a", &rules), ReleaseType::None);
+    assert_eq!(
+      get_release_type_of("feat(internal): a", &rules),
+      ReleaseType::None
+    );
+    assert_eq!(
+      get_release_type_of("feat(api): a", &rules),
+      ReleaseType::Minor
+    );
+  }
+}
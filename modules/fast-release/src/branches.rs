@@ -0,0 +1,492 @@
+// Copyright (c) Toolbi Software. All rights reserved.
+// Check the README file in the project root for more information.
+
+use crate::{
+  config::{Config, ConfigBranch, ConfigBranchPreRelease},
+  error::{FastReleaseError, FastReleaseErrorBuilder},
+  git::{self, repo::Tag},
+  tag_format::TagFormat,
+};
+use git2::Repository;
+use lazy_static::lazy_static;
+use regex::Regex;
+use semver::{Prerelease, Version};
+use tracing::debug;
+
+lazy_static! {
+  static ref MAINTENANCE_REGEX: Regex =
+    Regex::new(r"^(?P<major>\d+)\.(?:(?P<minor>\d+)\.x|x(?:\.x)?)$").unwrap();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BranchType {
+  Release,
+  Maintenance,
+  PreRelease,
+}
+
+// A configured branch resolved against an existing git branch
+#[derive(Debug, Clone)]
+pub struct Branch {
+  pub name: String,
+  pub branch_type: BranchType,
+  pub pre_release: Option<String>,
+  pub channel: Option<String>,
+  pub range: Option<BranchRange>,
+  pub tag_format: TagFormat,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BranchRange {
+  pub min: Version,
+  pub max: Option<Version>,
+}
+
+impl BranchRange {
+  // Parses the maintenance ranges like '1.x', '1.x.x' or '1.2.x'
+  pub fn parse(value: &str) -> Option<Self> {
+    let captures: regex::Captures = MAINTENANCE_REGEX.captures(value)?;
+    let major: u64 = captures["major"].parse().ok()?;
+
+    match captures.name("minor") {
+      Some(minor) => {
+        let minor: u64 = minor.as_str().parse().ok()?;
+        Some(Self {
+          min: Version::new(major, minor, 0),
+          max: Some(Version::new(major, minor + 1, 0)),
+        })
+      }
+      None => Some(Self {
+        min: Version::new(major, 0, 0),
+        max: Some(Version::new(major + 1, 0, 0)),
+      }),
+    }
+  }
+
+  pub fn contains(&self, version: &Version) -> bool {
+    let version: Version = Version::new(version.major, version.minor, version.patch);
+    version >= self.min && self.max.as_ref().is_none_or(|v: &Version| &version < v)
+  }
+
+  pub fn overlaps(&self, other: &BranchRange) -> bool {
+    let below: bool = self.max.as_ref().is_some_and(|v: &Version| v <= &other.min);
+    let above: bool = other.max.as_ref().is_some_and(|v: &Version| v <= &self.min);
+
+    !below && !above
+  }
+}
+
+impl std::fmt::Display for BranchRange {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match &self.max {
+      Some(max) => write!(f, ">={} <{}", self.min, max),
+      None => write!(f, ">={}", self.min),
+    }
+  }
+}
+
+//
+
+fn error(message: &str) -> FastReleaseError {
+  FastReleaseErrorBuilder::new(message)
+    .category("CONFIG")
+    .get()
+}
+
+pub fn is_valid_pre_release(identifier: &str) -> bool {
+  Prerelease::new(identifier).is_ok()
+    && !identifier.is_empty()
+    && !identifier.contains('.')
+    && !identifier.chars().all(|v: char| v.is_ascii_digit())
+}
+
+fn resolve(
+  config: &Config,
+  config_branch: &ConfigBranch,
+  name: &str,
+) -> Result<Branch, FastReleaseError> {
+  let pre_release: Option<String> = match &config_branch.pre_release {
+    Some(ConfigBranchPreRelease::BranchName) => Some(name.to_string()),
+    Some(ConfigBranchPreRelease::Identifier(identifier)) => Some(identifier.clone()),
+    None => None,
+  };
+
+  if let Some(identifier) = &pre_release {
+    if !is_valid_pre_release(identifier) {
+      return Err(error(&format!(
+        "The branch '{}' can't be used as a pre-release identifier. Set a 'prerelease' identifier for it.",
+        name
+      )));
+    }
+  }
+
+  // Branches named like a range are maintenance branches
+  let range: Option<BranchRange> = match (&pre_release, &config_branch.range) {
+    (Some(_), _) => None,
+    (None, Some(range)) => Some(range.clone()),
+    (None, None) => BranchRange::parse(name),
+  };
+
+  let branch_type: BranchType = if pre_release.is_some() {
+    BranchType::PreRelease
+  } else if range.is_some() {
+    BranchType::Maintenance
+  } else {
+    BranchType::Release
+  };
+
+  Ok(Branch {
+    name: name.to_string(),
+    branch_type,
+    pre_release,
+    channel: config_branch.channel.clone(),
+    range,
+    tag_format: config_branch
+      .tag_format
+      .clone()
+      .unwrap_or_else(|| config.tag_format.clone()),
+  })
+}
+
+fn validate(branches: &[Branch]) -> Result<(), FastReleaseError> {
+  if !branches
+    .iter()
+    .any(|v: &Branch| v.branch_type == BranchType::Release)
+  {
+    return Err(error(
+      "There are no release branches on the configuration file. There must be at least one that isn't a maintenance or a pre-release branch.",
+    ));
+  }
+
+  let maintenance: Vec<(&Branch, &BranchRange)> = branches
+    .iter()
+    .filter_map(|v: &Branch| v.range.as_ref().map(|range: &BranchRange| (v, range)))
+    .collect();
+  for (index, (branch, range)) in maintenance.iter().enumerate() {
+    for (other_branch, other_range) in &maintenance[(index + 1)..] {
+      if range.overlaps(other_range) {
+        return Err(error(&format!(
+          "The ranges of the maintenance branches '{}' ('{}') and '{}' ('{}') overlap.",
+          branch.name, range, other_branch.name, other_range
+        )));
+      }
+    }
+  }
+
+  let mut identifiers: Vec<&str> = Vec::new();
+  for branch in branches {
+    if let Some(identifier) = &branch.pre_release {
+      if identifiers.contains(&identifier.as_str()) {
+        return Err(error(&format!(
+          "The pre-release identifier '{}' is used by more than one branch.",
+          identifier
+        )));
+      }
+      identifiers.push(identifier);
+    }
+  }
+
+  Ok(())
+}
+
+// Expands the configured branches, including the name patterns, into the branches that exist on
+// the repository. The order of the configuration is kept.
+pub fn expand(config: &Config, names: &[String]) -> Result<Vec<Branch>, FastReleaseError> {
+  let mut result: Vec<Branch> = Vec::new();
+
+  for config_branch in &config.branches {
+    let matches: Vec<&str> = match &config_branch.pattern {
+      Some(pattern) => names
+        .iter()
+        .filter(|v: &&String| pattern.matches(v))
+        .map(|v: &String| v.as_str())
+        .collect(),
+      None => vec![config_branch.name.as_str()],
+    };
+
+    for name in matches {
+      if let Some(existing) = result.iter().find(|v: &&Branch| v.name == name) {
+        return Err(error(&format!(
+          "The branch '{}' matches more than one entry on the configuration file ('{}' and '{}').",
+          name, existing.name, config_branch.name
+        )));
+      }
+
+      result.push(resolve(config, config_branch, name)?);
+    }
+  }
+
+  // The first release branch publishes to the default channel, the rest of the branches
+  // publish to a channel named after them
+  let first_release: Option<String> = result
+    .iter()
+    .find(|v: &&Branch| v.branch_type == BranchType::Release)
+    .map(|v: &Branch| v.name.clone());
+  for branch in &mut result {
+    if branch.channel.is_none() && Some(&branch.name) != first_release.as_ref() {
+      branch.channel = Some(branch.name.clone());
+    }
+  }
+
+  validate(&result)?;
+
+  debug!(
+    message = format!(
+      "Releasing from the branches {}.",
+      result
+        .iter()
+        .map(|v: &Branch| format!("'{}'", v.name))
+        .collect::<Vec<String>>()
+        .join(", ")
+    ),
+    category = "BRANCHES"
+  );
+
+  Ok(result)
+}
+
+pub fn get_current<'a>(branches: &'a [Branch], name: &str) -> Option<&'a Branch> {
+  branches.iter().find(|v: &&Branch| v.name == name)
+}
+
+// Release branches can publish until the lowest version released on a later release branch, so
+// a branch like 'main' never publishes a version that 'next' has already published
+fn get_release_branch_max(
+  repo: &Repository,
+  remote: &str,
+  branches: &[Branch],
+  branch: &Branch,
+  head: &str,
+  project: Option<&str>,
+) -> Result<Option<Version>, FastReleaseError> {
+  let later: Vec<&Branch> = branches
+    .iter()
+    .filter(|v: &&Branch| v.branch_type == BranchType::Release)
+    .skip_while(|v: &&Branch| v.name != branch.name)
+    .skip(1)
+    .collect();
+  if later.is_empty() {
+    return Ok(None);
+  }
+
+  let tags: Vec<Tag> = git::repo::get_tags(repo)?;
+  let mut result: Option<Version> = None;
+
+  for later_branch in later {
+    let later_head: String = match git::branch::get_hash(repo, &later_branch.name, remote) {
+      Ok(hash) => hash,
+      Err(_) => {
+        debug!(
+          message = format!(
+            "The branch '{}' doesn't exist yet, so it doesn't limit the release range.",
+            later_branch.name
+          ),
+          category = "BRANCHES"
+        );
+        continue;
+      }
+    };
+
+    for tag in &tags {
+      let version: Version = match later_branch.tag_format.parse(&tag.name, project) {
+        Some(version) if version.pre.is_empty() => version,
+        _ => continue,
+      };
+
+      if git::repo::is_ancestor(repo, &tag.hash, &later_head)
+        && !git::repo::is_ancestor(repo, &tag.hash, head)
+        && result.as_ref().is_none_or(|v: &Version| &version < v)
+      {
+        result = Some(version);
+      }
+    }
+  }
+
+  Ok(result)
+}
+
+pub fn get_range(
+  repo: &Repository,
+  remote: &str,
+  branches: &[Branch],
+  branch: &Branch,
+  head: &str,
+  project: Option<&str>,
+  last_version: Option<&Version>,
+) -> Result<Option<BranchRange>, FastReleaseError> {
+  match branch.branch_type {
+    BranchType::Maintenance => Ok(branch.range.clone()),
+    BranchType::PreRelease => Ok(None),
+    BranchType::Release => {
+      let max: Option<Version> =
+        get_release_branch_max(repo, remote, branches, branch, head, project)?;
+      let min: Version = last_version
+        .map(|v: &Version| Version::new(v.major, v.minor, v.patch))
+        .unwrap_or_else(|| Version::new(0, 0, 0));
+
+      Ok(max.map(|max: Version| BranchRange {
+        min,
+        max: Some(max),
+      }))
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    config::{self, Config},
+    testing,
+  };
+
+  // Some of the invalid branches are already rejected while reading the configuration
+  fn expand_branches(branches: &str, names: &[&str]) -> Result<Vec<Branch>, FastReleaseError> {
+    let config: Config = config::parse(&format!(
+      "
+version: 1
+tag_format: v${{version}}
+branches: {}
+projects:
+  - name: app
+    path: .
+",
+      branches
+    ))?;
+    let names: Vec<String> = names.iter().map(|v: &&str| v.to_string()).collect();
+
+    expand(&config, &names)
+  }
+
+  #[test]
+  fn parses_the_maintenance_ranges() {
+    let range: BranchRange = BranchRange::parse("1.x").unwrap();
+    assert_eq!(range.to_string(), ">=1.0.0 <2.0.0");
+    assert_eq!(BranchRange::parse("1.x.x"), Some(range));
+    assert_eq!(
+      BranchRange::parse("1.2.x").unwrap().to_string(),
+      ">=1.2.0 <1.3.0"
+    );
+
+    assert_eq!(BranchRange::parse("1.2.3"), None);
+    assert_eq!(BranchRange::parse("x"), None);
+    assert_eq!(BranchRange::parse("main"), None);
+  }
+
+  #[test]
+  fn compares_the_ranges() {
+    let range: BranchRange = BranchRange::parse("1.2.x").unwrap();
+    assert!(range.contains(&Version::parse("1.2.9-beta.1").unwrap()));
+    assert!(!range.contains(&Version::new(1, 3, 0)));
+    assert!(!range.contains(&Version::new(1, 1, 9)));
+
+    assert!(range.overlaps(&BranchRange::parse("1.x").unwrap()));
+    assert!(!range.overlaps(&BranchRange::parse("1.3.x").unwrap()));
+    assert!(BranchRange {
+      min: Version::new(1, 0, 0),
+      max: None,
+    }
+    .overlaps(&range));
+  }
+
+  #[test]
+  fn resolves_the_types_and_the_channels() {
+    let branches: Vec<Branch> = expand_branches(
+      "[main, next, '[0-9].x', { name: beta, prerelease: true }, { name: alpha, prerelease: preview, channel: early }]",
+      &["main", "next", "1.x", "2.x", "feature"],
+    )
+    .unwrap();
+
+    let summary: Vec<(&str, BranchType, Option<&str>, Option<&str>)> = branches
+      .iter()
+      .map(|v: &Branch| {
+        (
+          v.name.as_str(),
+          v.branch_type,
+          v.pre_release.as_deref(),
+          v.channel.as_deref(),
+        )
+      })
+      .collect();
+    assert_eq!(
+      summary,
+      [
+        ("main", BranchType::Release, None, None),
+        ("next", BranchType::Release, None, Some("next")),
+        ("1.x", BranchType::Maintenance, None, Some("1.x")),
+        ("2.x", BranchType::Maintenance, None, Some("2.x")),
+        ("beta", BranchType::PreRelease, Some("beta"), Some("beta")),
+        (
+          "alpha",
+          BranchType::PreRelease,
+          Some("preview"),
+          Some("early")
+        ),
+      ]
+    );
+    assert_eq!(branches[2].range, Some(BranchRange::parse("1.x").unwrap()));
+  }
+
+  #[test]
+  fn rejects_the_invalid_branches() {
+    assert!(expand_branches("['1.x']", &[]).is_err());
+    assert!(expand_branches("[main, '1.x', '1.2.x']", &[]).is_err());
+    assert!(expand_branches("[main, '*']", &["main"]).is_err());
+    assert!(expand_branches(
+      "[main, { name: beta, prerelease: rc }, { name: rc, prerelease: true }]",
+      &[]
+    )
+    .is_err());
+    assert!(expand_branches("[main, { name: '2024', prerelease: true }]", &[]).is_err());
+  }
+
+  // The version 1.1.0 is on 'next', so 'main' can release up to it
+  #[test]
+  fn limits_the_range_of_a_release_branch() {
+    let test_repo: testing::TestRepo = testing::TestRepo::new();
+    test_repo.commit("feat: a");
+    let main: String = test_repo.head();
+    test_repo
+      .repo
+      .tag_lightweight(
+        "v1.0.0",
+        &test_repo.repo.revparse_single("HEAD").unwrap(),
+        false,
+      )
+      .unwrap();
+    test_repo.commit("feat: b");
+    test_repo
+      .repo
+      .tag_lightweight(
+        "v1.1.0",
+        &test_repo.repo.revparse_single("HEAD").unwrap(),
+        false,
+      )
+      .unwrap();
+    let next: git2::Commit = test_repo.repo.head().unwrap().peel_to_commit().unwrap();
+    test_repo.repo.branch("next", &next, false).unwrap();
+
+    let branches: Vec<Branch> = expand_branches("[main, next]", &[]).unwrap();
+    let range = |branch: &Branch, head: &str| -> Option<BranchRange> {
+      get_range(
+        &test_repo.repo,
+        "origin",
+        &branches,
+        branch,
+        head,
+        None,
+        Some(&Version::new(1, 0, 0)),
+      )
+      .unwrap()
+    };
+
+    assert_eq!(
+      range(&branches[0], &main),
+      Some(BranchRange {
+        min: Version::new(1, 0, 0),
+        max: Some(Version::new(1, 1, 0)),
+      })
+    );
+    assert_eq!(range(&branches[1], &next.id().to_string()), None);
+  }
+}
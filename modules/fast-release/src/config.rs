@@ -1,47 +1,167 @@
 // Copyright (c) Toolbi Software. All rights reserved.
 // Check the README file in the project root for more information.
 
-// TODO Put the configuration for the modules inside the own modules
-
 use crate::{
+  analyzer::rules::{ReleaseRule, ReleaseType},
+  branches::{self, BranchRange},
   cli::CliParams,
-  constants::{CONFIG_FILE_EXT, CONFIG_FILE_NAME, CONFIG_VERSION},
+  constants::{
+    CONFIG_FILE_EXT, CONFIG_FILE_NAME, CONFIG_VERSION, DEFAULT_REMOTE, DEFAULT_TAG_MESSAGE,
+  },
+  dependencies::{self, CascadeRule, DependencyGraph},
   error::{FastReleaseError, FastReleaseErrorBuilder},
+  tag_format::TagFormat,
 };
+use glob::Pattern;
 use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
 use std::{
-  collections::HashMap,
   fs::{self, File, OpenOptions},
-  io::Read,
+  io::{self, Read},
   path::{Path, PathBuf},
 };
 use tracing::{debug, warn};
 
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum ConfigFilePreRelease {
+  Enabled(bool),
+  Identifier(String),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 struct ConfigFileBranch {
-  pre_release: Option<bool>,
+  name: String,
+  #[serde(alias = "pre_release")]
+  prerelease: Option<ConfigFilePreRelease>,
+  channel: Option<String>,
+  range: Option<String>,
+  tag_format: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(untagged)]
 enum ConfigBranchEnum {
   Simple(String),
-  WithProperties(HashMap<String, Vec<ConfigFileBranch>>),
+  WithProperties(ConfigFileBranch),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigBranchPreRelease {
+  BranchName,
+  Identifier(String),
 }
 
 #[derive(Debug, Clone)]
 pub struct ConfigBranch {
   pub name: String,
-  pub pre_release: bool,
+  pub pattern: Option<Pattern>,
+  pub pre_release: Option<ConfigBranchPreRelease>,
+  pub channel: Option<String>,
+  pub range: Option<BranchRange>,
+  pub tag_format: Option<TagFormat>,
+}
+
+//
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+enum ConfigFileModule {
+  Simple(String),
+  WithOptions(Mapping),
+}
+
+// The options of a module are kept as they are, each module deserializes its own ones. Modules
+// with a 'plugin' path run an external executable instead of a built-in module.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "ConfigFileModule")]
+pub struct ConfigModule {
+  pub name: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub plugin: Option<String>,
+  #[serde(flatten)]
+  pub options: Mapping,
+}
+
+impl TryFrom<ConfigFileModule> for ConfigModule {
+  type Error = String;
+
+  fn try_from(value: ConfigFileModule) -> Result<Self, Self::Error> {
+    match value {
+      ConfigFileModule::Simple(name) => Ok(Self {
+        name,
+        plugin: None,
+        options: Mapping::new(),
+      }),
+      ConfigFileModule::WithOptions(mut options) => {
+        let name: String = match options.remove("name") {
+          Some(Value::String(name)) => name,
+          _ => return Err("There is a module without a name.".into()),
+        };
+        let plugin: Option<String> = match options.remove("plugin") {
+          Some(Value::String(plugin)) => Some(plugin),
+          Some(_) => {
+            return Err(format!(
+              "The plugin path of the module '{}' must be a string.",
+              name
+            ))
+          }
+          None => None,
+        };
+
+        Ok(Self {
+          name,
+          plugin,
+          options,
+        })
+      }
+    }
+  }
 }
 
 //
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConfigProject {
-  name: String,
-  path: String,
-  modules: Vec<String>,
+  pub name: String,
+  pub path: String,
+  #[serde(default)]
+  pub modules: Vec<ConfigModule>,
+  #[serde(default)]
+  pub dependencies: Vec<String>,
+  pub cascade: Option<CascadeRule>,
+}
+
+//
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ConfigFileReleaseRule {
+  #[serde(rename = "type")]
+  commit_type: Option<String>,
+  scope: Option<String>,
+  breaking: Option<bool>,
+  revert: Option<bool>,
+  release: ReleaseType,
+}
+
+//
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFileGit {
+  remote: Option<String>,
+  push: Option<bool>,
+  annotated: Option<bool>,
+  tag_message: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConfigGit {
+  pub remote: String,
+  pub push: bool,
+  // Tags with a message are annotated, the rest are lightweight
+  pub tag_message: Option<String>,
 }
 
 //
@@ -50,18 +170,27 @@ pub struct ConfigProject {
 struct ConfigFile {
   version: u8,
   tag_format: String,
-  modules: Vec<String>,
+  #[serde(default)]
+  modules: Vec<ConfigModule>,
   branches: Vec<ConfigBranchEnum>,
   projects: Vec<ConfigProject>,
+  release_rules: Option<Vec<ConfigFileReleaseRule>>,
+  cascade: Option<CascadeRule>,
+  git: Option<ConfigFileGit>,
+  rollback: Option<bool>,
 }
 
 #[derive(Debug, Clone)]
 pub struct Config {
-  pub version: u8,
-  pub tag_format: String,
-  pub modules: Vec<String>,
+  pub tag_format: TagFormat,
+  pub modules: Vec<ConfigModule>,
   pub branches: Vec<ConfigBranch>,
   pub projects: Vec<ConfigProject>,
+  pub release_rules: Vec<ReleaseRule>,
+  pub cascade: CascadeRule,
+  pub git: ConfigGit,
+  // Releases that fail on the 'publish' step are taken back, instead of being resumed
+  pub rollback: bool,
   pub dry_run: bool,
 }
 
@@ -83,7 +212,7 @@ fn find_file(base_path: &Path) -> Option<PathBuf> {
         if files_found == 0 {
           file = Some((file_name, path));
         }
-        files_found = files_found + 1;
+        files_found += 1;
       }
     }
   }
@@ -127,7 +256,7 @@ pub fn get_file(file_path: &Option<String>) -> Result<PathBuf, FastReleaseError>
           category = "CONFIG"
         );
 
-        let path: PathBuf = base_path.join(&file_path);
+        let path: PathBuf = base_path.join(file_path);
         if fs::metadata(&path).is_ok() {
           debug!(
             message = format!("Using the custom configuration file '{}'.", &file_path),
@@ -180,114 +309,388 @@ fn read_file(file_path: PathBuf) -> Result<ConfigFile, FastReleaseError> {
     }
   };
 
-  let content: String = {
-    let mut data: String = String::new();
-    let _ = file.read_to_string(&mut data);
-    data
-  };
-
-  let parse: ConfigFile = match serde_yaml::from_str(&content) {
-    Ok(content) => content,
-    Err(_) => {
-      return Err(
-        FastReleaseErrorBuilder::new(
-          "Failed to parse the configuration file. The file might be wrongly formatted.",
-        )
+  let mut content: String = String::new();
+  if let Err(error) = file.read_to_string(&mut content) {
+    return Err(
+      FastReleaseErrorBuilder::new("Failed to read the configuration file.")
         .category("CONFIG")
+        .error(error)
         .get(),
-      );
-    }
-  };
+    );
+  }
+
+  parse_file(&content)
+}
+
+// The error tells where the file is wrong, when serde knows it
+fn parse_file(content: &str) -> Result<ConfigFile, FastReleaseError> {
+  serde_yaml::from_str(content).map_err(|v: serde_yaml::Error| {
+    let message: String = match v.location() {
+      Some(location) => format!(
+        "Failed to parse the configuration file on the line {}, column {}.",
+        location.line(),
+        location.column()
+      ),
+      None => "Failed to parse the configuration file.".to_string(),
+    };
 
-  Ok(parse)
+    FastReleaseErrorBuilder::new(&message)
+      .category("CONFIG")
+      .error(io::Error::other(v))
+      .get()
+  })
 }
 
 fn validate_and_transform_config(
   file_config: ConfigFile,
   cli_params: &CliParams,
 ) -> Result<Config, FastReleaseError> {
-  fn version(version: u8) -> u8 {
+  fn version(version: u8) {
     if version != CONFIG_VERSION {
       // TODO Handle outdated configuration files
       warn!(
-        message = "The configuration file is on version '{}'. It should be on version '{}'.",
+        message = format!(
+          "The configuration file is on version '{}'. It should be on version '{}'.",
+          version, CONFIG_VERSION
+        ),
         category = "CONFIG"
       )
     }
-
-    version
   }
 
-  // TODO Handle tag format
-  fn tag_format(tag_format: String) -> String {
-    tag_format
+  fn tag_format(tag_format: String) -> Result<TagFormat, FastReleaseError> {
+    TagFormat::new(&tag_format)
   }
 
-  // TODO Handle modules
-  fn modules(modules: Vec<String>) -> Vec<String> {
-    modules
+  fn modules(
+    modules: Vec<ConfigModule>,
+    projects: &[ConfigProject],
+  ) -> Result<Vec<ConfigModule>, FastReleaseError> {
+    for module in &modules {
+      crate::modules::validate(module)?;
+    }
+    for project in projects {
+      for module in &project.modules {
+        crate::modules::validate(module)?;
+      }
+    }
+
+    Ok(modules)
   }
 
   fn branches(branches: Vec<ConfigBranchEnum>) -> Result<Vec<ConfigBranch>, FastReleaseError> {
-    if branches.len() == 0 {
-      return Err(
-        FastReleaseErrorBuilder::new(
-          "There are no branches on the configuration file. There must be at least one.",
-        )
+    fn error(message: &str) -> FastReleaseError {
+      FastReleaseErrorBuilder::new(message)
         .category("CONFIG")
-        .get(),
-      );
+        .get()
+    }
+
+    fn branch(properties: ConfigFileBranch) -> Result<ConfigBranch, FastReleaseError> {
+      let name: String = properties.name.trim().to_string();
+      if name.is_empty() {
+        return Err(error("There is a branch without a name."));
+      }
+
+      let pattern: Option<Pattern> = if name.contains(['*', '?', '[']) {
+        match Pattern::new(&name) {
+          Ok(pattern) => Some(pattern),
+          Err(_) => {
+            return Err(error(&format!(
+              "The branch name pattern '{}' is not valid.",
+              name
+            )))
+          }
+        }
+      } else {
+        None
+      };
+
+      let pre_release: Option<ConfigBranchPreRelease> = match properties.prerelease {
+        Some(ConfigFilePreRelease::Enabled(true)) => Some(ConfigBranchPreRelease::BranchName),
+        Some(ConfigFilePreRelease::Identifier(identifier)) => {
+          if !branches::is_valid_pre_release(&identifier) {
+            return Err(error(&format!(
+              "The pre-release identifier '{}' of the branch '{}' is not valid. It must be a non-numeric SemVer identifier.",
+              identifier, name
+            )));
+          }
+          Some(ConfigBranchPreRelease::Identifier(identifier))
+        }
+        _ => None,
+      };
+
+      let range: Option<BranchRange> = match properties.range {
+        Some(range) => match BranchRange::parse(&range) {
+          Some(range) => Some(range),
+          None => {
+            return Err(error(&format!(
+              "The range '{}' of the branch '{}' is not valid. It must look like '1.x' or '1.2.x'.",
+              range, name
+            )))
+          }
+        },
+        None => None,
+      };
+
+      if pre_release.is_some() && range.is_some() {
+        return Err(error(&format!(
+          "The branch '{}' can't be a pre-release and a maintenance branch at the same time.",
+          name
+        )));
+      }
+
+      let tag_format: Option<TagFormat> = match properties.tag_format {
+        Some(tag_format) => Some(TagFormat::new(&tag_format)?),
+        None => None,
+      };
+
+      Ok(ConfigBranch {
+        name,
+        pattern,
+        pre_release,
+        channel: properties.channel,
+        range,
+        tag_format,
+      })
+    }
+
+    if branches.is_empty() {
+      return Err(error(
+        "There are no branches on the configuration file. There must be at least one.",
+      ));
     }
 
     let mut result: Vec<ConfigBranch> = Vec::new();
-    for branch in branches {
-      match branch {
-        ConfigBranchEnum::Simple(name) => result.push(ConfigBranch {
+    for branch_entry in branches {
+      let properties: ConfigFileBranch = match branch_entry {
+        ConfigBranchEnum::Simple(name) => ConfigFileBranch {
           name,
-          pre_release: false,
-        }),
-        ConfigBranchEnum::WithProperties(properties) => {
-          for (name, branches) in properties {
-            for branch in branches {
-              result.push(ConfigBranch {
-                name,
-                pre_release: branch.pre_release.unwrap_or(false),
-              });
-              break;
-            }
-            break;
-          }
+          prerelease: None,
+          channel: None,
+          range: None,
+          tag_format: None,
+        },
+        ConfigBranchEnum::WithProperties(properties) => properties,
+      };
+      let branch: ConfigBranch = branch(properties)?;
+
+      if result.iter().any(|v: &ConfigBranch| v.name == branch.name) {
+        return Err(error(&format!(
+          "The branch '{}' is defined more than once on the configuration file.",
+          branch.name
+        )));
+      }
+
+      result.push(branch);
+    }
+
+    Ok(result)
+  }
+
+  fn projects(
+    projects: Vec<ConfigProject>,
+    tag_format: &TagFormat,
+    branches: &[ConfigBranch],
+  ) -> Result<Vec<ConfigProject>, FastReleaseError> {
+    fn error(message: &str) -> FastReleaseError {
+      FastReleaseErrorBuilder::new(message)
+        .category("CONFIG")
+        .get()
+    }
+
+    if projects.is_empty() {
+      return Err(error(
+        "There are no projects on the configuration file. There must be at least one.",
+      ));
+    }
+
+    // Every project needs its own tags when there's more than one
+    if projects.len() > 1 {
+      let tag_formats: Vec<&TagFormat> = std::iter::once(tag_format)
+        .chain(
+          branches
+            .iter()
+            .filter_map(|v: &ConfigBranch| v.tag_format.as_ref()),
+        )
+        .collect();
+      if let Some(tag_format) = tag_formats.iter().find(|v: &&&TagFormat| !v.has_project()) {
+        return Err(error(&format!(
+          "The tag format '{}' must contain the '${{project}}' placeholder when there's more than one project.",
+          tag_format
+        )));
+      }
+    }
+
+    let mut result: Vec<ConfigProject> = Vec::new();
+    for project in projects {
+      let name: String = project.name.trim().to_string();
+      if name.is_empty() {
+        return Err(error("There is a project without a name."));
+      }
+      if result.iter().any(|v: &ConfigProject| v.name == name) {
+        return Err(error(&format!(
+          "The project '{}' is defined more than once on the configuration file.",
+          name
+        )));
+      }
+
+      let path: String = {
+        let path: &str = project.path.trim();
+        let path: &str = path.strip_prefix("./").unwrap_or(path);
+        let path: &str = path.trim_end_matches('/');
+        if path.is_empty() { "." } else { path }.to_string()
+      };
+      if Path::new(&path).is_absolute() || path.split('/').any(|v: &str| v == "..") {
+        return Err(error(&format!(
+          "The path '{}' of the project '{}' must be relative to the root of the repository.",
+          project.path, name
+        )));
+      }
+
+      result.push(ConfigProject {
+        name,
+        path,
+        modules: project.modules,
+        dependencies: project.dependencies,
+        cascade: project.cascade,
+      });
+    }
+
+    for project in &result {
+      for dependency in &project.dependencies {
+        if dependency == &project.name {
+          return Err(error(&format!(
+            "The project '{}' can't depend on itself.",
+            project.name
+          )));
+        }
+        if !result.iter().any(|v: &ConfigProject| &v.name == dependency) {
+          return Err(error(&format!(
+            "The project '{}' depends on the project '{}', which doesn't exist.",
+            project.name, dependency
+          )));
         }
       }
     }
 
+    let graph: DependencyGraph = dependencies::get_graph(None, &result)?;
+    dependencies::sort(&result, &graph)?;
+
     Ok(result)
   }
 
-  fn projects(projects: Vec<ConfigProject>) -> Result<Vec<ConfigProject>, FastReleaseError> {
-    if projects.len() == 0 {
+  fn release_rules(
+    release_rules: Option<Vec<ConfigFileReleaseRule>>,
+  ) -> Result<Vec<ReleaseRule>, FastReleaseError> {
+    fn pattern(value: Option<String>) -> Result<Option<Pattern>, FastReleaseError> {
+      match value {
+        Some(value) => match Pattern::new(&value) {
+          Ok(pattern) => Ok(Some(pattern)),
+          Err(_) => Err(
+            FastReleaseErrorBuilder::new(&format!(
+              "The release rule pattern '{}' is not valid.",
+              value
+            ))
+            .category("CONFIG")
+            .get(),
+          ),
+        },
+        None => Ok(None),
+      }
+    }
+
+    let mut result: Vec<ReleaseRule> = Vec::new();
+    for rule in release_rules.unwrap_or_default() {
+      if rule.commit_type.is_none()
+        && rule.scope.is_none()
+        && rule.breaking.is_none()
+        && rule.revert.is_none()
+      {
+        return Err(
+          FastReleaseErrorBuilder::new(
+            "There is a release rule without conditions. It must have at least one of 'type', 'scope', 'breaking' or 'revert'.",
+          )
+          .category("CONFIG")
+          .get(),
+        );
+      }
+
+      result.push(ReleaseRule {
+        commit_type: pattern(rule.commit_type)?,
+        scope: pattern(rule.scope)?,
+        breaking: rule.breaking,
+        revert: rule.revert,
+        release: rule.release,
+      });
+    }
+
+    Ok(result)
+  }
+
+  fn cascade(cascade: Option<CascadeRule>) -> CascadeRule {
+    cascade.unwrap_or(CascadeRule::Patch)
+  }
+
+  fn git(git: Option<ConfigFileGit>) -> Result<ConfigGit, FastReleaseError> {
+    let git: ConfigFileGit = git.unwrap_or(ConfigFileGit {
+      remote: None,
+      push: None,
+      annotated: None,
+      tag_message: None,
+    });
+
+    let remote: String = git.remote.unwrap_or_else(|| DEFAULT_REMOTE.to_string());
+    if remote.trim().is_empty() {
       return Err(
-        FastReleaseErrorBuilder::new(
-          "There are no projects on the configuration file. There must be at least one.",
-        )
-        .category("CONFIG")
-        .get(),
+        FastReleaseErrorBuilder::new("The git remote can't be empty.")
+          .category("CONFIG")
+          .get(),
       );
     }
 
-    Ok(projects)
+    let tag_message: Option<String> = match (git.annotated, git.tag_message) {
+      (Some(false), Some(_)) => {
+        return Err(
+          FastReleaseErrorBuilder::new(
+            "The tag message can only be set when the tags are annotated.",
+          )
+          .category("CONFIG")
+          .get(),
+        )
+      }
+      (Some(true), None) => Some(DEFAULT_TAG_MESSAGE.to_string()),
+      (_, tag_message) => tag_message,
+    };
+
+    Ok(ConfigGit {
+      remote,
+      push: git.push.unwrap_or(true),
+      tag_message,
+    })
+  }
+
+  fn rollback(rollback: Option<bool>) -> bool {
+    rollback.unwrap_or(false)
   }
 
   fn dry_run(dry_run: bool) -> bool {
     dry_run
   }
 
+  version(file_config.version);
+  let tag_format: TagFormat = tag_format(file_config.tag_format)?;
+  let branches: Vec<ConfigBranch> = branches(file_config.branches)?;
+  let projects: Vec<ConfigProject> = projects(file_config.projects, &tag_format, &branches)?;
+
   Ok(Config {
-    version: version(file_config.version),
-    tag_format: tag_format(file_config.tag_format),
-    modules: modules(file_config.modules),
-    branches: branches(file_config.branches)?,
-    projects: projects(file_config.projects)?,
+    tag_format,
+    modules: modules(file_config.modules, &projects)?,
+    branches,
+    projects,
+    release_rules: release_rules(file_config.release_rules)?,
+    cascade: cascade(file_config.cascade),
+    git: git(file_config.git)?,
+    rollback: rollback(file_config.rollback),
     dry_run: dry_run(cli_params.dry_run),
   })
 }
@@ -299,3 +702,34 @@ pub fn get(cli_params: &CliParams) -> Result<Config, FastReleaseError> {
 
   Ok(config)
 }
+
+#[cfg(test)]
+pub fn parse(content: &str) -> Result<Config, FastReleaseError> {
+  let file_config: ConfigFile = parse_file(content)?;
+
+  validate_and_transform_config(
+    file_config,
+    &CliParams {
+      config_file_path: None,
+      dry_run: false,
+    },
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn tells_where_the_configuration_file_is_wrong() {
+    let error: FastReleaseError = parse_file("version: 1\nbranches: main\n").unwrap_err();
+
+    assert_eq!(
+      error.message,
+      "Failed to parse the configuration file on the line 2, column 11."
+    );
+    assert!(error
+      .error
+      .is_some_and(|v: io::Error| v.to_string().contains("branches: invalid type")));
+  }
+}
@@ -4,3 +4,12 @@
 pub const CONFIG_VERSION: u8 = 1;
 pub const CONFIG_FILE_NAME: [&str; 2] = [".fast-release", "fast-release"];
 pub const CONFIG_FILE_EXT: [&str; 2] = ["yml", "yaml"];
+pub const FIRST_RELEASE_VERSION: &str = "1.0.0";
+pub const DEFAULT_REMOTE: &str = "origin";
+pub const DEFAULT_TAG_MESSAGE: &str = "Release ${tag}";
+pub const DEFAULT_GIT_NAME: &str = "FastRelease";
+pub const DEFAULT_GIT_EMAIL: &str = "fast-release@users.noreply.github.com";
+// The git notes that keep the channels of every release, and the journal of the incomplete ones
+pub const NOTES_REF: &str = "refs/notes/fast-release";
+// The notes of the remote, which are merged into the local ones
+pub const NOTES_REMOTE_REF: &str = "refs/notes/fast-release-remote";
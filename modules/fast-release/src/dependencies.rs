@@ -0,0 +1,303 @@
+// Copyright (c) Toolbi Software. All rights reserved.
+// Check the README file in the project root for more information.
+
+use crate::{
+  analyzer::rules::ReleaseType,
+  config::ConfigProject,
+  error::{FastReleaseError, FastReleaseErrorBuilder},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+  collections::HashMap,
+  fs,
+  path::{Component, Path, PathBuf},
+};
+use toml::{Table, Value};
+use tracing::debug;
+
+const CARGO_DEPENDENCY_TABLES: [&str; 2] = ["dependencies", "build-dependencies"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CascadeRule {
+  None,
+  Patch,
+  Match,
+}
+
+impl CascadeRule {
+  pub fn apply(&self, release_type: ReleaseType) -> ReleaseType {
+    match (self, release_type) {
+      (_, ReleaseType::None) | (CascadeRule::None, _) => ReleaseType::None,
+      (CascadeRule::Patch, _) => ReleaseType::Patch,
+      (CascadeRule::Match, release_type) => release_type,
+    }
+  }
+}
+
+// Project name -> names of the projects it depends on
+pub type DependencyGraph = HashMap<String, Vec<String>>;
+
+fn normalize(path: &Path) -> PathBuf {
+  let mut result: PathBuf = PathBuf::new();
+  for component in path.components() {
+    match component {
+      Component::CurDir => {}
+      Component::ParentDir => {
+        result.pop();
+      }
+      component => result.push(component),
+    }
+  }
+
+  result
+}
+
+fn get_cargo_path_dependencies(manifest: &Table) -> Vec<String> {
+  let mut tables: Vec<&Table> = CARGO_DEPENDENCY_TABLES
+    .iter()
+    .filter_map(|v: &&str| manifest.get(*v).and_then(|v: &Value| v.as_table()))
+    .collect();
+
+  if let Some(targets) = manifest.get("target").and_then(|v: &Value| v.as_table()) {
+    for target in targets.values().filter_map(|v: &Value| v.as_table()) {
+      tables.extend(
+        CARGO_DEPENDENCY_TABLES
+          .iter()
+          .filter_map(|v: &&str| target.get(*v).and_then(|v: &Value| v.as_table())),
+      );
+    }
+  }
+
+  tables
+    .iter()
+    .flat_map(|v: &&Table| v.values())
+    .filter_map(|v: &Value| v.get("path").and_then(|v: &Value| v.as_str()))
+    .map(|v: &str| v.to_string())
+    .collect()
+}
+
+// Reads the path dependencies of the Cargo manifest of a project, returning the names of the
+// projects they point to
+fn infer_cargo_dependencies(
+  root: &Path,
+  project: &ConfigProject,
+  projects: &[ConfigProject],
+) -> Result<Vec<String>, FastReleaseError> {
+  let project_path: PathBuf = normalize(Path::new(&project.path));
+  let manifest_path: PathBuf = root.join(&project_path).join("Cargo.toml");
+
+  let content: String = match fs::read_to_string(&manifest_path) {
+    Ok(content) => content,
+    Err(_) => return Ok(Vec::new()),
+  };
+
+  let manifest: Table = match content.parse::<Table>() {
+    Ok(manifest) => manifest,
+    Err(_) => {
+      return Err(
+        FastReleaseErrorBuilder::new(&format!(
+          "Failed to parse the Cargo manifest of the project '{}'.",
+          project.name
+        ))
+        .category("DEPENDENCIES")
+        .get(),
+      )
+    }
+  };
+
+  let mut result: Vec<String> = Vec::new();
+  for path in get_cargo_path_dependencies(&manifest) {
+    let path: PathBuf = normalize(&project_path.join(path));
+
+    if let Some(dependency) = projects
+      .iter()
+      .find(|v: &&ConfigProject| normalize(Path::new(&v.path)) == path)
+    {
+      if dependency.name != project.name && !result.contains(&dependency.name) {
+        result.push(dependency.name.clone());
+      }
+    }
+  }
+
+  Ok(result)
+}
+
+// Builds the graph from the declared dependencies. When a root path is given, the path
+// dependencies of the Cargo manifests are added too.
+pub fn get_graph(
+  root: Option<&Path>,
+  projects: &[ConfigProject],
+) -> Result<DependencyGraph, FastReleaseError> {
+  let mut graph: DependencyGraph = HashMap::new();
+
+  for project in projects {
+    let mut dependencies: Vec<String> = project.dependencies.clone();
+
+    if let Some(root) = root {
+      for dependency in infer_cargo_dependencies(root, project, projects)? {
+        if !dependencies.contains(&dependency) {
+          debug!(
+            message = format!(
+              "The project '{}' depends on the project '{}' through a Cargo path dependency.",
+              project.name, dependency
+            ),
+            category = "DEPENDENCIES"
+          );
+          dependencies.push(dependency);
+        }
+      }
+    }
+
+    graph.insert(project.name.clone(), dependencies);
+  }
+
+  Ok(graph)
+}
+
+// Orders the projects so that every project comes after the projects it depends on
+pub fn sort<'a>(
+  projects: &'a [ConfigProject],
+  graph: &DependencyGraph,
+) -> Result<Vec<&'a ConfigProject>, FastReleaseError> {
+  let mut result: Vec<&ConfigProject> = Vec::new();
+  let mut pending: Vec<&ConfigProject> = projects.iter().collect();
+
+  while !pending.is_empty() {
+    let (ready, rest): (Vec<&ConfigProject>, Vec<&ConfigProject>) =
+      pending.into_iter().partition(|v: &&ConfigProject| {
+        graph.get(&v.name).is_none_or(|dependencies: &Vec<String>| {
+          dependencies
+            .iter()
+            .all(|d: &String| result.iter().any(|r: &&ConfigProject| &r.name == d))
+        })
+      });
+
+    if ready.is_empty() {
+      return Err(
+        FastReleaseErrorBuilder::new(&format!(
+          "There is a dependency cycle between the projects {}.",
+          rest
+            .iter()
+            .map(|v: &&ConfigProject| format!("'{}'", v.name))
+            .collect::<Vec<String>>()
+            .join(", ")
+        ))
+        .category("CONFIG")
+        .get(),
+      );
+    }
+
+    result.extend(ready);
+    pending = rest;
+  }
+
+  Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use tempfile::TempDir;
+
+  fn get_projects(content: &str) -> Vec<ConfigProject> {
+    serde_yaml::from_str(content).unwrap()
+  }
+
+  fn get_names(projects: &[&ConfigProject]) -> Vec<String> {
+    projects
+      .iter()
+      .map(|v: &&ConfigProject| v.name.clone())
+      .collect()
+  }
+
+  #[test]
+  fn sorts_the_projects_after_their_dependencies() {
+    let projects: Vec<ConfigProject> = get_projects(
+      "
+- { name: app, path: app, dependencies: [client, core] }
+- { name: docs, path: docs }
+- { name: client, path: client, dependencies: [core] }
+- { name: core, path: core }
+",
+    );
+    let graph: DependencyGraph = get_graph(None, &projects).unwrap();
+
+    assert_eq!(
+      get_names(&sort(&projects, &graph).unwrap()),
+      ["docs", "core", "client", "app"]
+    );
+  }
+
+  #[test]
+  fn reports_the_dependency_cycles() {
+    let projects: Vec<ConfigProject> = get_projects(
+      "
+- { name: core, path: core }
+- { name: app, path: app, dependencies: [client] }
+- { name: client, path: client, dependencies: [app, core] }
+",
+    );
+    let graph: DependencyGraph = get_graph(None, &projects).unwrap();
+
+    let error: FastReleaseError = sort(&projects, &graph).unwrap_err();
+    assert_eq!(
+      error.message,
+      "There is a dependency cycle between the projects 'app', 'client'."
+    );
+  }
+
+  #[test]
+  fn adds_the_cargo_path_dependencies() {
+    let root: TempDir = TempDir::new().unwrap();
+    for (path, manifest) in [
+      (
+        "crates/app",
+        "[dependencies]\ncore = { path = \"../core\" }\nserde = \"1\"\n[target.'cfg(unix)'.build-dependencies]\nbuild = { path = \"../../tools/./build\" }\n",
+      ),
+      ("crates/core", "[dev-dependencies]\napp = { path = \"../app\" }\n"),
+      ("tools/build", "[package]\nname = \"build\"\n"),
+    ] {
+      fs::create_dir_all(root.path().join(path)).unwrap();
+      fs::write(root.path().join(path).join("Cargo.toml"), manifest).unwrap();
+    }
+    let projects: Vec<ConfigProject> = get_projects(
+      "
+- { name: app, path: ./crates/app, dependencies: [docs] }
+- { name: core, path: crates/core }
+- { name: build, path: tools/build }
+- { name: docs, path: docs }
+",
+    );
+
+    let graph: DependencyGraph = get_graph(Some(root.path()), &projects).unwrap();
+
+    assert_eq!(graph["app"], ["docs", "core", "build"]);
+    // The dev-dependencies aren't published, so they can't create a cycle
+    assert!(graph["core"].is_empty());
+    assert_eq!(
+      get_names(&sort(&projects, &graph).unwrap()),
+      ["core", "build", "docs", "app"]
+    );
+  }
+
+  #[test]
+  fn applies_the_cascade_rules() {
+    assert_eq!(
+      CascadeRule::None.apply(ReleaseType::Major),
+      ReleaseType::None
+    );
+    assert_eq!(
+      CascadeRule::Patch.apply(ReleaseType::Major),
+      ReleaseType::Patch
+    );
+    assert_eq!(
+      CascadeRule::Match.apply(ReleaseType::Minor),
+      ReleaseType::Minor
+    );
+    assert_eq!(
+      CascadeRule::Match.apply(ReleaseType::None),
+      ReleaseType::None
+    );
+  }
+}
@@ -0,0 +1,96 @@
+// Copyright (c) Toolbi Software. All rights reserved.
+// Check the README file in the project root for more information.
+
+use crate::{
+  error::{FastReleaseError, FastReleaseErrorBuilder},
+  git::error,
+};
+use env_ci::CiEnv;
+use git2::{Branch, BranchType, Branches, Reference, Repository};
+
+fn list(repo: &Repository, branch_type: BranchType) -> Result<Vec<String>, FastReleaseError> {
+  let branches: Branches = repo
+    .branches(Some(branch_type))
+    .map_err(|v: git2::Error| error("Failed to list the branches.", v))?;
+
+  let mut result: Vec<String> = Vec::new();
+  for branch in branches {
+    let (branch, _): (Branch, BranchType) =
+      branch.map_err(|v: git2::Error| error("Failed to read a branch.", v))?;
+    if let Ok(Some(name)) = branch.name() {
+      result.push(name.to_string());
+    }
+  }
+
+  Ok(result)
+}
+
+pub fn get_current(repo: &Repository, ci_env: &CiEnv) -> Result<String, FastReleaseError> {
+  // The CI knows the branch even when the repository is on a detached HEAD
+  if let Some(branch) = &ci_env.branch {
+    return Ok(branch.to_string());
+  }
+
+  let head: Reference = repo
+    .head()
+    .map_err(|v: git2::Error| error("Failed to resolve HEAD.", v))?;
+
+  if !head.is_branch() {
+    return Err(
+      FastReleaseErrorBuilder::new(
+        "The repository is on a detached HEAD and the branch couldn't be detected.",
+      )
+      .category("GIT")
+      .get(),
+    );
+  }
+
+  match head.shorthand() {
+    Some(name) => Ok(name.to_string()),
+    None => Err(
+      FastReleaseErrorBuilder::new("The branch name is not valid UTF-8.")
+        .category("GIT")
+        .get(),
+    ),
+  }
+}
+
+pub fn get_local(repo: &Repository) -> Result<Vec<String>, FastReleaseError> {
+  list(repo, BranchType::Local)
+}
+
+pub fn get_remote(repo: &Repository, remote: &str) -> Result<Vec<String>, FastReleaseError> {
+  let prefix: String = format!("{}/", remote);
+
+  Ok(
+    list(repo, BranchType::Remote)?
+      .into_iter()
+      .filter_map(|v: String| v.strip_prefix(&prefix).map(|v: &str| v.to_string()))
+      .filter(|v: &String| v != "HEAD")
+      .collect(),
+  )
+}
+
+pub fn get_all(repo: &Repository, remote: &str) -> Result<Vec<String>, FastReleaseError> {
+  let mut result: Vec<String> = get_local(repo)?;
+  result.extend(get_remote(repo, remote)?);
+  result.sort();
+  result.dedup();
+
+  Ok(result)
+}
+
+// Resolves the commit a branch points to, falling back to the remote one when there's no
+// local branch, as it happens on most CI checkouts
+pub fn get_hash(repo: &Repository, branch: &str, remote: &str) -> Result<String, FastReleaseError> {
+  let reference: Reference = repo
+    .find_branch(branch, BranchType::Local)
+    .or_else(|_| repo.find_branch(&format!("{}/{}", remote, branch), BranchType::Remote))
+    .map(|v: Branch| v.into_reference())
+    .map_err(|v: git2::Error| error(&format!("Couldn't find the branch '{}'.", branch), v))?;
+
+  reference
+    .peel_to_commit()
+    .map(|v: git2::Commit| v.id().to_string())
+    .map_err(|v: git2::Error| error(&format!("Failed to resolve the branch '{}'.", branch), v))
+}
@@ -0,0 +1,229 @@
+// Copyright (c) Toolbi Software. All rights reserved.
+// Check the README file in the project root for more information.
+
+use git2::{Cred, CredentialType, RemoteCallbacks};
+use hosted_git_info::FromUrlBuilder;
+use std::env;
+use tracing::debug;
+
+// Environment variable -> user name sent along with the token over HTTPS
+const TOKEN_VARIABLES: [(&str, &str); 9] = [
+  ("GH_TOKEN", "x-access-token"),
+  ("GITHUB_TOKEN", "x-access-token"),
+  ("GL_TOKEN", "gitlab-ci-token"),
+  ("GITLAB_TOKEN", "gitlab-ci-token"),
+  ("BB_TOKEN", "x-token-auth"),
+  ("BITBUCKET_TOKEN", "x-token-auth"),
+  ("GITEA_TOKEN", "x-access-token"),
+  ("FORGEJO_TOKEN", "x-access-token"),
+  ("GIT_TOKEN", "x-access-token"),
+];
+
+#[derive(Debug, Clone)]
+pub struct Credentials {
+  pub username: String,
+  pub password: String,
+}
+
+fn get_var(name: &str) -> Option<String> {
+  env::var(name)
+    .ok()
+    .filter(|v: &String| !v.trim().is_empty())
+}
+
+// 'GIT_CREDENTIALS' has the 'user:password' form, the tokens get the user name of their host
+pub fn from_env() -> Option<Credentials> {
+  if let Some(value) = get_var("GIT_CREDENTIALS") {
+    let (username, password): (&str, &str) = value.split_once(':').unwrap_or(("git", &value));
+    debug!(
+      message = "Using the credentials of 'GIT_CREDENTIALS'.",
+      category = "GIT"
+    );
+    return Some(Credentials {
+      username: username.to_string(),
+      password: password.to_string(),
+    });
+  }
+
+  for (name, username) in TOKEN_VARIABLES {
+    if let Some(token) = get_var(name) {
+      debug!(
+        message = format!("Using the token of '{}'.", name),
+        category = "GIT"
+      );
+      return Some(Credentials {
+        username: username.to_string(),
+        password: token,
+      });
+    }
+  }
+
+  None
+}
+
+pub fn is_http(url: &str) -> bool {
+  url.starts_with("https://") || url.starts_with("http://")
+}
+
+// Tokens only work over HTTPS, so the SSH URLs of known hosts are pushed through HTTPS instead
+pub fn get_push_url(url: &str, credentials: Option<&Credentials>) -> String {
+  if credentials.is_none() || is_http(url) {
+    return url.to_string();
+  }
+
+  match hosted_git_info::from_url(&FromUrlBuilder::new(url).no_git_plus(true).get()) {
+    Some(mut info) => {
+      // The user of SSH URLs, usually 'git', isn't the one of the token
+      info.auth = None;
+      info.https()
+    }
+    None => url.to_string(),
+  }
+}
+
+// Every kind of credential is only tried once, as libgit2 keeps asking while the remote
+// rejects them
+fn next_credential(
+  allowed: CredentialType,
+  tried: CredentialType,
+  has_credentials: bool,
+) -> Option<CredentialType> {
+  [
+    (CredentialType::USER_PASS_PLAINTEXT, has_credentials),
+    (CredentialType::SSH_KEY, true),
+    (CredentialType::DEFAULT, true),
+  ]
+  .into_iter()
+  .find(|(kind, usable): &(CredentialType, bool)| {
+    *usable && allowed.contains(*kind) && !tried.contains(*kind)
+  })
+  .map(|(kind, _): (CredentialType, bool)| kind)
+}
+
+pub fn get_callbacks<'a>(credentials: Option<Credentials>) -> RemoteCallbacks<'a> {
+  let mut callbacks: RemoteCallbacks = RemoteCallbacks::new();
+  let mut tried: CredentialType = CredentialType::empty();
+
+  callbacks.credentials(
+    move |_url: &str, username: Option<&str>, allowed: CredentialType| {
+      let kind: Option<CredentialType> = next_credential(allowed, tried, credentials.is_some());
+      if let Some(kind) = kind {
+        tried |= kind;
+      }
+
+      match (kind, &credentials) {
+        (Some(CredentialType::USER_PASS_PLAINTEXT), Some(credentials)) => {
+          Cred::userpass_plaintext(&credentials.username, &credentials.password)
+        }
+        (Some(CredentialType::SSH_KEY), _) => Cred::ssh_key_from_agent(username.unwrap_or("git")),
+        (Some(CredentialType::DEFAULT), _) => Cred::default(),
+        _ => Err(git2::Error::from_str(
+          "There are no credentials for the remote. Set 'GIT_CREDENTIALS', a token like 'GITHUB_TOKEN' or 'GL_TOKEN', or load an SSH key on the SSH agent.",
+        )),
+      }
+    },
+  );
+
+  callbacks
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing;
+
+  const NO_TOKENS: [(&str, Option<&str>); 10] = [
+    ("GIT_CREDENTIALS", None),
+    ("GH_TOKEN", None),
+    ("GITHUB_TOKEN", None),
+    ("GL_TOKEN", None),
+    ("GITLAB_TOKEN", None),
+    ("BB_TOKEN", None),
+    ("BITBUCKET_TOKEN", None),
+    ("GITEA_TOKEN", None),
+    ("FORGEJO_TOKEN", None),
+    ("GIT_TOKEN", None),
+  ];
+
+  fn with_vars(vars: &[(&str, Option<&str>)]) -> Option<Credentials> {
+    let mut all: Vec<(&str, Option<&str>)> = NO_TOKENS.to_vec();
+    all.extend_from_slice(vars);
+    testing::with_env(&all, from_env)
+  }
+
+  #[test]
+  fn reads_the_credentials_of_the_environment() {
+    let credentials: Credentials = with_vars(&[("GIT_CREDENTIALS", Some("bot:secret"))]).unwrap();
+    assert_eq!(credentials.username, "bot");
+    assert_eq!(credentials.password, "secret");
+
+    let credentials: Credentials =
+      with_vars(&[("GL_TOKEN", Some("gl")), ("GIT_TOKEN", Some("git"))]).unwrap();
+    assert_eq!(credentials.username, "gitlab-ci-token");
+    assert_eq!(credentials.password, "gl");
+
+    assert!(with_vars(&[("GITHUB_TOKEN", Some(" "))]).is_none());
+  }
+
+  #[test]
+  fn pushes_the_ssh_urls_through_https_with_a_token() {
+    let credentials: Credentials = Credentials {
+      username: "x-access-token".to_string(),
+      password: "token".to_string(),
+    };
+    let url: &str = "git@github.com:owner/repo.git";
+
+    assert_eq!(
+      get_push_url(url, Some(&credentials)),
+      "https://github.com/owner/repo.git"
+    );
+    assert_eq!(get_push_url(url, None), url);
+    assert_eq!(
+      get_push_url("https://gitlab.com/owner/repo.git", Some(&credentials)),
+      "https://gitlab.com/owner/repo.git"
+    );
+  }
+
+  #[test]
+  fn tries_the_token_then_the_ssh_agent_then_the_helper() {
+    let all: CredentialType =
+      CredentialType::USER_PASS_PLAINTEXT | CredentialType::SSH_KEY | CredentialType::DEFAULT;
+    let mut tried: CredentialType = CredentialType::empty();
+    let mut order: Vec<CredentialType> = Vec::new();
+    while let Some(kind) = next_credential(all, tried, true) {
+      tried |= kind;
+      order.push(kind);
+    }
+
+    assert_eq!(
+      order,
+      vec![
+        CredentialType::USER_PASS_PLAINTEXT,
+        CredentialType::SSH_KEY,
+        CredentialType::DEFAULT
+      ]
+    );
+  }
+
+  #[test]
+  fn skips_the_credentials_that_cant_be_used() {
+    let empty: CredentialType = CredentialType::empty();
+
+    assert_eq!(
+      next_credential(CredentialType::USER_PASS_PLAINTEXT, empty, false),
+      None
+    );
+    assert_eq!(
+      next_credential(
+        CredentialType::USER_PASS_PLAINTEXT | CredentialType::SSH_KEY,
+        empty,
+        false
+      ),
+      Some(CredentialType::SSH_KEY)
+    );
+    assert_eq!(
+      next_credential(CredentialType::SSH_KEY, CredentialType::SSH_KEY, true),
+      None
+    );
+  }
+}
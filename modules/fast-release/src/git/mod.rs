@@ -2,4 +2,16 @@
 // Check the README file in the project root for more information.
 
 pub mod branch;
+pub mod credentials;
+pub mod notes;
 pub mod repo;
+
+use crate::error::{FastReleaseError, FastReleaseErrorBuilder};
+use std::io::Error;
+
+pub fn error(message: &str, error: git2::Error) -> FastReleaseError {
+  FastReleaseErrorBuilder::new(message)
+    .category("GIT")
+    .error(Error::other(error))
+    .get()
+}
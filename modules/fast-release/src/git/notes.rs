@@ -0,0 +1,465 @@
+// Copyright (c) Toolbi Software. All rights reserved.
+// Check the README file in the project root for more information.
+
+use crate::{
+  analyzer::rules::ReleaseType,
+  constants::{NOTES_REF, NOTES_REMOTE_REF},
+  error::FastReleaseError,
+  git::{error, repo},
+  modules::ReleaseInfo,
+  release::LastRelease,
+};
+use git2::{Commit, Note, Oid, Reference, Repository, Tree};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tracing::debug;
+
+const PUSH_ATTEMPTS: usize = 3;
+
+// The steps done by a release that is tagged but not complete yet, so a new run resumes it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Journal {
+  pub release_type: ReleaseType,
+  // The notes can't be generated again once the release is tagged
+  pub notes: String,
+  pub last_release: Option<LastRelease>,
+  // The commit the release started from, when the 'prepare' step committed on top of it
+  #[serde(default)]
+  pub base: Option<String>,
+  // Like 'push', 'publish:github' or 'success:github'
+  #[serde(default)]
+  pub steps: Vec<String>,
+  #[serde(default)]
+  pub releases: Vec<ReleaseInfo>,
+}
+
+impl Journal {
+  pub fn has(&self, step: &str) -> bool {
+    self.steps.iter().any(|v: &String| v == step)
+  }
+
+  pub fn add(&mut self, step: &str) {
+    if !self.has(step) {
+      self.steps.push(step.to_string());
+    }
+  }
+
+  pub fn remove(&mut self, step: &str) {
+    self.steps.retain(|v: &String| v != step);
+  }
+}
+
+// The channels are 'null' for the default one. The journal is gone once the release is complete.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct TagNote {
+  #[serde(default)]
+  channels: Vec<Option<String>>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  journal: Option<Journal>,
+}
+
+// Tag -> note of the release. The note goes on the commit, which can have the tags of more than
+// one project.
+type Notes = BTreeMap<String, TagNote>;
+
+fn read(repo: &Repository, reference: &str, oid: Oid) -> Notes {
+  repo
+    .find_note(Some(reference), oid)
+    .ok()
+    .and_then(|v: Note| v.message().map(|v: &str| v.to_string()))
+    .and_then(|v: String| serde_json::from_str::<Notes>(&v).ok())
+    .unwrap_or_default()
+}
+
+fn write(repo: &Repository, oid: Oid, notes: &Notes) -> Result<(), FastReleaseError> {
+  let signature: git2::Signature = repo::get_signature(repo)?;
+  let message: String = serde_json::to_string(notes).unwrap_or_default();
+
+  repo
+    .note(&signature, &signature, Some(NOTES_REF), oid, &message, true)
+    .map_err(|v: git2::Error| {
+      error(
+        &format!("Failed to write the notes of the commit '{}'.", oid),
+        v,
+      )
+    })?;
+
+  Ok(())
+}
+
+fn get_oid(hash: &str) -> Result<Oid, FastReleaseError> {
+  Oid::from_str(hash)
+    .map_err(|v: git2::Error| error(&format!("The commit '{}' is not valid.", hash), v))
+}
+
+// The tags released before the channels were tracked are on the default channel
+pub fn get_channels(repo: &Repository, tag: &str, hash: &str) -> Vec<Option<String>> {
+  Oid::from_str(hash)
+    .ok()
+    .and_then(|v: Oid| read(repo, NOTES_REF, v).remove(tag))
+    .map(|v: TagNote| v.channels)
+    .filter(|v: &Vec<Option<String>>| !v.is_empty())
+    .unwrap_or_else(|| vec![None])
+}
+
+pub fn add_channel(
+  repo: &Repository,
+  tag: &str,
+  hash: &str,
+  channel: Option<&str>,
+) -> Result<(), FastReleaseError> {
+  let oid: Oid = get_oid(hash)?;
+  let mut notes: Notes = read(repo, NOTES_REF, oid);
+  let note: &mut TagNote = notes.entry(tag.to_string()).or_default();
+  if note
+    .channels
+    .iter()
+    .any(|v: &Option<String>| v.as_deref() == channel)
+  {
+    return Ok(());
+  }
+  note.channels.push(channel.map(|v: &str| v.to_string()));
+
+  write(repo, oid, &notes)?;
+  debug!(
+    message = format!(
+      "Added the channel '{}' to the tag '{}'.",
+      channel.unwrap_or("default"),
+      tag
+    ),
+    category = "GIT"
+  );
+
+  Ok(())
+}
+
+pub fn get_journal(repo: &Repository, tag: &str, hash: &str) -> Option<Journal> {
+  Oid::from_str(hash)
+    .ok()
+    .and_then(|v: Oid| read(repo, NOTES_REF, v).remove(tag))
+    .and_then(|v: TagNote| v.journal)
+}
+
+// A release without a journal is complete
+pub fn set_journal(
+  repo: &Repository,
+  tag: &str,
+  hash: &str,
+  journal: Option<&Journal>,
+) -> Result<(), FastReleaseError> {
+  let oid: Oid = get_oid(hash)?;
+  let mut notes: Notes = read(repo, NOTES_REF, oid);
+  let note: &mut TagNote = notes.entry(tag.to_string()).or_default();
+  if note.journal.as_ref() == journal {
+    return Ok(());
+  }
+  note.journal = journal.cloned();
+
+  write(repo, oid, &notes)
+}
+
+// Drops the channels and the journal of a tag that was taken back
+pub fn remove(repo: &Repository, tag: &str, hash: &str) -> Result<(), FastReleaseError> {
+  let oid: Oid = get_oid(hash)?;
+  let mut notes: Notes = read(repo, NOTES_REF, oid);
+  if notes.remove(tag).is_none() {
+    return Ok(());
+  }
+
+  if !notes.is_empty() {
+    return write(repo, oid, &notes);
+  }
+
+  let signature: git2::Signature = repo::get_signature(repo)?;
+  repo
+    .note_delete(oid, Some(NOTES_REF), &signature, &signature)
+    .map_err(|v: git2::Error| {
+      error(
+        &format!("Failed to remove the notes of the commit '{}'.", oid),
+        v,
+      )
+    })
+}
+
+// The journal of a release is gone once any run completes it
+fn merge(mut local: Notes, remote: Notes) -> Notes {
+  for (tag, remote_note) in remote {
+    let local_note: &mut TagNote = match local.get_mut(&tag) {
+      Some(local_note) => local_note,
+      None => {
+        local.insert(tag, remote_note);
+        continue;
+      }
+    };
+
+    for channel in remote_note.channels {
+      if !local_note.channels.contains(&channel) {
+        local_note.channels.push(channel);
+      }
+    }
+
+    local_note.journal = match (local_note.journal.take(), remote_note.journal) {
+      (Some(mut journal), Some(remote_journal)) => {
+        for step in &remote_journal.steps {
+          journal.add(step);
+        }
+        for release in remote_journal.releases {
+          if !journal.releases.contains(&release) {
+            journal.releases.push(release);
+          }
+        }
+        Some(journal)
+      }
+      _ => None,
+    };
+  }
+
+  local
+}
+
+// The notes of the remote are merged into the local ones, which keep what a failed run couldn't
+// push. The local notes then have the notes of the remote in their history, so they're pushed
+// without taking anything away from the remote.
+pub fn fetch(repo: &Repository, remote: &str) -> Result<(), FastReleaseError> {
+  repo::fetch(
+    repo,
+    remote,
+    &[format!("{}:{}", NOTES_REF, NOTES_REMOTE_REF)],
+  )?;
+
+  // The remote doesn't have notes yet
+  let remote_commit: Oid = match repo.refname_to_id(NOTES_REMOTE_REF) {
+    Ok(remote_commit) => remote_commit,
+    Err(_) => return Ok(()),
+  };
+  let local_commit: Oid = match repo.refname_to_id(NOTES_REF) {
+    Ok(local_commit) => local_commit,
+    Err(_) => return fast_forward(repo, remote_commit),
+  };
+
+  let is_descendant = |commit: Oid, ancestor: Oid| -> bool {
+    commit == ancestor || repo.graph_descendant_of(commit, ancestor).unwrap_or(false)
+  };
+  if is_descendant(local_commit, remote_commit) {
+    return Ok(());
+  }
+  if is_descendant(remote_commit, local_commit) {
+    return fast_forward(repo, remote_commit);
+  }
+
+  let targets: Vec<Oid> = repo
+    .notes(Some(NOTES_REMOTE_REF))
+    .map_err(|v: git2::Error| error("Failed to read the notes of the remote.", v))?
+    .filter_map(Result::ok)
+    .map(|(_, v): (Oid, Oid)| v)
+    .collect();
+  for target in targets {
+    let local: Notes = read(repo, NOTES_REF, target);
+    let merged: Notes = merge(local.clone(), read(repo, NOTES_REMOTE_REF, target));
+    if merged != local {
+      write(repo, target, &merged)?;
+    }
+  }
+
+  let merge_error =
+    |v: git2::Error| -> FastReleaseError { error("Failed to merge the notes of the remote.", v) };
+  let signature: git2::Signature = repo::get_signature(repo)?;
+  let head: Commit = repo
+    .find_reference(NOTES_REF)
+    .and_then(|v: Reference| v.peel_to_commit())
+    .map_err(merge_error)?;
+  let remote_head: Commit = repo.find_commit(remote_commit).map_err(merge_error)?;
+  let tree: Tree = head.tree().map_err(merge_error)?;
+  repo
+    .commit(
+      Some(NOTES_REF),
+      &signature,
+      &signature,
+      "Merge the notes of the remote",
+      &tree,
+      &[&head, &remote_head],
+    )
+    .map_err(merge_error)?;
+
+  Ok(())
+}
+
+// The local notes don't have anything the remote doesn't
+fn fast_forward(repo: &Repository, remote_commit: Oid) -> Result<(), FastReleaseError> {
+  repo
+    .reference(
+      NOTES_REF,
+      remote_commit,
+      true,
+      "fast-forward to the notes of the remote",
+    )
+    .map_err(|v: git2::Error| error("Failed to update the notes from the remote.", v))?;
+
+  Ok(())
+}
+
+// The remote can get the notes of another run between the fetch and the push
+pub fn push(repo: &Repository, remote: &str) -> Result<(), FastReleaseError> {
+  let mut attempt: usize = 1;
+  loop {
+    match repo::push(repo, remote, &[format!("{0}:{0}", NOTES_REF)]) {
+      Err(error) if attempt < PUSH_ATTEMPTS && repo::is_non_fast_forward(&error) => {
+        debug!(
+          message = "The remote has new notes, so they're merged before pushing again.",
+          category = "GIT"
+        );
+        fetch(repo, remote)?;
+        attempt += 1;
+      }
+      result => return result,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::TestRepo;
+
+  #[test]
+  fn merges_the_notes_that_another_run_pushed() {
+    let test_repo: TestRepo = TestRepo::new();
+    test_repo.write("lib.rs", "lib");
+    let hash: String = test_repo.commit("feat: add the library");
+    test_repo.push();
+
+    add_channel(&test_repo.repo, "v1.0.0", &hash, None).unwrap();
+    push(&test_repo.repo, "origin").unwrap();
+
+    // Another run adds a channel on the remote in the meantime
+    let remote: Repository = test_repo.remote();
+    add_channel(&remote, "v1.0.0", &hash, Some("next")).unwrap();
+    let remote_commit: Oid = remote.refname_to_id(NOTES_REF).unwrap();
+
+    add_channel(&test_repo.repo, "v1.0.0", &hash, Some("beta")).unwrap();
+    push(&test_repo.repo, "origin").unwrap();
+
+    let channels: Vec<Option<String>> = get_channels(&remote, "v1.0.0", &hash);
+    assert_eq!(
+      channels,
+      vec![None, Some("beta".to_string()), Some("next".to_string())]
+    );
+    let head: Oid = remote.refname_to_id(NOTES_REF).unwrap();
+    assert!(remote.graph_descendant_of(head, remote_commit).unwrap());
+  }
+
+  #[test]
+  fn fast_forwards_to_the_notes_of_the_remote() {
+    let test_repo: TestRepo = TestRepo::new();
+    test_repo.write("lib.rs", "lib");
+    let hash: String = test_repo.commit("feat: add the library");
+    test_repo.push();
+
+    let remote: Repository = test_repo.remote();
+    add_channel(&remote, "v1.0.0", &hash, None).unwrap();
+
+    fetch(&test_repo.repo, "origin").unwrap();
+
+    assert_eq!(
+      test_repo.repo.refname_to_id(NOTES_REF).unwrap(),
+      remote.refname_to_id(NOTES_REF).unwrap()
+    );
+    assert_eq!(get_channels(&test_repo.repo, "v1.0.0", &hash), vec![None]);
+  }
+
+  fn get_journal_with(steps: &[&str]) -> Journal {
+    Journal {
+      release_type: ReleaseType::Minor,
+      notes: "The notes of the release.".to_string(),
+      last_release: None,
+      base: None,
+      steps: steps.iter().map(|v: &&str| v.to_string()).collect(),
+      releases: Vec::new(),
+    }
+  }
+
+  #[test]
+  fn keeps_the_journal_until_the_release_completes() {
+    let test_repo: TestRepo = TestRepo::new();
+    test_repo.write("lib.rs", "lib");
+    let hash: String = test_repo.commit("feat: add the library");
+
+    add_channel(&test_repo.repo, "v1.0.0", &hash, Some("next")).unwrap();
+    let mut journal: Journal = get_journal_with(&["push"]);
+    journal.releases.push(ReleaseInfo {
+      module: "github".to_string(),
+      name: "GitHub release".to_string(),
+      url: Some("https://github.com/acme/app/releases/v1.0.0".to_string()),
+      channel: Some("next".to_string()),
+    });
+    set_journal(&test_repo.repo, "v1.0.0", &hash, Some(&journal)).unwrap();
+
+    assert_eq!(get_journal(&test_repo.repo, "v1.0.0", &hash), Some(journal));
+    assert_eq!(get_journal(&test_repo.repo, "v1.1.0", &hash), None);
+
+    set_journal(&test_repo.repo, "v1.0.0", &hash, None).unwrap();
+
+    assert_eq!(get_journal(&test_repo.repo, "v1.0.0", &hash), None);
+    assert_eq!(
+      get_channels(&test_repo.repo, "v1.0.0", &hash),
+      vec![Some("next".to_string())]
+    );
+  }
+
+  // Two runs resumed the same release and each did some of its steps
+  #[test]
+  fn merges_the_steps_that_each_run_completed() {
+    let test_repo: TestRepo = TestRepo::new();
+    test_repo.write("lib.rs", "lib");
+    let hash: String = test_repo.commit("feat: add the library");
+    test_repo.push();
+
+    let journal: Journal = get_journal_with(&["push"]);
+    set_journal(&test_repo.repo, "v1.0.0", &hash, Some(&journal)).unwrap();
+    push(&test_repo.repo, "origin").unwrap();
+
+    let remote: Repository = test_repo.remote();
+    let remote_journal: Journal = get_journal_with(&["push", "publish:exec"]);
+    set_journal(&remote, "v1.0.0", &hash, Some(&remote_journal)).unwrap();
+    let local_journal: Journal = get_journal_with(&["push", "publish:github"]);
+    set_journal(&test_repo.repo, "v1.0.0", &hash, Some(&local_journal)).unwrap();
+
+    fetch(&test_repo.repo, "origin").unwrap();
+
+    let merged: Journal = get_journal(&test_repo.repo, "v1.0.0", &hash).unwrap();
+    assert_eq!(merged.steps, ["push", "publish:github", "publish:exec"]);
+    assert!(!merged.has("success:github"));
+  }
+
+  // A run that completes the release drops the journal for every other run
+  #[test]
+  fn drops_the_journal_that_another_run_completed() {
+    let test_repo: TestRepo = TestRepo::new();
+    test_repo.write("lib.rs", "lib");
+    let hash: String = test_repo.commit("feat: add the library");
+    test_repo.push();
+
+    set_journal(
+      &test_repo.repo,
+      "v1.0.0",
+      &hash,
+      Some(&get_journal_with(&["push"])),
+    )
+    .unwrap();
+    push(&test_repo.repo, "origin").unwrap();
+
+    let remote: Repository = test_repo.remote();
+    set_journal(&remote, "v1.0.0", &hash, None).unwrap();
+    set_journal(
+      &test_repo.repo,
+      "v1.0.0",
+      &hash,
+      Some(&get_journal_with(&["push", "publish:github"])),
+    )
+    .unwrap();
+
+    fetch(&test_repo.repo, "origin").unwrap();
+
+    assert_eq!(get_journal(&test_repo.repo, "v1.0.0", &hash), None);
+  }
+}
@@ -0,0 +1,711 @@
+// Copyright (c) Toolbi Software. All rights reserved.
+// Check the README file in the project root for more information.
+
+use crate::{
+  constants::{DEFAULT_GIT_EMAIL, DEFAULT_GIT_NAME},
+  error::{FastReleaseError, FastReleaseErrorBuilder},
+  git::{
+    credentials::{self, Credentials},
+    error,
+  },
+};
+use env_ci::CiEnv;
+use git2::{
+  AutotagOption, Direction, ErrorClass, ErrorCode, FetchOptions, Index, ObjectType, Oid,
+  PushOptions, PushUpdate, Reference, Remote, RemoteCallbacks, Repository, ResetType, Signature,
+};
+use std::{cell::RefCell, env, io, path::PathBuf};
+use tracing::debug;
+
+// Where the references of a remote are listed, see 'list_remote'
+const LIST_NAMESPACE: &str = "refs/fast-release/list/";
+// The statuses of the references that a remote rejects because it has other commits
+const NON_FAST_FORWARD_STATUSES: [&str; 2] = ["non-fast-forward", "fetch first"];
+
+#[derive(Debug, Clone)]
+pub struct Tag {
+  pub name: String,
+  pub hash: String,
+}
+
+pub fn open(ci_env: &CiEnv) -> Result<Repository, FastReleaseError> {
+  let path: PathBuf = match &ci_env.root {
+    Some(root) => PathBuf::from(root),
+    None => match env::current_dir() {
+      Ok(path) => path,
+      Err(error) => {
+        return Err(
+          FastReleaseErrorBuilder::new("Failed to get the current directory.")
+            .category("GIT")
+            .error(error)
+            .get(),
+        )
+      }
+    },
+  };
+
+  debug!(
+    message = format!("Opening the git repository at '{}'.", path.display()),
+    category = "GIT"
+  );
+
+  Repository::discover(&path)
+    .map_err(|v: git2::Error| error("Failed to open the git repository.", v))
+}
+
+pub fn get_root(repo: &Repository) -> PathBuf {
+  repo.workdir().unwrap_or_else(|| repo.path()).to_path_buf()
+}
+
+pub fn get_head(repo: &Repository) -> Result<String, FastReleaseError> {
+  repo
+    .head()
+    .and_then(|v: Reference| v.peel_to_commit())
+    .map(|v: git2::Commit| v.id().to_string())
+    .map_err(|v: git2::Error| error("Failed to resolve HEAD.", v))
+}
+
+pub fn get_commit_time(repo: &Repository, hash: &str) -> Result<i64, FastReleaseError> {
+  Oid::from_str(hash)
+    .and_then(|v: Oid| repo.find_commit(v))
+    .map(|v: git2::Commit| v.time().seconds())
+    .map_err(|v: git2::Error| error(&format!("Failed to read the commit '{}'.", hash), v))
+}
+
+pub fn get_remote_url(repo: &Repository, remote: &str) -> Result<String, FastReleaseError> {
+  let remote: Remote = repo
+    .find_remote(remote)
+    .map_err(|v: git2::Error| error(&format!("Couldn't find the remote '{}'.", remote), v))?;
+
+  match remote.url() {
+    Some(url) => Ok(url.to_string()),
+    None => Err(
+      FastReleaseErrorBuilder::new("The remote URL is not valid UTF-8.")
+        .category("GIT")
+        .get(),
+    ),
+  }
+}
+
+pub fn get_tags(repo: &Repository) -> Result<Vec<Tag>, FastReleaseError> {
+  let names: git2::string_array::StringArray = repo
+    .tag_names(None)
+    .map_err(|v: git2::Error| error("Failed to list the tags.", v))?;
+
+  let mut tags: Vec<Tag> = Vec::new();
+  for name in names.iter().flatten() {
+    // Tags that don't point to a commit can't be a release
+    if let Ok(commit) = repo
+      .revparse_single(&format!("refs/tags/{}", name))
+      .and_then(|v: git2::Object| v.peel_to_commit())
+    {
+      tags.push(Tag {
+        name: name.to_string(),
+        hash: commit.id().to_string(),
+      });
+    }
+  }
+
+  Ok(tags)
+}
+
+pub fn is_ancestor(repo: &Repository, ancestor: &str, descendant: &str) -> bool {
+  match (Oid::from_str(ancestor), Oid::from_str(descendant)) {
+    (Ok(ancestor), Ok(descendant)) => {
+      ancestor == descendant
+        || repo
+          .graph_descendant_of(descendant, ancestor)
+          .unwrap_or(false)
+    }
+    _ => false,
+  }
+}
+
+// The git configuration usually isn't set on CI, so there are defaults for the releases
+pub fn get_signature(repo: &Repository) -> Result<Signature<'static>, FastReleaseError> {
+  let name: Option<String> = env::var("GIT_COMMITTER_NAME").ok();
+  let email: Option<String> = env::var("GIT_COMMITTER_EMAIL").ok();
+
+  if name.is_none() && email.is_none() {
+    if let Ok(signature) = repo.signature() {
+      return Ok(signature.to_owned());
+    }
+  }
+
+  Signature::now(
+    name.as_deref().unwrap_or(DEFAULT_GIT_NAME),
+    email.as_deref().unwrap_or(DEFAULT_GIT_EMAIL),
+  )
+  .map_err(|v: git2::Error| error("Failed to create the signature of the release.", v))
+}
+
+pub fn get_tag_hash(repo: &Repository, name: &str) -> Option<String> {
+  repo
+    .revparse_single(&format!("refs/tags/{}", name))
+    .and_then(|v: git2::Object| v.peel_to_commit())
+    .map(|v: git2::Commit| v.id().to_string())
+    .ok()
+}
+
+// Tags with a message are annotated. An existing tag on the same commit is left as it is, so a
+// failed release can run again.
+pub fn create_tag(
+  repo: &Repository,
+  name: &str,
+  hash: &str,
+  message: Option<&str>,
+) -> Result<(), FastReleaseError> {
+  if let Some(tag_hash) = get_tag_hash(repo, name) {
+    if tag_hash == hash {
+      debug!(
+        message = format!(
+          "The tag '{}' already exists on the commit '{}'.",
+          name, hash
+        ),
+        category = "GIT"
+      );
+      return Ok(());
+    }
+
+    return Err(
+      FastReleaseErrorBuilder::new(&format!(
+        "The tag '{}' already exists on another commit, '{}'.",
+        name, tag_hash
+      ))
+      .category("GIT")
+      .get(),
+    );
+  }
+
+  let target: git2::Object = Oid::from_str(hash)
+    .and_then(|v: Oid| repo.find_object(v, Some(ObjectType::Commit)))
+    .map_err(|v: git2::Error| error(&format!("Failed to find the commit '{}'.", hash), v))?;
+
+  let result: Result<Oid, git2::Error> = match message {
+    Some(message) => repo.tag(name, &target, &get_signature(repo)?, message, false),
+    None => repo.tag_lightweight(name, &target, false),
+  };
+  result.map_err(|v: git2::Error| error(&format!("Failed to create the tag '{}'.", name), v))?;
+
+  debug!(
+    message = format!("Created the tag '{}' on the commit '{}'.", name, hash),
+    category = "GIT"
+  );
+
+  Ok(())
+}
+
+pub fn delete_tag(repo: &Repository, name: &str) -> Result<(), FastReleaseError> {
+  repo
+    .tag_delete(name)
+    .map_err(|v: git2::Error| error(&format!("Failed to delete the tag '{}'.", name), v))
+}
+
+// Opens the remote with the credentials of the environment. Known hosts are reached through
+// HTTPS when there is a token.
+fn get_remote<'a, 'b>(
+  repo: &'a Repository,
+  remote_name: &str,
+) -> Result<(Remote<'a>, RemoteCallbacks<'b>), FastReleaseError> {
+  let url: String = get_remote_url(repo, remote_name)?;
+  let credentials: Option<Credentials> = credentials::from_env();
+  let remote_url: String = credentials::get_push_url(&url, credentials.as_ref());
+
+  let remote: Remote = if remote_url == url {
+    repo.find_remote(remote_name)
+  } else {
+    debug!(
+      message = format!("Connecting through '{}' to use the token.", remote_url),
+      category = "GIT"
+    );
+    repo.remote_anonymous(&remote_url)
+  }
+  .map_err(|v: git2::Error| error(&format!("Couldn't find the remote '{}'.", remote_name), v))?;
+
+  Ok((remote, credentials::get_callbacks(credentials)))
+}
+
+// The references of the remote that match the pattern, with the commits they point to. libgit2
+// can't list a remote without references, as git2 reads the empty list from a null pointer, so
+// they're fetched to a namespace of their own that is removed afterwards.
+fn list_remote(
+  repo: &Repository,
+  remote_name: &str,
+  pattern: &str,
+) -> Result<Vec<(String, String)>, FastReleaseError> {
+  let list_error = |v: git2::Error| -> FastReleaseError {
+    error(&format!("Failed to list the remote '{}'.", remote_name), v)
+  };
+  let (mut remote, callbacks): (Remote, RemoteCallbacks) = get_remote(repo, remote_name)?;
+
+  // Without the tags that point to the fetched commits, nor the 'FETCH_HEAD'
+  let mut options: FetchOptions = FetchOptions::new();
+  options
+    .remote_callbacks(callbacks)
+    .download_tags(AutotagOption::None)
+    .update_fetchhead(false);
+  let name: &str = pattern.strip_prefix("refs/").unwrap_or(pattern);
+  remote
+    .fetch(
+      &[format!("+{}:{}{}", pattern, LIST_NAMESPACE, name)],
+      Some(&mut options),
+      None,
+    )
+    .map_err(list_error)?;
+
+  let mut result: Vec<(String, String)> = Vec::new();
+  let mut references: Vec<Reference> = repo
+    .references_glob(&format!("{}*", LIST_NAMESPACE))
+    .map_err(list_error)?
+    .flatten()
+    .collect();
+  for reference in &mut references {
+    let name: Option<String> = reference
+      .name()
+      .and_then(|v: &str| v.strip_prefix(LIST_NAMESPACE))
+      .map(|v: &str| format!("refs/{}", v));
+    if let (Some(name), Ok(commit)) = (name, reference.peel_to_commit()) {
+      result.push((name, commit.id().to_string()));
+    }
+    reference.delete().map_err(list_error)?;
+  }
+
+  Ok(result)
+}
+
+// The commit of a branch on the remote, without moving the local references
+pub fn get_remote_hash(
+  repo: &Repository,
+  remote_name: &str,
+  branch: &str,
+) -> Result<Option<String>, FastReleaseError> {
+  let reference: String = format!("refs/heads/{}", branch);
+
+  Ok(
+    list_remote(repo, remote_name, &reference)?
+      .into_iter()
+      .find(|(name, _): &(String, String)| *name == reference)
+      .map(|(_, hash): (String, String)| hash),
+  )
+}
+
+// The tags of the remote on a commit, without adding them to the local tags
+pub fn get_remote_tags(
+  repo: &Repository,
+  remote_name: &str,
+  hash: &str,
+) -> Result<Vec<String>, FastReleaseError> {
+  Ok(
+    list_remote(repo, remote_name, "refs/tags/*")?
+      .into_iter()
+      .filter(|(_, tag_hash): &(String, String)| tag_hash == hash)
+      .filter_map(|(name, _): (String, String)| {
+        name.strip_prefix("refs/tags/").map(|v: &str| v.to_string())
+      })
+      .collect(),
+  )
+}
+
+// Only tells that the remote is reachable with the credentials. libgit2 has no dry run for pushes,
+// so a missing write access still fails on the push.
+pub fn verify_remote_access(repo: &Repository, remote_name: &str) -> Result<(), FastReleaseError> {
+  let (mut remote, callbacks): (Remote, RemoteCallbacks) = get_remote(repo, remote_name)?;
+
+  remote
+    .connect_auth(Direction::Push, Some(callbacks), None)
+    .map(|_| ())
+    .map_err(|v: git2::Error| {
+      error(
+        &format!(
+          "Can't connect to the remote '{}' for pushing. Check that the credentials, like 'GITHUB_TOKEN', 'GL_TOKEN', 'GIT_CREDENTIALS' or the SSH key, are valid.",
+          remote_name
+        ),
+        v,
+      )
+    })
+}
+
+// Fetches the refspecs from the remote with the credentials of the environment
+pub fn fetch(
+  repo: &Repository,
+  remote_name: &str,
+  refspecs: &[String],
+) -> Result<(), FastReleaseError> {
+  let (mut remote, callbacks): (Remote, RemoteCallbacks) = get_remote(repo, remote_name)?;
+
+  let mut options: FetchOptions = FetchOptions::new();
+  options.remote_callbacks(callbacks);
+
+  remote
+    .fetch(refspecs, Some(&mut options), None)
+    .map_err(|v: git2::Error| {
+      error(
+        &format!("Failed to fetch from the remote '{}'.", remote_name),
+        v,
+      )
+    })?;
+
+  debug!(
+    message = format!(
+      "Fetched {} from the remote '{}'.",
+      refspecs.join(", "),
+      remote_name
+    ),
+    category = "GIT"
+  );
+
+  Ok(())
+}
+
+// Pushes the refspecs to the remote with the credentials of the environment
+pub fn push(
+  repo: &Repository,
+  remote_name: &str,
+  refspecs: &[String],
+) -> Result<(), FastReleaseError> {
+  push_refspecs(repo, remote_name, refspecs, None)
+}
+
+// Moves a branch of the remote to a commit that isn't ahead of it, like
+// 'git push --force-with-lease': the push is cancelled when the branch of the remote isn't on the
+// expected commit anymore, so the commits pushed in the meantime aren't lost
+pub fn force_push_with_lease(
+  repo: &Repository,
+  remote_name: &str,
+  branch: &str,
+  hash: &str,
+  expected: &str,
+) -> Result<(), FastReleaseError> {
+  let expected: Oid = Oid::from_str(expected)
+    .map_err(|v: git2::Error| error(&format!("Invalid commit hash '{}'.", expected), v))?;
+  let reference: String = format!("refs/heads/{}", branch);
+
+  push_refspecs(
+    repo,
+    remote_name,
+    &[format!("+{}:{}", hash, reference)],
+    Some((&reference, expected)),
+  )
+}
+
+fn push_refspecs(
+  repo: &Repository,
+  remote_name: &str,
+  refspecs: &[String],
+  lease: Option<(&str, Oid)>,
+) -> Result<(), FastReleaseError> {
+  // The remote can reject a reference without failing the whole push
+  let rejected: RefCell<Vec<(String, String)>> = RefCell::new(Vec::new());
+  let moved: RefCell<Option<(String, Oid)>> = RefCell::new(None);
+  let (mut remote, mut callbacks): (Remote, RemoteCallbacks) = get_remote(repo, remote_name)?;
+  callbacks.push_update_reference(|reference: &str, status: Option<&str>| {
+    if let Some(status) = status {
+      rejected
+        .borrow_mut()
+        .push((reference.to_string(), status.to_string()));
+    }
+    Ok(())
+  });
+  if let Some((reference, expected)) = lease {
+    let moved: &RefCell<Option<(String, Oid)>> = &moved;
+    // The updates hold the commits the remote advertised on this connection
+    callbacks.push_negotiation(move |updates: &[PushUpdate]| {
+      for update in updates {
+        if update.dst_refname() == Some(reference) && update.src() != expected {
+          *moved.borrow_mut() = Some((reference.to_string(), update.src()));
+          return Err(git2::Error::from_str("the reference moved on the remote"));
+        }
+      }
+      Ok(())
+    });
+  }
+
+  let mut options: PushOptions = PushOptions::new();
+  options.remote_callbacks(callbacks);
+
+  let result: Result<(), git2::Error> = remote.push(refspecs, Some(&mut options));
+  drop(options);
+  if let Some((reference, hash)) = moved.into_inner() {
+    return Err(
+      FastReleaseErrorBuilder::new(&format!(
+        "The reference '{}' moved to the commit '{}' on the remote '{}', so it wasn't pushed.",
+        reference, hash, remote_name
+      ))
+      .category("GIT")
+      .get(),
+    );
+  }
+  result.map_err(|v: git2::Error| {
+    error(
+      &format!("Failed to push to the remote '{}'.", remote_name),
+      v,
+    )
+  })?;
+
+  let rejected: Vec<(String, String)> = rejected.into_inner();
+  if !rejected.is_empty() {
+    let builder: FastReleaseErrorBuilder = FastReleaseErrorBuilder::new(&format!(
+      "The remote '{}' rejected {}.",
+      remote_name,
+      rejected
+        .iter()
+        .map(|(reference, status): &(String, String)| format!("'{}' ({})", reference, status))
+        .collect::<Vec<String>>()
+        .join(", ")
+    ))
+    .category("GIT");
+
+    // Reported like the fast-forwards that libgit2 refuses before pushing
+    let non_fast_forward: Option<&String> = rejected
+      .iter()
+      .map(|(_, status): &(String, String)| status)
+      .find(|v: &&String| NON_FAST_FORWARD_STATUSES.contains(&v.as_str()));
+    return Err(match non_fast_forward {
+      Some(status) => builder
+        .error(io::Error::other(git2::Error::new(
+          ErrorCode::NotFastForward,
+          ErrorClass::Reference,
+          status,
+        )))
+        .get(),
+      None => builder.get(),
+    });
+  }
+
+  debug!(
+    message = format!(
+      "Pushed {} to the remote '{}'.",
+      refspecs.join(", "),
+      remote_name
+    ),
+    category = "GIT"
+  );
+
+  Ok(())
+}
+
+// The remote has commits that the pushed reference doesn't have
+pub fn is_non_fast_forward(error: &FastReleaseError) -> bool {
+  error
+    .error
+    .as_ref()
+    .and_then(|v: &io::Error| v.get_ref())
+    .and_then(|v: &(dyn std::error::Error + Send + Sync + 'static)| v.downcast_ref::<git2::Error>())
+    .is_some_and(|v: &git2::Error| v.code() == ErrorCode::NotFastForward)
+}
+
+// Commits the files, relative to the root of the repository, on top of HEAD. Returns the hash of
+// the new commit, or nothing when the files didn't change.
+pub fn commit(
+  repo: &Repository,
+  paths: &[PathBuf],
+  message: &str,
+  author: &Signature,
+) -> Result<Option<String>, FastReleaseError> {
+  let mut index: Index = repo
+    .index()
+    .map_err(|v: git2::Error| error("Failed to read the index.", v))?;
+  for path in paths {
+    index.add_path(path).map_err(|v: git2::Error| {
+      error(
+        &format!("Failed to stage the file '{}'.", path.display()),
+        v,
+      )
+    })?;
+  }
+  index
+    .write()
+    .map_err(|v: git2::Error| error("Failed to write the index.", v))?;
+
+  let tree_id: Oid = index
+    .write_tree()
+    .map_err(|v: git2::Error| error("Failed to write the tree of the index.", v))?;
+  let parent: git2::Commit = repo
+    .head()
+    .and_then(|v: Reference| v.peel_to_commit())
+    .map_err(|v: git2::Error| error("Failed to resolve HEAD.", v))?;
+
+  if parent.tree_id() == tree_id {
+    return Ok(None);
+  }
+
+  let tree: git2::Tree = repo
+    .find_tree(tree_id)
+    .map_err(|v: git2::Error| error("Failed to read the tree of the index.", v))?;
+  let committer: Signature = get_signature(repo)?;
+
+  repo
+    .commit(Some("HEAD"), author, &committer, message, &tree, &[&parent])
+    .map(|v: Oid| Some(v.to_string()))
+    .map_err(|v: git2::Error| error("Failed to create the release commit.", v))
+}
+
+// Moves the branch to a commit, along with the files of the working tree
+pub fn reset(repo: &Repository, hash: &str) -> Result<(), FastReleaseError> {
+  let target: git2::Object = Oid::from_str(hash)
+    .and_then(|v: Oid| repo.find_object(v, Some(ObjectType::Commit)))
+    .map_err(|v: git2::Error| error(&format!("Failed to find the commit '{}'.", hash), v))?;
+
+  repo
+    .reset(&target, ResetType::Hard, None)
+    .map_err(|v: git2::Error| error(&format!("Failed to reset to the commit '{}'.", hash), v))
+}
+
+// Takes back the last commit keeping its changes on the working tree
+pub fn undo_commit(repo: &Repository, hash: &str) -> Result<(), FastReleaseError> {
+  let parent: git2::Object = Oid::from_str(hash)
+    .and_then(|v: Oid| repo.find_commit(v))
+    .and_then(|v: git2::Commit| v.parent(0))
+    .map(|v: git2::Commit| v.into_object())
+    .map_err(|v: git2::Error| error(&format!("Failed to find the parent of '{}'.", hash), v))?;
+
+  repo
+    .reset(&parent, ResetType::Mixed, None)
+    .map_err(|v: git2::Error| error(&format!("Failed to undo the commit '{}'.", hash), v))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::TestRepo;
+
+  #[test]
+  fn tells_the_ancestors_of_a_commit() {
+    let test_repo: TestRepo = TestRepo::new();
+    test_repo.write("lib.rs", "lib");
+    let first: String = test_repo.commit("feat: add the library");
+    test_repo.write("lib.rs", "lib 2");
+    let second: String = test_repo.commit("fix: change the library");
+
+    assert!(is_ancestor(&test_repo.repo, &first, &second));
+    assert!(is_ancestor(&test_repo.repo, &second, &second));
+    assert!(!is_ancestor(&test_repo.repo, &second, &first));
+    assert!(!is_ancestor(&test_repo.repo, "not a hash", &second));
+  }
+
+  #[test]
+  fn gets_the_commit_of_a_remote_branch() {
+    let test_repo: TestRepo = TestRepo::new();
+    test_repo.write("lib.rs", "lib");
+    let hash: String = test_repo.commit("feat: add the library");
+
+    assert_eq!(
+      get_remote_hash(&test_repo.repo, "origin", "main").unwrap(),
+      None
+    );
+
+    test_repo.push();
+    test_repo.write("lib.rs", "lib 2");
+    test_repo.commit("fix: change the library");
+
+    assert_eq!(
+      get_remote_hash(&test_repo.repo, "origin", "main").unwrap(),
+      Some(hash)
+    );
+    assert_eq!(
+      get_remote_hash(&test_repo.repo, "origin", "next").unwrap(),
+      None
+    );
+    assert!(get_remote_hash(&test_repo.repo, "upstream", "main").is_err());
+  }
+
+  #[test]
+  fn resets_the_branch_and_the_working_tree() {
+    let test_repo: TestRepo = TestRepo::new();
+    test_repo.write("lib.rs", "lib");
+    let first: String = test_repo.commit("feat: add the library");
+    test_repo.write("lib.rs", "lib 2");
+    test_repo.write("main.rs", "main");
+    test_repo.commit("fix: change the library");
+
+    reset(&test_repo.repo, &first).unwrap();
+
+    assert_eq!(test_repo.head(), first);
+    assert_eq!(test_repo.read("lib.rs").as_deref(), Some("lib"));
+    assert_eq!(test_repo.read("main.rs"), None);
+    assert!(reset(&test_repo.repo, &"0".repeat(40)).is_err());
+  }
+
+  #[test]
+  fn creates_lightweight_and_annotated_tags() {
+    let test_repo: TestRepo = TestRepo::new();
+    test_repo.write("lib.rs", "lib");
+    let hash: String = test_repo.commit("feat: add the library");
+
+    create_tag(&test_repo.repo, "v1.0.0", &hash, None).unwrap();
+    create_tag(&test_repo.repo, "v1.0.1", &hash, Some("Release 1.0.1")).unwrap();
+
+    let lightweight: git2::Object = test_repo.repo.revparse_single("refs/tags/v1.0.0").unwrap();
+    assert_eq!(lightweight.kind(), Some(ObjectType::Commit));
+    let annotated: git2::Tag = test_repo
+      .repo
+      .revparse_single("refs/tags/v1.0.1")
+      .and_then(|v: git2::Object| v.peel_to_tag())
+      .unwrap();
+    assert_eq!(annotated.message(), Some("Release 1.0.1"));
+
+    let mut tags: Vec<(String, String)> = get_tags(&test_repo.repo)
+      .unwrap()
+      .into_iter()
+      .map(|v: Tag| (v.name, v.hash))
+      .collect();
+    tags.sort();
+    assert_eq!(
+      tags,
+      vec![
+        ("v1.0.0".to_string(), hash.clone()),
+        ("v1.0.1".to_string(), hash.clone())
+      ]
+    );
+    assert_eq!(get_tag_hash(&test_repo.repo, "v1.0.1"), Some(hash));
+    assert_eq!(get_tag_hash(&test_repo.repo, "v2.0.0"), None);
+  }
+
+  #[test]
+  fn keeps_an_existing_tag_only_on_the_same_commit() {
+    let test_repo: TestRepo = TestRepo::new();
+    test_repo.write("lib.rs", "lib");
+    let first: String = test_repo.commit("feat: add the library");
+    test_repo.write("lib.rs", "lib 2");
+    let second: String = test_repo.commit("fix: change the library");
+
+    create_tag(&test_repo.repo, "v1.0.0", &first, None).unwrap();
+    create_tag(&test_repo.repo, "v1.0.0", &first, Some("Release 1.0.0")).unwrap();
+
+    let result: Result<(), FastReleaseError> = create_tag(&test_repo.repo, "v1.0.0", &second, None);
+    assert!(result.unwrap_err().message.contains(&first));
+    assert_eq!(get_tag_hash(&test_repo.repo, "v1.0.0"), Some(first));
+  }
+
+  #[test]
+  fn force_pushes_a_branch_that_is_on_the_expected_commit() {
+    let test_repo: TestRepo = TestRepo::new();
+    test_repo.write("lib.rs", "lib");
+    let base: String = test_repo.commit("feat: add the library");
+    test_repo.write("lib.rs", "lib 2");
+    let hash: String = test_repo.commit("chore(release): 1.0.0");
+    test_repo.push();
+
+    force_push_with_lease(&test_repo.repo, "origin", "main", &base, &hash).unwrap();
+
+    assert_eq!(test_repo.remote_head(), Some(base));
+  }
+
+  #[test]
+  fn keeps_a_branch_that_moved_on_the_remote() {
+    let test_repo: TestRepo = TestRepo::new();
+    test_repo.write("lib.rs", "lib");
+    let base: String = test_repo.commit("feat: add the library");
+    test_repo.write("lib.rs", "lib 2");
+    let hash: String = test_repo.commit("chore(release): 1.0.0");
+    test_repo.write("lib.rs", "lib 3");
+    let on_top: String = test_repo.commit("fix: change the library");
+    test_repo.push();
+
+    let result: Result<(), FastReleaseError> =
+      force_push_with_lease(&test_repo.repo, "origin", "main", &base, &hash);
+    assert!(result.unwrap_err().message.contains(&on_top));
+
+    assert_eq!(test_repo.remote_head(), Some(on_top));
+  }
+}
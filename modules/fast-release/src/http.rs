@@ -0,0 +1,122 @@
+// Copyright (c) Toolbi Software. All rights reserved.
+// Check the README file in the project root for more information.
+
+use crate::error::{FastReleaseError, FastReleaseErrorBuilder};
+use serde::de::DeserializeOwned;
+use std::{
+  env,
+  io::Error,
+  time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use ureq::{Agent, AgentBuilder, Response};
+use url::Url;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+// Uploads of big assets take a while
+const READ_TIMEOUT: Duration = Duration::from_secs(300);
+const MAX_BODY_LENGTH: usize = 1000;
+
+pub fn agent() -> Agent {
+  AgentBuilder::new()
+    .timeout_connect(CONNECT_TIMEOUT)
+    .timeout_read(READ_TIMEOUT)
+    .user_agent(&format!("fast-release/{}", env!("CARGO_PKG_VERSION")))
+    .build()
+}
+
+// The first variable with a value, like the token of a hosting service
+pub fn get_env(names: &[&str]) -> Option<String> {
+  names
+    .iter()
+    .filter_map(|v: &&str| env::var(v).ok())
+    .find(|v: &String| !v.trim().is_empty())
+}
+
+// The domain of a base URL with its port, like 'git.example.com:8443'
+pub fn get_domain(url: &str) -> Option<String> {
+  let url: Url = Url::parse(url).ok()?;
+  let host: &str = url.host_str()?;
+
+  Some(match url.port() {
+    Some(port) => format!("{}:{}", host, port),
+    None => host.to_string(),
+  })
+}
+
+// A 'multipart/form-data' body with a single file, for the APIs that don't take the raw content.
+// Returns the content type, with the boundary, and the body.
+pub fn multipart(field: &str, file_name: &str, content: &[u8]) -> (String, Vec<u8>) {
+  let boundary: String = format!(
+    "fast-release-{}",
+    SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map(|v: Duration| v.as_nanos())
+      .unwrap_or_default()
+  );
+  let file_name: String = file_name.replace(['"', '\r', '\n'], "_");
+
+  let mut body: Vec<u8> = format!(
+    "--{}\r\nContent-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\nContent-Type: application/octet-stream\r\n\r\n",
+    boundary, field, file_name
+  )
+  .into_bytes();
+  body.extend_from_slice(content);
+  body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+
+  (format!("multipart/form-data; boundary={}", boundary), body)
+}
+
+pub fn status(error: &ureq::Error) -> Option<u16> {
+  match error {
+    ureq::Error::Status(status, _) => Some(*status),
+    ureq::Error::Transport(_) => None,
+  }
+}
+
+// The APIs explain on the body of the response why a request was rejected
+pub fn error(message: &str, category: &str, error: ureq::Error) -> FastReleaseError {
+  let details: String = match error {
+    ureq::Error::Status(status, response) => {
+      let body: String = response.into_string().unwrap_or_default();
+      let body: String = match body.char_indices().nth(MAX_BODY_LENGTH) {
+        Some((index, _)) => format!("{}...", &body[..index]),
+        None => body,
+      };
+      format!("The server responded with the status {}: {}", status, body)
+    }
+    ureq::Error::Transport(transport) => transport.to_string(),
+  };
+
+  FastReleaseErrorBuilder::new(message)
+    .category(category)
+    .error(Error::other(details))
+    .get()
+}
+
+// The URL of the next page of a list, from a header like '<https://...&page=2>; rel="next"'
+pub fn get_next_page(response: &Response) -> Option<String> {
+  response.header("Link")?.split(',').find_map(|link: &str| {
+    let mut parts: std::str::Split<char> = link.split(';');
+    let url: &str = parts.next()?.trim();
+    parts.any(|v: &str| v.trim() == "rel=\"next\"").then(|| {
+      url
+        .trim_start_matches('<')
+        .trim_end_matches('>')
+        .to_string()
+    })
+  })
+}
+
+pub fn read_json<T: DeserializeOwned>(
+  response: Response,
+  category: &str,
+) -> Result<T, FastReleaseError> {
+  let url: String = response.get_url().to_string();
+
+  response.into_json::<T>().map_err(|v: Error| {
+    FastReleaseErrorBuilder::new(&format!("Failed to read the response of '{}'.", url))
+      .category(category)
+      .error(v)
+      .get()
+  })
+}
@@ -0,0 +1,767 @@
+// Copyright (c) Toolbi Software. All rights reserved.
+// Check the README file in the project root for more information.
+
+use crate::{
+  analyzer::{commit::Commit, rules::ReleaseType},
+  config::{ConfigGit, ConfigProject},
+  error::{FastReleaseError, FastReleaseErrorBuilder},
+  git::{self, notes::Journal},
+  modules::{self, Context, Module, NextRelease, ReleaseInfo, Rollback},
+  project::{self, ProjectRelease, ReleaseScope},
+  release::LastRelease,
+};
+use env_ci::CiEnv;
+use git2::Repository;
+use std::collections::HashMap;
+use tracing::{debug, info, warn};
+
+// Project name -> loaded modules of the project
+type ProjectModules = HashMap<String, Vec<Box<dyn Module>>>;
+
+const PUSH_STEP: &str = "push";
+
+fn load_modules(scope: &ReleaseScope) -> Result<ProjectModules, FastReleaseError> {
+  let mut result: ProjectModules = HashMap::new();
+
+  for project in &scope.config.projects {
+    let mut loaded: Vec<Box<dyn Module>> = Vec::new();
+    for module in project::get_modules(scope.config, project) {
+      loaded.push(modules::load(&module)?);
+    }
+
+    debug!(
+      message = format!(
+        "The project '{}' runs the modules {}.",
+        project.name,
+        loaded
+          .iter()
+          .map(Box::as_ref)
+          .map(|v: &dyn Module| format!("'{}'", v.name()))
+          .collect::<Vec<String>>()
+          .join(", ")
+      ),
+      category = "LIFECYCLE"
+    );
+
+    result.insert(project.name.clone(), loaded);
+  }
+
+  Ok(result)
+}
+
+fn get_context(scope: &ReleaseScope, ci_env: &CiEnv, project: &ConfigProject) -> Context {
+  Context {
+    cwd: git::repo::get_root(scope.repo),
+    dry_run: scope.config.dry_run,
+    ci_env: ci_env.clone(),
+    git: scope.config.git.clone(),
+    branch: scope.branch.clone(),
+    project: project.clone(),
+    commits: Vec::new(),
+    last_release: None,
+    next_release: None,
+    releases: Vec::new(),
+    rollback: None,
+  }
+}
+
+// The modules on the journal of a release, where the repeated ones get their position, like
+// 'webhook#2'
+fn get_module_keys(modules: &[Box<dyn Module>]) -> Vec<String> {
+  let mut names: Vec<&str> = Vec::new();
+  for module in modules {
+    names.push(module.name());
+  }
+
+  names
+    .iter()
+    .enumerate()
+    .map(|(index, name): (usize, &&str)| {
+      match names[..index].iter().filter(|v: &&&str| *v == name).count() {
+        0 => name.to_string(),
+        count => format!("{}#{}", name, count + 1),
+      }
+    })
+    .collect()
+}
+
+// Creates the tag of the release on its commit and pushes it, so it exists before the modules
+// publish the release. The journal of the release starts with the tag.
+fn tag(
+  scope: &ReleaseScope,
+  context: &Context,
+  journal: &mut Journal,
+) -> Result<(), FastReleaseError> {
+  let next_release: &NextRelease = context.get_next_release("LIFECYCLE")?;
+  let git_config: &ConfigGit = &scope.config.git;
+
+  let message: Option<String> = git_config
+    .tag_message
+    .as_deref()
+    .map(|v: &str| context.render(v));
+  let kind: &str = match message {
+    Some(_) => "annotated",
+    None => "lightweight",
+  };
+
+  if context.dry_run {
+    info!(
+      "Would create the {} tag '{}' on the commit '{}'{}.",
+      kind,
+      next_release.tag,
+      next_release.hash,
+      match git_config.push {
+        true => format!(" and push it to the remote '{}'", git_config.remote),
+        false => String::new(),
+      }
+    );
+    return Ok(());
+  }
+
+  git::repo::create_tag(
+    scope.repo,
+    &next_release.tag,
+    &next_release.hash,
+    message.as_deref(),
+  )?;
+  git::notes::add_channel(
+    scope.repo,
+    &next_release.tag,
+    &next_release.hash,
+    next_release.channel.as_deref(),
+  )?;
+  git::notes::set_journal(
+    scope.repo,
+    &next_release.tag,
+    &next_release.hash,
+    Some(journal),
+  )?;
+
+  if !git_config.push {
+    info!(
+      "Created the {} tag '{}' without pushing it.",
+      kind, next_release.tag
+    );
+    return Ok(());
+  }
+
+  push_tag(scope, next_release, journal)?;
+  info!(
+    "Created the {} tag '{}' and pushed it to the remote '{}'.",
+    kind, next_release.tag, git_config.remote
+  );
+
+  Ok(())
+}
+
+fn push_tag(
+  scope: &ReleaseScope,
+  next_release: &NextRelease,
+  journal: &mut Journal,
+) -> Result<(), FastReleaseError> {
+  git::repo::push(
+    scope.repo,
+    &scope.config.git.remote,
+    &[format!("refs/tags/{0}:refs/tags/{0}", next_release.tag)],
+  )?;
+
+  journal.add(PUSH_STEP);
+  git::notes::set_journal(
+    scope.repo,
+    &next_release.tag,
+    &next_release.hash,
+    Some(journal),
+  )?;
+  git::notes::push(scope.repo, &scope.config.git.remote)
+}
+
+// Runs the steps of the modules that aren't on the journal yet, and drops the journal once every
+// step is done
+fn run_steps(
+  scope: &ReleaseScope,
+  modules: &mut [Box<dyn Module>],
+  context: &mut Context,
+  journal: &mut Journal,
+) -> Result<(), FastReleaseError> {
+  let next_release: NextRelease = context.get_next_release("LIFECYCLE")?.clone();
+  let keys: Vec<String> = get_module_keys(modules);
+
+  for (index, key) in keys.iter().enumerate() {
+    let step: String = format!("publish:{}", key);
+    if journal.has(&step) {
+      debug!(
+        message = format!("The module '{}' already published the release.", key),
+        category = "LIFECYCLE"
+      );
+      continue;
+    }
+
+    let release: Option<ReleaseInfo> = match modules[index].publish(context) {
+      Ok(release) => release,
+      Err(error) => {
+        if scope.config.rollback {
+          let rollback: Rollback = rollback(
+            scope,
+            modules,
+            context,
+            journal,
+            &keys,
+            index,
+            &next_release,
+          );
+          context.rollback = Some(rollback);
+        }
+        return Err(error);
+      }
+    };
+
+    if let Some(release) = release {
+      info!(
+        "Published the release '{}' with the module '{}'.",
+        release.name, release.module
+      );
+      context.releases.push(release.clone());
+      journal.releases.push(release);
+    }
+
+    journal.add(&step);
+    git::notes::set_journal(
+      scope.repo,
+      &next_release.tag,
+      &next_release.hash,
+      Some(journal),
+    )?;
+  }
+
+  for (module, key) in modules.iter_mut().zip(&keys) {
+    let step: String = format!("success:{}", key);
+    if journal.has(&step) {
+      continue;
+    }
+
+    module.success(context)?;
+
+    journal.add(&step);
+    git::notes::set_journal(
+      scope.repo,
+      &next_release.tag,
+      &next_release.hash,
+      Some(journal),
+    )?;
+  }
+
+  git::notes::set_journal(scope.repo, &next_release.tag, &next_release.hash, None)
+}
+
+// The release commit is only taken back while it's the last one of the branch, as the commits on
+// top of it would be lost
+fn reset_release_commit(
+  scope: &ReleaseScope,
+  hash: &str,
+  base: &str,
+) -> Result<String, FastReleaseError> {
+  let branch: &str = &scope.branch.name;
+  let on_top: FastReleaseError = FastReleaseErrorBuilder::new(&format!(
+    "The branch '{}' has commits on top of the release commit '{}', so revert it by hand.",
+    branch, hash
+  ))
+  .category("LIFECYCLE")
+  .get();
+
+  if git::repo::get_head(scope.repo)? != hash {
+    return Err(on_top);
+  }
+
+  if scope.config.git.push {
+    let remote: &str = &scope.config.git.remote;
+    if git::repo::get_remote_hash(scope.repo, remote, branch)?.as_deref() != Some(hash) {
+      return Err(on_top);
+    }
+    // Cancelled when a commit lands on top of the release commit after the check above
+    git::repo::force_push_with_lease(scope.repo, remote, branch, base, hash)?;
+  }
+  git::repo::reset(scope.repo, base)?;
+
+  Ok(format!(
+    "Took back the release commit '{}' from the branch '{}'.",
+    hash, branch
+  ))
+}
+
+// Moves the branch to the commit of a release that didn't complete, so the projects are
+// analyzed from there and the release is resumed
+pub fn move_to_release(repo: &Repository, hash: &str) -> Result<(), FastReleaseError> {
+  info!("Moving to the commit '{}' to resume its release.", hash);
+  git::repo::reset(repo, hash)
+}
+
+// Takes back a release that failed on the 'publish' step: what the modules published, then the
+// tag and the release commit. When a module that published can't take back its release, the tag
+// is kept so a new run resumes the release instead. The module that failed runs again either way.
+fn rollback(
+  scope: &ReleaseScope,
+  modules: &mut [Box<dyn Module>],
+  context: &mut Context,
+  journal: &mut Journal,
+  keys: &[String],
+  failed: usize,
+  next_release: &NextRelease,
+) -> Rollback {
+  let mut result: Rollback = Rollback::default();
+  let mut kept: bool = false;
+
+  // The module that failed can leave part of its release, like a draft without its assets
+  for index in (0..=failed).rev() {
+    let step: String = format!("publish:{}", keys[index]);
+    if index != failed && !journal.has(&step) {
+      continue;
+    }
+
+    match modules[index].rollback(context) {
+      Ok(done) => {
+        result.done.extend(done);
+        let name: String = modules[index].name().to_string();
+        journal.remove(&step);
+        journal.releases.retain(|v: &ReleaseInfo| v.module != name);
+        context.releases.retain(|v: &ReleaseInfo| v.module != name);
+      }
+      Err(error) => {
+        kept |= index != failed;
+        result.manual.push(error.message);
+      }
+    }
+  }
+
+  let tag: &str = &next_release.tag;
+  if kept {
+    result.manual.push(format!(
+      "The tag '{}' is kept, so a new run resumes the release once the rest is cleaned up.",
+      tag
+    ));
+    if let Err(error) = git::notes::set_journal(scope.repo, tag, &next_release.hash, Some(journal))
+    {
+      warn!("Failed to write the journal of the release: {}", error);
+    }
+    return result;
+  }
+
+  if scope.config.git.push && journal.has(PUSH_STEP) {
+    let remote: &str = &scope.config.git.remote;
+    if let Err(error) = git::repo::push(scope.repo, remote, &[format!(":refs/tags/{}", tag)]) {
+      result.manual.push(format!(
+        "{} Delete the tag '{}' from the remote '{}' by hand.",
+        error.message, tag, remote
+      ));
+      return result;
+    }
+    result.done.push(format!(
+      "Deleted the tag '{}' from the remote '{}'.",
+      tag, remote
+    ));
+  }
+
+  let local: Result<(), FastReleaseError> = git::repo::delete_tag(scope.repo, tag)
+    .and_then(|_| git::notes::remove(scope.repo, tag, &next_release.hash));
+  match local {
+    Ok(_) => result
+      .done
+      .push(format!("Deleted the local tag '{}'.", tag)),
+    Err(error) => result.manual.push(error.message),
+  }
+
+  if let Some(base) = &journal.base {
+    match reset_release_commit(scope, &next_release.hash, base) {
+      Ok(done) => result.done.push(done),
+      Err(error) => result.manual.push(error.message),
+    }
+  }
+
+  result
+}
+
+// Runs the steps that come after the tag. The journal is pushed even when a step fails, so a new
+// run resumes from there.
+fn publish(
+  scope: &ReleaseScope,
+  modules: &mut [Box<dyn Module>],
+  context: &mut Context,
+  journal: &mut Journal,
+) -> Result<(), FastReleaseError> {
+  let result: Result<(), FastReleaseError> = run_steps(scope, modules, context, journal);
+
+  if scope.config.git.push {
+    if let Err(error) = git::notes::push(scope.repo, &scope.config.git.remote) {
+      warn!("Failed to push the journal of the release: {}", error);
+    }
+  }
+
+  result
+}
+
+// Resumes a release that failed after its tag was created. The 'prepare' step isn't run again, so
+// only the changes it committed are on the tagged commit.
+fn resume(
+  scope: &ReleaseScope,
+  modules: &mut [Box<dyn Module>],
+  context: &mut Context,
+  mut journal: Journal,
+) -> Result<(), FastReleaseError> {
+  let next_release: NextRelease = context.get_next_release("LIFECYCLE")?.clone();
+
+  if context.dry_run {
+    info!(
+      "Would resume the release '{}' of the project '{}', which did the steps {}.",
+      next_release.tag,
+      context.project.name,
+      match journal.steps.is_empty() {
+        true => "'tag'".to_string(),
+        false => format!("'tag', '{}'", journal.steps.join("', '")),
+      }
+    );
+    return Ok(());
+  }
+
+  if scope.config.git.push && !journal.has(PUSH_STEP) {
+    push_tag(scope, &next_release, &mut journal)?;
+    info!(
+      "Pushed the tag '{}' to the remote '{}'.",
+      next_release.tag, scope.config.git.remote
+    );
+  }
+
+  publish(scope, modules, context, &mut journal)
+}
+
+// Runs the steps that come after the analysis for a single project
+fn release(
+  scope: &ReleaseScope,
+  modules: &mut [Box<dyn Module>],
+  context: &mut Context,
+) -> Result<(), FastReleaseError> {
+  for module in modules.iter_mut() {
+    module.verify_release(context)?;
+  }
+
+  let mut notes: Vec<String> = Vec::new();
+  for module in modules.iter_mut() {
+    if let Some(value) = module.generate_notes(context)? {
+      if !value.is_empty() {
+        notes.push(value);
+      }
+    }
+  }
+  if let Some(next_release) = &mut context.next_release {
+    next_release.notes = notes.join("\n\n");
+  }
+
+  let next_release: &NextRelease = context.get_next_release("LIFECYCLE")?;
+  let mut journal: Journal = Journal {
+    release_type: next_release.release_type,
+    notes: next_release.notes.clone(),
+    last_release: context.last_release.clone(),
+    base: None,
+    steps: Vec::new(),
+    releases: Vec::new(),
+  };
+
+  if context.dry_run {
+    if let Some(next_release) = context
+      .next_release
+      .as_ref()
+      .filter(|v: &&NextRelease| !v.notes.is_empty())
+    {
+      info!(
+        "The release notes of the project '{}' are:\n{}",
+        context.project.name, next_release.notes
+      );
+    }
+    tag(scope, context, &mut journal)?;
+    info!(
+      "Skipping the 'prepare', 'publish' and 'success' steps of the project '{}' on dry run mode.",
+      context.project.name
+    );
+    return Ok(());
+  }
+
+  // Projects released before this one may have moved HEAD already, so only a commit made by the
+  // 'prepare' step of this project can be taken back
+  let base: String = git::repo::get_head(scope.repo)?;
+  for module in modules.iter_mut() {
+    module.prepare(context)?;
+  }
+
+  // Modules like 'git' commit the release, so the tag goes on the HEAD that comes out of the
+  // 'prepare' step
+  let head: String = git::repo::get_head(scope.repo)?;
+  if let Some(next_release) = &mut context.next_release {
+    journal.base = Some(base).filter(|v: &String| *v != head);
+    next_release.hash = head;
+  }
+
+  tag(scope, context, &mut journal)?;
+  publish(scope, modules, context, &mut journal)
+}
+
+// Adds a release merged from a branch of another channel to the channel of the current branch
+fn add_channel(
+  scope: &ReleaseScope,
+  modules: &mut [Box<dyn Module>],
+  context: &mut Context,
+) -> Result<(), FastReleaseError> {
+  let next_release: NextRelease = context.get_next_release("LIFECYCLE")?.clone();
+  let channel: &str = next_release.channel.as_deref().unwrap_or("default");
+
+  if context.dry_run {
+    info!(
+      "Would add the release '{}' to the channel '{}'.",
+      next_release.tag, channel
+    );
+    return Ok(());
+  }
+
+  for module in modules.iter_mut() {
+    if let Some(release) = module.add_channel(context)? {
+      info!(
+        "Added the release '{}' to the channel '{}' with the module '{}'.",
+        release.name, channel, release.module
+      );
+      context.releases.push(release);
+    }
+  }
+
+  git::notes::add_channel(
+    scope.repo,
+    &next_release.tag,
+    &next_release.hash,
+    next_release.channel.as_deref(),
+  )?;
+  if scope.config.git.push {
+    git::notes::push(scope.repo, &scope.config.git.remote)?;
+  }
+
+  for module in modules.iter_mut() {
+    module.success(context)?;
+  }
+
+  Ok(())
+}
+
+// Lets every module know about a failed release. Errors on this step are only logged, as the
+// original error is the one that matters.
+fn fail(modules: &mut [Box<dyn Module>], context: &Context, error: &FastReleaseError) {
+  if context.dry_run {
+    return;
+  }
+
+  if let Some(rollback) = &context.rollback {
+    for done in &rollback.done {
+      info!("Rolled back: {}", done);
+    }
+    for manual in &rollback.manual {
+      warn!("Clean up by hand: {}", manual);
+    }
+  }
+
+  for module in modules.iter_mut() {
+    if let Err(fail_error) = module.fail(context, std::slice::from_ref(error)) {
+      warn!(
+        "The module '{}' failed on the 'fail' step: {}",
+        module.name(),
+        fail_error
+      );
+    }
+  }
+}
+
+pub fn run(scope: &ReleaseScope, ci_env: &CiEnv) -> Result<Vec<ProjectRelease>, FastReleaseError> {
+  let mut modules: ProjectModules = load_modules(scope)?;
+
+  // The channels and the journals of the releases are kept on the remote. Without them, the
+  // releases are added to the channels again and the releases that failed aren't resumed.
+  if scope.config.git.push {
+    if let Err(error) = git::notes::fetch(scope.repo, &scope.config.git.remote) {
+      warn!("Failed to fetch the notes of the releases: {}", error);
+    }
+  }
+
+  for project in &scope.config.projects {
+    let context: Context = get_context(scope, ci_env, project);
+    let project_modules: &mut Vec<Box<dyn Module>> = match modules.get_mut(&project.name) {
+      Some(project_modules) => project_modules,
+      None => continue,
+    };
+
+    if let Err(error) = project_modules
+      .iter_mut()
+      .try_for_each(|v: &mut Box<dyn Module>| v.verify_conditions(&context))
+    {
+      fail(project_modules, &context, &error);
+      return Err(error);
+    }
+  }
+
+  let project_releases: Vec<ProjectRelease> = project::analyze_all(
+    scope,
+    &mut |project: &ConfigProject, commits: &[Commit], last_release: Option<&LastRelease>| {
+      let mut context: Context = get_context(scope, ci_env, project);
+      context.commits = commits.to_vec();
+      context.last_release = last_release.cloned();
+
+      let mut result: ReleaseType = ReleaseType::None;
+      for module in modules.get_mut(&project.name).into_iter().flatten() {
+        if let Some(release_type) = module.analyze_commits(&context)? {
+          result = result.max(release_type);
+        }
+      }
+
+      Ok(result)
+    },
+  )?;
+
+  for project_release in &project_releases {
+    let project_modules: &mut Vec<Box<dyn Module>> =
+      match modules.get_mut(&project_release.project.name) {
+        Some(project_modules) => project_modules,
+        None => continue,
+      };
+
+    if let Some(release_to_resume) = &project_release.release_to_resume {
+      let mut context: Context = get_context(scope, ci_env, &project_release.project);
+      context.commits = release_to_resume.commits.clone();
+      context.last_release = release_to_resume.journal.last_release.clone();
+      context.releases = release_to_resume.journal.releases.clone();
+      context.next_release = Some(NextRelease {
+        version: release_to_resume.release.version.clone(),
+        tag: release_to_resume.release.tag.clone(),
+        channel: scope.branch.channel.clone(),
+        release_type: release_to_resume.journal.release_type,
+        hash: release_to_resume.release.hash.clone(),
+        notes: release_to_resume.journal.notes.clone(),
+      });
+
+      if let Err(error) = resume(
+        scope,
+        project_modules,
+        &mut context,
+        release_to_resume.journal.clone(),
+      ) {
+        fail(project_modules, &context, &error);
+        return Err(error);
+      }
+    }
+
+    if let Some(release_to_add) = &project_release.release_to_add {
+      let mut context: Context = get_context(scope, ci_env, &project_release.project);
+      context.last_release = project_release.last_release.clone();
+      context.next_release = Some(NextRelease {
+        version: release_to_add.version.clone(),
+        tag: release_to_add.tag.clone(),
+        channel: scope.branch.channel.clone(),
+        release_type: ReleaseType::None,
+        hash: release_to_add.hash.clone(),
+        notes: String::new(),
+      });
+
+      if let Err(error) = add_channel(scope, project_modules, &mut context) {
+        fail(project_modules, &context, &error);
+        return Err(error);
+      }
+    }
+
+    let (version, tag) = match (&project_release.next_version, &project_release.next_tag) {
+      (Some(version), Some(tag)) => (version, tag),
+      _ => continue,
+    };
+
+    let mut context: Context = get_context(scope, ci_env, &project_release.project);
+    context.commits = project_release.commits.clone();
+    context.last_release = project_release.last_release.clone();
+    context.next_release = Some(NextRelease {
+      version: version.clone(),
+      tag: tag.clone(),
+      channel: scope.branch.channel.clone(),
+      release_type: project_release.release_type,
+      hash: scope.head.to_string(),
+      notes: String::new(),
+    });
+
+    if let Err(error) = release(scope, project_modules, &mut context) {
+      fail(project_modules, &context, &error);
+      return Err(error);
+    }
+
+    if !context.releases.is_empty() {
+      debug!(
+        message = format!(
+          "The project '{}' was published as {}.",
+          project_release.project.name,
+          context
+            .releases
+            .iter()
+            .map(|v: &ReleaseInfo| format!("'{}'", v.name))
+            .collect::<Vec<String>>()
+            .join(", ")
+        ),
+        category = "LIFECYCLE"
+      );
+    }
+  }
+
+  Ok(project_releases)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    config::Config,
+    testing::{self, TestRepo},
+  };
+
+  const CONFIG: &str = r#"
+version: 1
+tag_format: ${project}@${version}
+branches: [main]
+rollback: true
+projects:
+  - name: a
+    path: a
+    modules:
+      - changelog
+      - name: git
+        assets: [CHANGELOG.md]
+  - name: b
+    path: b
+    modules:
+      - name: exec
+        publish: exit 1
+"#;
+
+  #[test]
+  fn rollback_keeps_the_release_commit_of_other_projects() {
+    let test_repo: TestRepo = TestRepo::new();
+    test_repo.write("a/lib.rs", "a");
+    test_repo.write("b/lib.rs", "b");
+    test_repo.commit("feat: add the projects");
+    test_repo.push();
+
+    let config: Config = testing::get_config(CONFIG);
+    let result: Result<Vec<ProjectRelease>, FastReleaseError> =
+      testing::with_scope(&test_repo, &config, |scope: &ReleaseScope| {
+        run(scope, &testing::get_ci_env())
+      });
+    assert!(result.is_err());
+
+    // The release commit of 'a' stays, along with its tag, while the tag of 'b' is taken back
+    let head: String = test_repo.head();
+    assert!(test_repo.read("a/CHANGELOG.md").is_some());
+    assert_eq!(test_repo.remote_head(), Some(head.clone()));
+    assert_eq!(
+      git::repo::get_tag_hash(&test_repo.repo, "a@1.0.0"),
+      Some(head)
+    );
+    assert!(test_repo.has_remote_tag("a@1.0.0"));
+    assert!(!test_repo.has_tag("b@1.0.0"));
+    assert!(!test_repo.has_remote_tag("b@1.0.0"));
+  }
+}
@@ -4,14 +4,35 @@
 // TODO Maybe create a config file if the config file doesn't exist or it's empty
 // Could be an argument on the CLI too
 
+mod analyzer;
+mod branches;
 mod cli;
 mod config;
 mod constants;
+mod dependencies;
 mod error;
+mod git;
+mod http;
+mod lifecycle;
+mod modules;
+mod notify;
+mod project;
+mod release;
+mod tag_format;
+#[cfg(test)]
+mod testing;
+mod verify;
 
-use crate::{cli::CliParams, config::Config};
+use crate::{
+  branches::Branch,
+  cli::CliParams,
+  config::Config,
+  project::{ProjectRelease, ReleaseScope},
+};
 use commonlib::{logger::LoggerBuilder, Logger};
+use env_ci::CiEnv;
 use error::{soft_panic, FastReleaseError, FastReleaseErrorBuilder};
+use git2::Repository;
 use tracing::info;
 
 fn init_logger() -> Result<(), FastReleaseError> {
@@ -35,6 +56,59 @@ fn run() -> Result<(), FastReleaseError> {
   let cli_params: CliParams = cli::get();
   let config: Config = config::get(&cli_params)?;
 
+  let ci_env: CiEnv = env_ci::get();
+  if ci_env.is_pull_request {
+    info!("The run was triggered by a pull request, so nothing is released. Skipping.");
+    return Ok(());
+  }
+
+  let repo: Repository = git::repo::open(&ci_env)?;
+  let branch_name: String = git::branch::get_current(&repo, &ci_env)?;
+
+  let branch_names: Vec<String> = {
+    let mut names: Vec<String> = git::branch::get_all(&repo, &config.git.remote)?;
+    if !names.contains(&branch_name) {
+      names.push(branch_name.clone());
+    }
+    names
+  };
+  let branches: Vec<Branch> = branches::expand(&config, &branch_names)?;
+
+  let branch: &Branch = verify::get_branch(&branches, &branch_name)?;
+  info!("Running on the branch '{}'.", branch.name);
+
+  info!(
+    "Releasing on the channel '{}'.",
+    branch.channel.as_deref().unwrap_or("default")
+  );
+
+  if let Some(hash) = verify::run(&repo, &config, branch)? {
+    lifecycle::move_to_release(&repo, &hash)?;
+  }
+
+  let head: String = git::repo::get_head(&repo)?;
+
+  let project_releases: Vec<ProjectRelease> = lifecycle::run(
+    &ReleaseScope {
+      repo: &repo,
+      config: &config,
+      branches: &branches,
+      branch,
+      head: &head,
+    },
+    &ci_env,
+  )?;
+
+  let released: usize = project_releases
+    .iter()
+    .filter(|v: &&ProjectRelease| v.next_version.is_some())
+    .count();
+  info!(
+    "Released {} of {} projects.",
+    released,
+    project_releases.len()
+  );
+
   Ok(())
 }
 
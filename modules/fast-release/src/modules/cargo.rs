@@ -0,0 +1,729 @@
+// Copyright (c) Toolbi Software. All rights reserved.
+// Check the README file in the project root for more information.
+
+use super::{get_options, Context, Module, NextRelease};
+use crate::{
+  config::ConfigModule,
+  error::{FastReleaseError, FastReleaseErrorBuilder},
+};
+use serde::Deserialize;
+use std::{
+  fs,
+  io::Error,
+  path::{Path, PathBuf},
+};
+use toml_edit::{DocumentMut, Item, TableLike, Value};
+use tracing::{debug, info};
+
+pub const NAME: &str = "cargo";
+
+pub const MANIFEST: &str = "Cargo.toml";
+const LOCKFILE: &str = "Cargo.lock";
+pub const DEPENDENCY_TABLES: [&str; 3] = ["dependencies", "dev-dependencies", "build-dependencies"];
+// The two character operators go first, so '<=1.0' isn't read as '<'
+const REQUIREMENT_OPERATORS: [&str; 7] = [">=", "<=", "=", "^", "~", ">", "<"];
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CargoOptions {
+  #[serde(default = "default_true")]
+  lockfile: bool,
+  #[serde(default = "default_true")]
+  dependents: bool,
+}
+
+fn default_true() -> bool {
+  true
+}
+
+pub struct CargoModule {
+  options: CargoOptions,
+}
+
+fn error(message: &str, error: Option<Error>) -> FastReleaseError {
+  let builder: FastReleaseErrorBuilder = FastReleaseErrorBuilder::new(message).category("CARGO");
+  match error {
+    Some(error) => builder.error(error).get(),
+    None => builder.get(),
+  }
+}
+
+pub fn read(path: &Path) -> Result<DocumentMut, FastReleaseError> {
+  let content: String = fs::read_to_string(path).map_err(|v: Error| {
+    error(
+      &format!("Failed to read the file '{}'.", path.display()),
+      Some(v),
+    )
+  })?;
+
+  content
+    .parse::<DocumentMut>()
+    .map_err(|v: toml_edit::TomlError| {
+      error(
+        &format!("Failed to parse the file '{}'.", path.display()),
+        Some(Error::other(v)),
+      )
+    })
+}
+
+fn write(path: &Path, document: &DocumentMut) -> Result<(), FastReleaseError> {
+  fs::write(path, document.to_string()).map_err(|v: Error| {
+    error(
+      &format!("Failed to write the file '{}'.", path.display()),
+      Some(v),
+    )
+  })
+}
+
+// Replaces a string keeping the whitespace and comments around it
+pub fn set_string(item: &mut Item, value: &str) {
+  let mut new_value: Value = Value::from(value);
+  if let Some(old_value) = item.as_value() {
+    *new_value.decor_mut() = old_value.decor().clone();
+  }
+  *item = Item::Value(new_value);
+}
+
+// Keeps the operator of a requirement like '^1.2.0' or '=1.2.0'
+fn get_requirement(old_requirement: &str, version: &str) -> String {
+  let operator: &str = REQUIREMENT_OPERATORS
+    .iter()
+    .find(|v: &&&str| old_requirement.trim().starts_with(**v))
+    .copied()
+    .unwrap_or("");
+
+  format!("{}{}", operator, version)
+}
+
+pub fn get_package_name(manifest: &DocumentMut) -> Option<String> {
+  manifest
+    .get("package")
+    .and_then(|v: &Item| v.get("name"))
+    .and_then(|v: &Item| v.as_str())
+    .map(|v: &str| v.to_string())
+}
+
+fn inherits_version(manifest: &DocumentMut) -> bool {
+  manifest
+    .get("package")
+    .and_then(|v: &Item| v.get("version"))
+    .and_then(|v: &Item| v.get("workspace"))
+    .and_then(|v: &Item| v.as_bool())
+    .unwrap_or(false)
+}
+
+pub fn is_workspace(manifest: &DocumentMut) -> bool {
+  manifest
+    .get("workspace")
+    .is_some_and(|v: &Item| v.is_table_like())
+}
+
+// Looks for the manifest with a '[workspace]' table, from the project up to the repository root
+pub fn find_workspace(
+  project_path: &Path,
+  root: &Path,
+) -> Result<Option<PathBuf>, FastReleaseError> {
+  let mut current: Option<&Path> = Some(project_path);
+
+  while let Some(path) = current {
+    let manifest_path: PathBuf = path.join(MANIFEST);
+    if manifest_path.is_file() && is_workspace(&read(&manifest_path)?) {
+      return Ok(Some(manifest_path));
+    }
+
+    if path == root {
+      break;
+    }
+    current = path.parent();
+  }
+
+  Ok(None)
+}
+
+pub fn get_workspace_members(workspace_path: &Path, workspace: &DocumentMut) -> Vec<PathBuf> {
+  let root: &Path = workspace_path.parent().unwrap_or(Path::new("."));
+  let get_patterns = |key: &str| -> Vec<String> {
+    workspace
+      .get("workspace")
+      .and_then(|v: &Item| v.get(key))
+      .and_then(|v: &Item| v.as_array())
+      .map(|v: &toml_edit::Array| {
+        v.iter()
+          .filter_map(|v: &Value| v.as_str())
+          .map(|v: &str| v.to_string())
+          .collect()
+      })
+      .unwrap_or_default()
+  };
+
+  let excluded: Vec<PathBuf> = get_patterns("exclude")
+    .iter()
+    .map(|v: &String| root.join(v))
+    .collect();
+
+  let mut result: Vec<PathBuf> = vec![workspace_path.to_path_buf()];
+  for pattern in get_patterns("members") {
+    let paths: glob::Paths = match glob::glob(&root.join(&pattern).to_string_lossy()) {
+      Ok(paths) => paths,
+      Err(_) => continue,
+    };
+
+    for path in paths.flatten() {
+      let manifest_path: PathBuf = path.join(MANIFEST);
+      if manifest_path.is_file() && !excluded.contains(&path) && !result.contains(&manifest_path) {
+        result.push(manifest_path);
+      }
+    }
+  }
+
+  result
+}
+
+// Updates the requirement of the path dependencies on the package that have a version
+fn update_dependencies(table: &mut dyn TableLike, package: &str, version: &str) -> bool {
+  let mut updated: bool = false;
+
+  for key in DEPENDENCY_TABLES {
+    let dependencies: &mut dyn TableLike = match table
+      .get_mut(key)
+      .and_then(|v: &mut Item| v.as_table_like_mut())
+    {
+      Some(dependencies) => dependencies,
+      None => continue,
+    };
+
+    for (name, dependency) in dependencies.iter_mut() {
+      let dependency_package: &str = dependency
+        .get("package")
+        .and_then(|v: &Item| v.as_str())
+        .unwrap_or(name.get());
+      if dependency_package != package || dependency.get("path").is_none() {
+        continue;
+      }
+
+      if let Some(requirement) = dependency.get_mut("version") {
+        let new_requirement: String = get_requirement(requirement.as_str().unwrap_or(""), version);
+        set_string(requirement, &new_requirement);
+        updated = true;
+      }
+    }
+  }
+
+  updated
+}
+
+// Includes the target specific tables, like '[target.'cfg(unix)'.dependencies]', and the
+// '[workspace.dependencies]' table
+fn update_manifest(manifest: &mut DocumentMut, package: &str, version: &str) -> bool {
+  let mut updated: bool = update_dependencies(manifest.as_table_mut(), package, version);
+
+  if let Some(targets) = manifest
+    .get_mut("target")
+    .and_then(|v: &mut Item| v.as_table_like_mut())
+  {
+    for (_, target) in targets.iter_mut() {
+      if let Some(target) = target.as_table_like_mut() {
+        updated |= update_dependencies(target, package, version);
+      }
+    }
+  }
+
+  if let Some(workspace) = manifest
+    .get_mut("workspace")
+    .and_then(|v: &mut Item| v.as_table_like_mut())
+  {
+    updated |= update_dependencies(workspace, package, version);
+  }
+
+  updated
+}
+
+impl CargoModule {
+  pub fn create(module: &ConfigModule) -> Result<Box<dyn Module>, FastReleaseError> {
+    let options: CargoOptions = get_options(module)?;
+
+    Ok(Box::new(Self { options }))
+  }
+
+  // Returns the packages that got the version, the one of the manifest first. The version of the
+  // workspace is shared by all the members that inherit it.
+  fn update_package(
+    &self,
+    manifest_path: &Path,
+    workspace_path: Option<&Path>,
+    version: &str,
+  ) -> Result<Vec<String>, FastReleaseError> {
+    let mut manifest: DocumentMut = read(manifest_path)?;
+    let package: String = match get_package_name(&manifest) {
+      Some(package) => package,
+      None => {
+        return Err(error(
+          &format!(
+            "The manifest '{}' doesn't have a package name.",
+            manifest_path.display()
+          ),
+          None,
+        ))
+      }
+    };
+
+    // Unlike the ones of the items, the lookups of the tables don't insert the missing keys
+    let package_version: &mut Item = match manifest
+      .get_mut("package")
+      .and_then(|v: &mut Item| v.as_table_like_mut())
+      .and_then(|v: &mut dyn TableLike| v.get_mut("version"))
+    {
+      Some(package_version) => package_version,
+      None => {
+        return Err(error(
+          &format!(
+            "The manifest '{}' doesn't have a package version.",
+            manifest_path.display()
+          ),
+          None,
+        ))
+      }
+    };
+
+    let mut packages: Vec<String> = vec![package.clone()];
+
+    // Versions inherited from the workspace are written on '[workspace.package]'
+    if package_version
+      .get("workspace")
+      .and_then(|v: &Item| v.as_bool())
+      .unwrap_or(false)
+    {
+      let workspace_path: &Path = match workspace_path {
+        Some(workspace_path) => workspace_path,
+        None => {
+          return Err(error(
+            &format!(
+              "The package '{}' inherits its version from a workspace that couldn't be found.",
+              package
+            ),
+            None,
+          ))
+        }
+      };
+
+      let mut workspace: DocumentMut = read(workspace_path)?;
+      let workspace_version: &mut Item = match workspace
+        .get_mut("workspace")
+        .and_then(|v: &mut Item| v.as_table_like_mut())
+        .and_then(|v: &mut dyn TableLike| v.get_mut("package"))
+        .and_then(|v: &mut Item| v.as_table_like_mut())
+        .and_then(|v: &mut dyn TableLike| v.get_mut("version"))
+      {
+        Some(workspace_version) => workspace_version,
+        None => {
+          return Err(error(
+            &format!(
+              "The package '{}' inherits its version from the workspace '{}', which doesn't have a '[workspace.package]' version.",
+              package,
+              workspace_path.display()
+            ),
+            None,
+          ))
+        }
+      };
+      set_string(workspace_version, version);
+      write(workspace_path, &workspace)?;
+
+      debug!(
+        message = format!(
+          "Updated the workspace version on '{}' to '{}'.",
+          workspace_path.display(),
+          version
+        ),
+        category = "CARGO"
+      );
+
+      for member_path in get_workspace_members(workspace_path, &workspace) {
+        let member: DocumentMut = read(&member_path)?;
+        if let Some(name) = get_package_name(&member).filter(|_: &String| inherits_version(&member))
+        {
+          if !packages.contains(&name) {
+            packages.push(name);
+          }
+        }
+      }
+    } else {
+      set_string(package_version, version);
+      write(manifest_path, &manifest)?;
+
+      debug!(
+        message = format!(
+          "Updated the version on '{}' to '{}'.",
+          manifest_path.display(),
+          version
+        ),
+        category = "CARGO"
+      );
+    }
+
+    Ok(packages)
+  }
+
+  fn update_dependents(
+    &self,
+    workspace_path: &Path,
+    package: &str,
+    version: &str,
+  ) -> Result<(), FastReleaseError> {
+    let workspace: DocumentMut = read(workspace_path)?;
+
+    for manifest_path in get_workspace_members(workspace_path, &workspace) {
+      let mut manifest: DocumentMut = read(&manifest_path)?;
+      if update_manifest(&mut manifest, package, version) {
+        write(&manifest_path, &manifest)?;
+
+        debug!(
+          message = format!(
+            "Updated the requirement on '{}' of '{}'.",
+            package,
+            manifest_path.display()
+          ),
+          category = "CARGO"
+        );
+      }
+    }
+
+    Ok(())
+  }
+
+  // Only the local packages of the lockfile, the ones without a source, are updated
+  fn update_lockfile(
+    &self,
+    lockfile_path: &Path,
+    names: &[String],
+    version: &str,
+  ) -> Result<(), FastReleaseError> {
+    if !lockfile_path.is_file() {
+      return Ok(());
+    }
+
+    let mut lockfile: DocumentMut = read(lockfile_path)?;
+    let mut updated: bool = false;
+
+    if let Some(packages) = lockfile
+      .get_mut("package")
+      .and_then(|v: &mut Item| v.as_array_of_tables_mut())
+    {
+      for entry in packages.iter_mut() {
+        let is_package: bool = entry
+          .get("name")
+          .and_then(|v: &Item| v.as_str())
+          .is_some_and(|v: &str| names.iter().any(|package: &String| package == v));
+        if !is_package || entry.get("source").is_some() {
+          continue;
+        }
+
+        if let Some(entry_version) = entry.get_mut("version") {
+          set_string(entry_version, version);
+          updated = true;
+        }
+      }
+    }
+
+    if updated {
+      write(lockfile_path, &lockfile)?;
+      debug!(
+        message = format!(
+          "Updated the version of '{}' on '{}'.",
+          names.join("', '"),
+          lockfile_path.display()
+        ),
+        category = "CARGO"
+      );
+    }
+
+    Ok(())
+  }
+}
+
+impl Module for CargoModule {
+  fn name(&self) -> &str {
+    NAME
+  }
+
+  fn verify_conditions(&mut self, context: &Context) -> Result<(), FastReleaseError> {
+    let manifest_path: PathBuf = context.project_path().join(MANIFEST);
+    if !manifest_path.is_file() {
+      return Err(error(
+        &format!(
+          "The project '{}' doesn't have a Cargo manifest at '{}'.",
+          context.project.name,
+          manifest_path.display()
+        ),
+        None,
+      ));
+    }
+
+    Ok(())
+  }
+
+  fn prepare(&mut self, context: &Context) -> Result<(), FastReleaseError> {
+    let next_release: &NextRelease = context.get_next_release(NAME)?;
+    let version: String = next_release.version.to_string();
+
+    let project_path: PathBuf = context.project_path();
+    let manifest_path: PathBuf = project_path.join(MANIFEST);
+    let workspace_path: Option<PathBuf> = find_workspace(&project_path, &context.cwd)?;
+
+    let packages: Vec<String> =
+      self.update_package(&manifest_path, workspace_path.as_deref(), &version)?;
+
+    if self.options.dependents {
+      if let Some(workspace_path) = &workspace_path {
+        for package in &packages {
+          self.update_dependents(workspace_path, package, &version)?;
+        }
+      }
+    }
+
+    if self.options.lockfile {
+      let lockfile_path: PathBuf = workspace_path
+        .as_deref()
+        .unwrap_or(&manifest_path)
+        .with_file_name(LOCKFILE);
+      self.update_lockfile(&lockfile_path, &packages, &version)?;
+    }
+
+    info!(
+      "Updated the version of the package '{}' to '{}'.",
+      packages[0], version
+    );
+    if packages.len() > 1 {
+      info!(
+        "The packages '{}' share the version of the workspace, so they got it too.",
+        packages[1..].join("', '")
+      );
+    }
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{config::Config, testing};
+  use tempfile::TempDir;
+
+  const CONFIG: &str = "
+version: 1
+tag_format: ${project}@${version}
+branches: [main]
+projects:
+  - name: core
+    path: crates/core
+";
+
+  const WORKSPACE: &str = r#"[workspace]
+members = ["crates/*"]
+exclude = ["crates/old"]
+
+[workspace.package]
+version = "1.0.0" # The shared version
+
+[workspace.dependencies]
+core = { path = "crates/core", version = "1.0.0" }
+"#;
+
+  const LOCKFILE: &str = r#"version = 3
+
+[[package]]
+name = "core"
+version = "1.0.0"
+
+[[package]]
+name = "core"
+version = "0.1.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+
+  fn write_files(root: &Path, files: &[(&str, &str)]) {
+    for (path, content) in files {
+      fs::create_dir_all(root.join(path).parent().unwrap()).unwrap();
+      fs::write(root.join(path), content).unwrap();
+    }
+  }
+
+  fn prepare(root: &Path, options: &str) -> Result<(), FastReleaseError> {
+    let config: Config = testing::get_config(CONFIG);
+    let mut context: Context = testing::get_context(&config, root);
+    context.next_release = Some(testing::get_next_release("core@1.1.0", "1.1.0"));
+
+    let mut module: Box<dyn Module> = CargoModule::create(&testing::get_module(&format!(
+      "{{ name: cargo, {} }}",
+      options
+    )))?;
+    module.verify_conditions(&context)?;
+    module.prepare(&context)
+  }
+
+  fn read_file(root: &Path, path: &str) -> String {
+    fs::read_to_string(root.join(path)).unwrap()
+  }
+
+  #[test]
+  fn keeps_the_operator_of_the_requirements() {
+    assert_eq!(get_requirement("1.0.0", "1.1.0"), "1.1.0");
+    assert_eq!(get_requirement("^1.0", "1.1.0"), "^1.1.0");
+    assert_eq!(get_requirement(" =1.0.0", "1.1.0"), "=1.1.0");
+    assert_eq!(get_requirement(">=1.0.0", "1.1.0"), ">=1.1.0");
+    assert_eq!(get_requirement("~1.0.0", "1.1.0"), "~1.1.0");
+    assert_eq!(get_requirement("<=1.0.0", "1.1.0"), "<=1.1.0");
+    assert_eq!(get_requirement("<2.0.0", "1.1.0"), "<1.1.0");
+  }
+
+  #[test]
+  fn updates_the_package_and_the_lockfile() {
+    let root: TempDir = TempDir::new().unwrap();
+    write_files(
+      root.path(),
+      &[
+        (
+          "crates/core/Cargo.toml",
+          "[package]\nname = \"core\"\nversion = \"1.0.0\" # The version\n",
+        ),
+        ("crates/core/Cargo.lock", LOCKFILE),
+      ],
+    );
+
+    prepare(root.path(), "").unwrap();
+
+    assert_eq!(
+      read_file(root.path(), "crates/core/Cargo.toml"),
+      "[package]\nname = \"core\"\nversion = \"1.1.0\" # The version\n"
+    );
+    assert_eq!(
+      read_file(root.path(), "crates/core/Cargo.lock"),
+      LOCKFILE.replacen("1.0.0", "1.1.0", 1)
+    );
+  }
+
+  #[test]
+  fn updates_the_version_inherited_from_the_workspace() {
+    let root: TempDir = TempDir::new().unwrap();
+    let app: &str = "[package]\nname = \"app\"\n\n[dependencies]\ncore = { path = \"../core\", version = \"=1.0.0\" }\nold = { package = \"core\", version = \"0.1\" }\n\n[target.'cfg(unix)'.build-dependencies]\nbuild-core = { package = \"core\", path = \"../core\", version = \"^1.0.0\" }\n";
+    let old: &str = "[dependencies]\ncore = { path = \"../core\", version = \"1.0.0\" }\n";
+    write_files(
+      root.path(),
+      &[
+        ("Cargo.toml", WORKSPACE),
+        ("Cargo.lock", LOCKFILE),
+        (
+          "crates/core/Cargo.toml",
+          "[package]\nname = \"core\"\nversion.workspace = true\n",
+        ),
+        ("crates/app/Cargo.toml", app),
+        ("crates/old/Cargo.toml", old),
+      ],
+    );
+
+    prepare(root.path(), "").unwrap();
+
+    assert_eq!(
+      read_file(root.path(), "Cargo.toml"),
+      WORKSPACE.replace("1.0.0", "1.1.0")
+    );
+    assert_eq!(
+      read_file(root.path(), "crates/core/Cargo.toml"),
+      "[package]\nname = \"core\"\nversion.workspace = true\n"
+    );
+    assert_eq!(
+      read_file(root.path(), "crates/app/Cargo.toml"),
+      app.replace("=1.0.0", "=1.1.0").replace("^1.0.0", "^1.1.0")
+    );
+    assert_eq!(read_file(root.path(), "crates/old/Cargo.toml"), old);
+    assert_eq!(
+      read_file(root.path(), "Cargo.lock"),
+      LOCKFILE.replacen("1.0.0", "1.1.0", 1)
+    );
+  }
+
+  #[test]
+  fn updates_the_lockfile_of_all_the_members_that_inherit_the_version() {
+    let root: TempDir = TempDir::new().unwrap();
+    let lockfile: &str = "version = 3\n\n[[package]]\nname = \"app\"\nversion = \"1.0.0\"\n\n[[package]]\nname = \"core\"\nversion = \"1.0.0\"\n\n[[package]]\nname = \"tool\"\nversion = \"0.3.0\"\n";
+    let tool: &str = "[package]\nname = \"tool\"\nversion = \"0.3.0\"\n\n[dependencies]\napp = { path = \"../app\", version = \"1.0.0\" }\n";
+    write_files(
+      root.path(),
+      &[
+        ("Cargo.toml", WORKSPACE),
+        ("Cargo.lock", lockfile),
+        (
+          "crates/core/Cargo.toml",
+          "[package]\nname = \"core\"\nversion.workspace = true\n",
+        ),
+        (
+          "crates/app/Cargo.toml",
+          "[package]\nname = \"app\"\nversion = { workspace = true }\n",
+        ),
+        ("crates/tool/Cargo.toml", tool),
+      ],
+    );
+
+    prepare(root.path(), "").unwrap();
+
+    assert_eq!(
+      read_file(root.path(), "Cargo.lock"),
+      lockfile.replace("1.0.0", "1.1.0")
+    );
+    assert_eq!(
+      read_file(root.path(), "crates/tool/Cargo.toml"),
+      tool.replace("1.0.0", "1.1.0")
+    );
+  }
+
+  #[test]
+  fn leaves_the_dependents_and_the_lockfile_when_disabled() {
+    let root: TempDir = TempDir::new().unwrap();
+    let app: &str = "[package]\nname = \"app\"\n\n[dependencies]\ncore = { path = \"../core\", version = \"1.0.0\" }\n";
+    write_files(
+      root.path(),
+      &[
+        ("Cargo.toml", WORKSPACE),
+        ("Cargo.lock", LOCKFILE),
+        (
+          "crates/core/Cargo.toml",
+          "[package]\nname = \"core\"\nversion.workspace = true\n",
+        ),
+        ("crates/app/Cargo.toml", app),
+      ],
+    );
+
+    prepare(root.path(), "dependents: false, lockfile: false").unwrap();
+
+    assert_eq!(read_file(root.path(), "crates/app/Cargo.toml"), app);
+    assert_eq!(read_file(root.path(), "Cargo.lock"), LOCKFILE);
+  }
+
+  #[test]
+  fn reports_the_missing_versions() {
+    let root: TempDir = TempDir::new().unwrap();
+    let manifest: &str = "[package]\nname = \"core\"\n";
+    write_files(root.path(), &[("crates/core/Cargo.toml", manifest)]);
+
+    let error: FastReleaseError = prepare(root.path(), "").unwrap_err();
+    assert!(error.message.contains("doesn't have a package version"));
+    assert_eq!(read_file(root.path(), "crates/core/Cargo.toml"), manifest);
+
+    let workspace: &str = "[workspace]\nmembers = [\"crates/*\"]\n";
+    write_files(
+      root.path(),
+      &[
+        ("Cargo.toml", workspace),
+        (
+          "crates/core/Cargo.toml",
+          "[package]\nname = \"core\"\nversion.workspace = true\n",
+        ),
+      ],
+    );
+
+    let error: FastReleaseError = prepare(root.path(), "").unwrap_err();
+    assert!(error.message.contains("'[workspace.package]' version"));
+    assert_eq!(read_file(root.path(), "Cargo.toml"), workspace);
+  }
+}
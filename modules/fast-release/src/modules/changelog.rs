@@ -0,0 +1,403 @@
+// Copyright (c) Toolbi Software. All rights reserved.
+// Check the README file in the project root for more information.
+
+use super::{get_options, notes::NotesModule, Context, Module, NextRelease};
+use crate::{
+  analyzer::{self, commit::Commit},
+  config::ConfigModule,
+  error::{FastReleaseError, FastReleaseErrorBuilder},
+  git, project,
+  release::{self, LastRelease},
+};
+use chrono::DateTime;
+use git2::Repository;
+use serde::Deserialize;
+use serde_yaml::Mapping;
+use std::{
+  fs,
+  io::{Error, ErrorKind},
+  path::PathBuf,
+};
+use tracing::info;
+
+pub const NAME: &str = "changelog";
+
+const DEFAULT_PATH: &str = "CHANGELOG.md";
+const DEFAULT_TITLE: &str = "# Changelog";
+const DEFAULT_UNRELEASED_TITLE: &str = "## [Unreleased]";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum ChangelogMode {
+  // Inserts the notes under the title, or under the marker when there's one
+  #[default]
+  Prepend,
+  // Keeps an 'Unreleased' section on top and moves its entries into the new release
+  KeepAChangelog,
+  // Rebuilds the whole file from the tags of the project
+  Regenerate,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ChangelogOptions {
+  path: Option<String>,
+  title: Option<String>,
+  marker: Option<String>,
+  #[serde(default)]
+  mode: ChangelogMode,
+  unreleased_title: Option<String>,
+  // The options of the notes module used to render the previous releases when regenerating
+  notes: Option<Mapping>,
+}
+
+pub struct ChangelogModule {
+  options: ChangelogOptions,
+  notes: Option<NotesModule>,
+}
+
+fn error(message: &str, error: Error) -> FastReleaseError {
+  FastReleaseErrorBuilder::new(message)
+    .category("CHANGELOG")
+    .error(error)
+    .get()
+}
+
+fn find_line(lines: &[&str], value: &str) -> Option<usize> {
+  lines
+    .iter()
+    .position(|v: &&str| v.trim().eq_ignore_ascii_case(value.trim()))
+}
+
+// Joins the parts with a blank line between them, ending the file with a new line
+fn join(parts: &[&str]) -> String {
+  let parts: Vec<&str> = parts
+    .iter()
+    .map(|v: &&str| v.trim_matches('\n'))
+    .filter(|v: &&str| !v.trim().is_empty())
+    .collect();
+
+  format!("{}\n", parts.join("\n\n"))
+}
+
+impl ChangelogModule {
+  pub fn create(module: &ConfigModule) -> Result<Box<dyn Module>, FastReleaseError> {
+    let options: ChangelogOptions = get_options(module)?;
+
+    let notes: Option<NotesModule> = match options.mode {
+      ChangelogMode::Regenerate => Some(NotesModule::new(&ConfigModule {
+        name: format!("{}.notes", NAME),
+        plugin: None,
+        options: options.notes.clone().unwrap_or_default(),
+      })?),
+      _ => None,
+    };
+
+    Ok(Box::new(Self { options, notes }))
+  }
+
+  fn title(&self) -> &str {
+    self.options.title.as_deref().unwrap_or(DEFAULT_TITLE)
+  }
+
+  fn prepend(&self, content: &str, notes: &str) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let anchor: &str = self.options.marker.as_deref().unwrap_or(self.title());
+
+    match find_line(&lines, anchor) {
+      Some(index) => join(&[
+        &lines[..=index].join("\n"),
+        notes,
+        &lines[(index + 1)..].join("\n"),
+      ]),
+      None => join(&[self.title(), notes, content]),
+    }
+  }
+
+  fn keep_a_changelog(&self, content: &str, notes: &str) -> String {
+    let unreleased_title: &str = self
+      .options
+      .unreleased_title
+      .as_deref()
+      .unwrap_or(DEFAULT_UNRELEASED_TITLE);
+    let lines: Vec<&str> = content.lines().collect();
+
+    let index: usize = match find_line(&lines, unreleased_title) {
+      Some(index) => index,
+      None => {
+        // Without an 'Unreleased' section, the notes go under the title
+        let content: String = self.prepend(content, unreleased_title);
+        return self.keep_a_changelog(&content, notes);
+      }
+    };
+
+    // The entries of the 'Unreleased' section go until the next release
+    let end: usize = lines[(index + 1)..]
+      .iter()
+      .position(|v: &&str| v.starts_with("## "))
+      .map(|v: usize| index + 1 + v)
+      .unwrap_or(lines.len());
+    let unreleased: String = lines[(index + 1)..end].join("\n");
+
+    join(&[
+      &lines[..=index].join("\n"),
+      notes,
+      &unreleased,
+      &lines[end..].join("\n"),
+    ])
+  }
+
+  fn regenerate(
+    &self,
+    context: &Context,
+    next_release: &NextRelease,
+  ) -> Result<String, FastReleaseError> {
+    let notes_module: &NotesModule = match &self.notes {
+      Some(notes_module) => notes_module,
+      None => return Ok(join(&[self.title(), &next_release.notes])),
+    };
+
+    let repo: Repository = Repository::discover(&context.cwd)
+      .map_err(|v: git2::Error| git::error("Failed to open the git repository.", v))?;
+    let releases: Vec<LastRelease> = release::get_releases(
+      &repo,
+      &next_release.hash,
+      Some(&context.project.name),
+      &context.branch.tag_format,
+    )?;
+
+    let mut sections: Vec<String> = vec![self.title().to_string(), next_release.notes.clone()];
+    for (index, release) in releases.iter().enumerate().rev() {
+      let previous: Option<&LastRelease> = index.checked_sub(1).map(|v: usize| &releases[v]);
+      let commits: Vec<Commit> = analyzer::get_commits(
+        &repo,
+        &release.hash,
+        previous.map(|v: &LastRelease| v.hash.as_str()),
+        project::get_path(&context.project),
+      )?;
+
+      let date: String =
+        DateTime::from_timestamp(git::repo::get_commit_time(&repo, &release.hash)?, 0)
+          .map(|v: DateTime<chrono::Utc>| v.format("%Y-%m-%d").to_string())
+          .unwrap_or_default();
+
+      let historic_release: NextRelease = NextRelease {
+        version: release.version.clone(),
+        tag: release.tag.clone(),
+        channel: None,
+        release_type: analyzer::analyze(&commits, &[]),
+        hash: release.hash.clone(),
+        notes: String::new(),
+      };
+      let historic_context: Context = Context {
+        commits,
+        last_release: previous.cloned(),
+        next_release: Some(historic_release.clone()),
+        ..context.clone()
+      };
+
+      sections.push(notes_module.generate(&historic_context, &historic_release, &date)?);
+    }
+
+    Ok(join(
+      &sections
+        .iter()
+        .map(|v: &String| v.as_str())
+        .collect::<Vec<&str>>(),
+    ))
+  }
+}
+
+impl Module for ChangelogModule {
+  fn name(&self) -> &str {
+    NAME
+  }
+
+  fn prepare(&mut self, context: &Context) -> Result<(), FastReleaseError> {
+    let next_release: &NextRelease = context.get_next_release(NAME)?;
+    let path: PathBuf = context
+      .project_path()
+      .join(self.options.path.as_deref().unwrap_or(DEFAULT_PATH));
+
+    // Only a missing changelog starts empty, any other error would overwrite the existing one
+    let content: String = match fs::read_to_string(&path) {
+      Ok(content) => content,
+      Err(v) if v.kind() == ErrorKind::NotFound => String::new(),
+      Err(v) => {
+        return Err(error(
+          &format!("Failed to read the changelog '{}'.", path.display()),
+          v,
+        ))
+      }
+    };
+
+    let result: String = match self.options.mode {
+      ChangelogMode::Prepend => self.prepend(&content, &next_release.notes),
+      ChangelogMode::KeepAChangelog => self.keep_a_changelog(&content, &next_release.notes),
+      ChangelogMode::Regenerate => self.regenerate(context, next_release)?,
+    };
+
+    if let Some(parent) = path.parent() {
+      fs::create_dir_all(parent)
+        .map_err(|v: Error| error("Failed to create the directory of the changelog.", v))?;
+    }
+    fs::write(&path, result).map_err(|v: Error| {
+      error(
+        &format!("Failed to write the changelog '{}'.", path.display()),
+        v,
+      )
+    })?;
+
+    info!(
+      "Updated the changelog '{}' of the project '{}'.",
+      path.display(),
+      context.project.name
+    );
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    config::Config,
+    testing::{self, TestRepo},
+  };
+  use std::path::Path;
+  use tempfile::TempDir;
+
+  const CONFIG: &str = "
+version: 1
+tag_format: v${version}
+branches: [main]
+projects:
+  - name: app
+    path: .
+";
+
+  const NOTES: &str = "## 1.1.0\n\n- The new entry";
+
+  fn run_prepare(cwd: &Path, options: &str, hash: &str) -> Result<(), FastReleaseError> {
+    let config: Config = testing::get_config(CONFIG);
+    let mut context: Context = testing::get_context(&config, cwd);
+    let mut next_release: NextRelease = testing::get_next_release("v1.1.0", "1.1.0");
+    next_release.hash = hash.to_string();
+    next_release.notes = NOTES.to_string();
+    context.next_release = Some(next_release);
+
+    let mut module: Box<dyn Module> = ChangelogModule::create(&testing::get_module(&format!(
+      "{{ name: changelog, {} }}",
+      options
+    )))
+    .unwrap();
+    module.prepare(&context)
+  }
+
+  fn prepare(cwd: &Path, options: &str, hash: &str) -> String {
+    run_prepare(cwd, options, hash).unwrap();
+
+    fs::read_to_string(cwd.join("CHANGELOG.md")).unwrap()
+  }
+
+  fn prepare_file(content: &str, options: &str) -> String {
+    let cwd: TempDir = TempDir::new().unwrap();
+    fs::write(cwd.path().join("CHANGELOG.md"), content).unwrap();
+
+    prepare(cwd.path(), options, &"0".repeat(40))
+  }
+
+  #[test]
+  fn prepends_the_notes() {
+    let cwd: TempDir = TempDir::new().unwrap();
+    assert_eq!(
+      prepare(cwd.path(), "path: CHANGELOG.md", &"0".repeat(40)),
+      "# Changelog\n\n## 1.1.0\n\n- The new entry\n"
+    );
+
+    assert_eq!(
+      prepare_file("# Changelog\n\n## 1.0.0\n\n- An entry\n", ""),
+      "# Changelog\n\n## 1.1.0\n\n- The new entry\n\n## 1.0.0\n\n- An entry\n"
+    );
+    assert_eq!(
+      prepare_file("## 1.0.0\n\n- An entry\n", "title: '# History'"),
+      "# History\n\n## 1.1.0\n\n- The new entry\n\n## 1.0.0\n\n- An entry\n"
+    );
+    assert_eq!(
+      prepare_file(
+        "# Changelog\n\nThe intro.\n\n<!-- releases -->\n\n## 1.0.0\n",
+        "marker: '<!-- releases -->'"
+      ),
+      "# Changelog\n\nThe intro.\n\n<!-- releases -->\n\n## 1.1.0\n\n- The new entry\n\n## 1.0.0\n"
+    );
+  }
+
+  #[test]
+  fn moves_the_unreleased_entries_into_the_release() {
+    assert_eq!(
+      prepare_file(
+        "# Changelog\n\n## [Unreleased]\n\n### Added\n\n- A manual entry\n\n## 1.0.0\n\n- An entry\n",
+        "mode: keep-a-changelog"
+      ),
+      "# Changelog\n\n## [Unreleased]\n\n## 1.1.0\n\n- The new entry\n\n### Added\n\n- A manual entry\n\n## 1.0.0\n\n- An entry\n"
+    );
+    assert_eq!(
+      prepare_file("# Changelog\n\n## 1.0.0\n", "mode: keep-a-changelog"),
+      "# Changelog\n\n## [Unreleased]\n\n## 1.1.0\n\n- The new entry\n\n## 1.0.0\n"
+    );
+  }
+
+  #[test]
+  fn regenerates_the_changelog_from_the_tags() {
+    let test_repo: TestRepo = TestRepo::new();
+    test_repo.write("CHANGELOG.md", "Edited by hand");
+    test_repo.write("a", "");
+    test_repo.commit("feat: add the first feature");
+    git::repo::create_tag(&test_repo.repo, "v1.0.0", &test_repo.head(), None).unwrap();
+    test_repo.write("b", "");
+    test_repo.commit("fix: fix the first feature");
+    git::repo::create_tag(&test_repo.repo, "v1.0.1", &test_repo.head(), None).unwrap();
+    test_repo.write("c", "");
+    let head: String = test_repo.commit("feat: add the second feature");
+
+    let content: String = prepare(&test_repo.path(), "mode: regenerate", &head);
+
+    // The dates and the hashes of the previous releases change on every run
+    let lines: Vec<&str> = content
+      .lines()
+      .filter(|v: &&str| !v.is_empty())
+      .map(|v: &str| v.split(" (").next().unwrap())
+      .collect();
+    assert_eq!(
+      lines,
+      [
+        "# Changelog",
+        "## 1.1.0",
+        "- The new entry",
+        "## 1.0.1",
+        "### Bug Fixes",
+        "* fix the first feature",
+        "## 1.0.0",
+        "### Features",
+        "* add the first feature",
+      ]
+    );
+  }
+
+  #[test]
+  fn keeps_a_changelog_that_cant_be_read() {
+    let cwd: TempDir = TempDir::new().unwrap();
+    let content: &[u8] = b"# Changelog\n\n## 1.0.0 \xff\n";
+    fs::write(cwd.path().join("CHANGELOG.md"), content).unwrap();
+
+    for mode in ["prepend", "keep-a-changelog"] {
+      let error: FastReleaseError =
+        run_prepare(cwd.path(), &format!("mode: {}", mode), &"0".repeat(40)).unwrap_err();
+
+      assert_eq!(error.category.as_deref(), Some("CHANGELOG"));
+      assert!(error.message.starts_with("Failed to read the changelog"));
+      assert_eq!(fs::read(cwd.path().join("CHANGELOG.md")).unwrap(), content);
+    }
+  }
+}
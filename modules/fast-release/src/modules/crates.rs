@@ -0,0 +1,725 @@
+// Copyright (c) Toolbi Software. All rights reserved.
+// Check the README file in the project root for more information.
+
+use super::{
+  cargo::{self, DEPENDENCY_TABLES, MANIFEST},
+  get_options, Context, Module, NextRelease, ReleaseInfo,
+};
+use crate::{
+  config::ConfigModule,
+  error::{FastReleaseError, FastReleaseErrorBuilder},
+  http,
+};
+use serde::Deserialize;
+use std::{
+  env, fs,
+  io::Error,
+  path::{Path, PathBuf},
+  process::{Command, Output},
+  thread,
+  time::{Duration, Instant},
+};
+use toml_edit::{DocumentMut, Item, TableLike};
+use tracing::{debug, info};
+use ureq::Agent;
+use url::Url;
+
+pub const NAME: &str = "crates";
+
+const CRATES_IO: &str = "crates-io";
+const CRATES_IO_INDEX: &str = "sparse+https://index.crates.io/";
+const DEFAULT_TIMEOUT: u64 = 300;
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CratesOptions {
+  // The name of a registry on the Cargo configuration, or the URL of its index
+  registry: Option<String>,
+  index: Option<String>,
+  // The version bump of the 'prepare' step leaves the worktree dirty, unless it's committed
+  #[serde(default = "default_true")]
+  allow_dirty: bool,
+  #[serde(default)]
+  no_verify: bool,
+  #[serde(default)]
+  args: Vec<String>,
+  // Seconds to wait for the index to show each version, where zero doesn't wait
+  #[serde(default = "default_timeout")]
+  timeout: u64,
+}
+
+fn default_true() -> bool {
+  true
+}
+
+fn default_timeout() -> u64 {
+  DEFAULT_TIMEOUT
+}
+
+#[derive(Debug, Clone)]
+struct Package {
+  name: String,
+  version: String,
+  manifest_path: PathBuf,
+  // The packages of the workspace it depends on through a path
+  dependencies: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IndexEntry {
+  vers: String,
+}
+
+pub struct CratesModule {
+  options: CratesOptions,
+  agent: Agent,
+  // The packages published by this run, for the registries without a known index
+  published: Vec<String>,
+}
+
+fn error(message: &str) -> FastReleaseError {
+  FastReleaseErrorBuilder::new(message)
+    .category("CRATES")
+    .get()
+}
+
+// Resolves the fields inherited from the workspace, like 'version.workspace = true'
+fn get_package_field<'a>(
+  manifest: &'a DocumentMut,
+  workspace: Option<&'a DocumentMut>,
+  key: &str,
+) -> Option<&'a Item> {
+  let item: &Item = manifest.get("package")?.get(key)?;
+
+  if item
+    .get("workspace")
+    .and_then(|v: &Item| v.as_bool())
+    .unwrap_or(false)
+  {
+    return workspace?.get("workspace")?.get("package")?.get(key);
+  }
+
+  Some(item)
+}
+
+// 'publish = false' skips every registry, while a list only allows the ones on it
+fn is_publishable(manifest: &DocumentMut, workspace: Option<&DocumentMut>, registry: &str) -> bool {
+  match get_package_field(manifest, workspace, "publish") {
+    Some(item) => match (item.as_bool(), item.as_array()) {
+      (Some(publish), _) => publish,
+      (_, Some(registries)) => registries
+        .iter()
+        .any(|v: &toml_edit::Value| v.as_str() == Some(registry)),
+      _ => true,
+    },
+    None => true,
+  }
+}
+
+fn get_path_dependencies(manifest: &DocumentMut) -> Vec<String> {
+  let mut tables: Vec<&dyn TableLike> = vec![manifest.as_table()];
+  if let Some(targets) = manifest
+    .get("target")
+    .and_then(|v: &Item| v.as_table_like())
+  {
+    tables.extend(
+      targets
+        .iter()
+        .filter_map(|(_, v): (&str, &Item)| v.as_table_like()),
+    );
+  }
+
+  let mut result: Vec<String> = Vec::new();
+  for table in tables {
+    // Dev dependencies are left out of the published manifest, so they don't need to be first
+    for key in DEPENDENCY_TABLES
+      .iter()
+      .filter(|v: &&&str| **v != "dev-dependencies")
+    {
+      let dependencies: &dyn TableLike = match table.get(key).and_then(|v: &Item| v.as_table_like())
+      {
+        Some(dependencies) => dependencies,
+        None => continue,
+      };
+
+      for (name, dependency) in dependencies.iter() {
+        if dependency.get("path").is_none() {
+          continue;
+        }
+        let package: String = dependency
+          .get("package")
+          .and_then(|v: &Item| v.as_str())
+          .unwrap_or(name)
+          .to_string();
+        if !result.contains(&package) {
+          result.push(package);
+        }
+      }
+    }
+  }
+
+  result
+}
+
+// Publishes the packages after the ones they depend on. Cycles are left on their original order,
+// as Cargo rejects them anyway.
+fn sort_packages(packages: Vec<Package>) -> Vec<Package> {
+  let names: Vec<String> = packages.iter().map(|v: &Package| v.name.clone()).collect();
+  let mut pending: Vec<Package> = packages;
+  let mut result: Vec<Package> = Vec::new();
+
+  while !pending.is_empty() {
+    let is_ready = |package: &Package, result: &[Package]| -> bool {
+      package.dependencies.iter().all(|dependency: &String| {
+        !names.contains(dependency) || result.iter().any(|v: &Package| &v.name == dependency)
+      })
+    };
+
+    let index: usize = pending
+      .iter()
+      .position(|v: &Package| is_ready(v, &result))
+      .unwrap_or(0);
+    result.push(pending.remove(index));
+  }
+
+  result
+}
+
+// The path of a crate on a sparse index, like 'se/rd/serde'
+fn get_index_path(name: &str) -> String {
+  let name: String = name.to_lowercase();
+
+  match name.len() {
+    1 => format!("1/{}", name),
+    2 => format!("2/{}", name),
+    3 => format!("3/{}/{}", &name[..1], name),
+    _ => format!("{}/{}/{}", &name[..2], &name[2..4], name),
+  }
+}
+
+fn has_credentials_file() -> bool {
+  let cargo_home: Option<PathBuf> = env::var_os("CARGO_HOME")
+    .map(PathBuf::from)
+    .or_else(|| env::var_os("HOME").map(|v: std::ffi::OsString| PathBuf::from(v).join(".cargo")));
+
+  cargo_home.is_some_and(|v: PathBuf| {
+    v.join("credentials.toml").is_file() || v.join("credentials").is_file()
+  })
+}
+
+impl CratesModule {
+  pub fn create(module: &ConfigModule) -> Result<Box<dyn Module>, FastReleaseError> {
+    let options: CratesOptions = get_options(module)?;
+
+    if options.registry.is_some() && options.index.is_some() {
+      return Err(
+        FastReleaseErrorBuilder::new(&format!(
+          "The module '{}' takes either a 'registry' or an 'index', but not both.",
+          NAME
+        ))
+        .category("CONFIG")
+        .get(),
+      );
+    }
+
+    Ok(Box::new(Self {
+      options,
+      agent: http::agent(),
+      published: Vec::new(),
+    }))
+  }
+
+  // The registry given by its index is named after it
+  fn get_registry(&self) -> &str {
+    self
+      .options
+      .registry
+      .as_deref()
+      .or(self.options.index.as_deref())
+      .unwrap_or(CRATES_IO)
+  }
+
+  // Cargo takes the index of named registries from the environment or its configuration, and
+  // only the environment is looked at here
+  fn get_index_url(&self) -> Option<String> {
+    let index: String = match (&self.options.index, &self.options.registry) {
+      (Some(index), _) => index.clone(),
+      (None, Some(registry)) => env::var(format!(
+        "CARGO_REGISTRIES_{}_INDEX",
+        registry.to_uppercase().replace('-', "_")
+      ))
+      .ok()?,
+      (None, None) => CRATES_IO_INDEX.to_string(),
+    };
+
+    index
+      .strip_prefix("sparse+")
+      .map(|v: &str| format!("{}/", v.trim_end_matches('/')))
+  }
+
+  // Every package of the project, which is a single one unless the project is a virtual workspace
+  fn get_packages(&self, context: &Context) -> Result<Vec<Package>, FastReleaseError> {
+    let project_path: PathBuf = context.project_path();
+    let manifest_path: PathBuf = project_path.join(MANIFEST);
+    let manifest: DocumentMut = cargo::read(&manifest_path)?;
+    let workspace_path: Option<PathBuf> = cargo::find_workspace(&project_path, &context.cwd)?;
+    let workspace: Option<DocumentMut> = match &workspace_path {
+      Some(workspace_path) => Some(cargo::read(workspace_path)?),
+      None => None,
+    };
+
+    let manifest_paths: Vec<PathBuf> = match (manifest.get("package"), &workspace) {
+      (Some(_), _) => vec![manifest_path],
+      (None, Some(workspace)) => cargo::get_workspace_members(
+        workspace_path.as_deref().unwrap_or(&manifest_path),
+        workspace,
+      ),
+      (None, None) => Vec::new(),
+    };
+
+    let mut result: Vec<Package> = Vec::new();
+    for manifest_path in manifest_paths {
+      let manifest: DocumentMut = cargo::read(&manifest_path)?;
+      let name: String = match cargo::get_package_name(&manifest) {
+        Some(name) => name,
+        None => continue,
+      };
+
+      if !is_publishable(&manifest, workspace.as_ref(), self.get_registry()) {
+        info!(
+          "The package '{}' isn't published to '{}', so it's skipped.",
+          name,
+          self.get_registry()
+        );
+        continue;
+      }
+
+      let version: String = get_package_field(&manifest, workspace.as_ref(), "version")
+        .and_then(|v: &Item| v.as_str())
+        .map(|v: &str| v.to_string())
+        .ok_or_else(|| error(&format!("The package '{}' doesn't have a version.", name)))?;
+
+      result.push(Package {
+        name,
+        version,
+        dependencies: get_path_dependencies(&manifest),
+        manifest_path,
+      });
+    }
+
+    Ok(sort_packages(result))
+  }
+
+  fn is_published(&self, index_url: &str, package: &Package) -> Result<bool, FastReleaseError> {
+    let url: String = format!("{}{}", index_url, get_index_path(&package.name));
+
+    let content: Option<String> = match Url::parse(&url).ok().filter(|v: &Url| v.scheme() == "file")
+    {
+      Some(file_url) => file_url
+        .to_file_path()
+        .ok()
+        .and_then(|v: PathBuf| fs::read_to_string(v).ok()),
+      None => match self.agent.get(&url).set("Cache-Control", "no-cache").call() {
+        Ok(response) => Some(response.into_string().map_err(|v: Error| {
+          FastReleaseErrorBuilder::new(&format!("Failed to read the index entry '{}'.", url))
+            .category("CRATES")
+            .error(v)
+            .get()
+        })?),
+        Err(ureq::Error::Status(404, _)) => None,
+        Err(v) => {
+          return Err(http::error(
+            &format!("Failed to read the index entry '{}'.", url),
+            "CRATES",
+            v,
+          ))
+        }
+      },
+    };
+
+    Ok(content.is_some_and(|content: String| {
+      content
+        .lines()
+        .filter_map(|v: &str| serde_json::from_str::<IndexEntry>(v).ok())
+        .any(|v: IndexEntry| v.vers == package.version)
+    }))
+  }
+
+  // Dependents fail to publish until the index has the version they depend on
+  fn wait(&self, index_url: &str, package: &Package) -> Result<(), FastReleaseError> {
+    let timeout: Duration = Duration::from_secs(self.options.timeout);
+    let start: Instant = Instant::now();
+
+    while !self.is_published(index_url, package)? {
+      if start.elapsed() >= timeout {
+        return Err(error(&format!(
+          "The version '{}' of the package '{}' didn't show up on the index after {} seconds.",
+          package.version, package.name, self.options.timeout
+        )));
+      }
+      thread::sleep(POLL_INTERVAL);
+    }
+
+    debug!(
+      message = format!(
+        "The index has the version '{}' of the package '{}'.",
+        package.version, package.name
+      ),
+      category = "CRATES"
+    );
+
+    Ok(())
+  }
+
+  fn run_publish(&self, package: &Package) -> Result<(), FastReleaseError> {
+    let mut args: Vec<String> = vec![
+      "publish".into(),
+      "--manifest-path".into(),
+      package.manifest_path.to_string_lossy().to_string(),
+    ];
+    if let Some(registry) = &self.options.registry {
+      args.extend(["--registry".into(), registry.clone()]);
+    }
+    if let Some(index) = &self.options.index {
+      args.extend(["--index".into(), index.clone()]);
+    }
+    if self.options.allow_dirty {
+      args.push("--allow-dirty".into());
+    }
+    if self.options.no_verify {
+      args.push("--no-verify".into());
+    }
+    args.extend(self.options.args.iter().cloned());
+
+    debug!(
+      message = format!("Running 'cargo {}'.", args.join(" ")),
+      category = "CRATES"
+    );
+
+    let output: Output = Command::new("cargo")
+      .args(&args)
+      .current_dir(package.manifest_path.parent().unwrap_or(Path::new(".")))
+      .output()
+      .map_err(|v: Error| {
+        FastReleaseErrorBuilder::new("Failed to run 'cargo publish'.")
+          .category("CRATES")
+          .error(v)
+          .get()
+      })?;
+
+    if !output.status.success() {
+      return Err(
+        FastReleaseErrorBuilder::new(&format!(
+          "Failed to publish the version '{}' of the package '{}'.",
+          package.version, package.name
+        ))
+        .category("CRATES")
+        .error(Error::other(
+          String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ))
+        .get(),
+      );
+    }
+
+    Ok(())
+  }
+}
+
+impl Module for CratesModule {
+  fn name(&self) -> &str {
+    NAME
+  }
+
+  fn verify_conditions(&mut self, context: &Context) -> Result<(), FastReleaseError> {
+    let manifest_path: PathBuf = context.project_path().join(MANIFEST);
+    if !manifest_path.is_file() {
+      return Err(error(&format!(
+        "The project '{}' doesn't have a Cargo manifest at '{}'.",
+        context.project.name,
+        manifest_path.display()
+      )));
+    }
+
+    // Registries given by their index take the token through the arguments
+    let token_variable: Option<String> = match (&self.options.registry, &self.options.index) {
+      (_, Some(_)) => None,
+      (Some(registry), None) => Some(format!(
+        "CARGO_REGISTRIES_{}_TOKEN",
+        registry.to_uppercase().replace('-', "_")
+      )),
+      (None, None) => Some("CARGO_REGISTRY_TOKEN".to_string()),
+    };
+    if let Some(token_variable) = token_variable {
+      if http::get_env(&[&token_variable]).is_none() && !has_credentials_file() {
+        return Err(error(&format!(
+          "The module '{}' needs a registry token on '{}' or from 'cargo login'.",
+          NAME, token_variable
+        )));
+      }
+    }
+
+    Ok(())
+  }
+
+  fn publish(&mut self, context: &Context) -> Result<Option<ReleaseInfo>, FastReleaseError> {
+    let next_release: &NextRelease = context.get_next_release(NAME)?;
+    let version: String = next_release.version.to_string();
+    let registry: String = self.get_registry().to_string();
+
+    let packages: Vec<Package> = self.get_packages(context)?;
+    if packages.is_empty() {
+      info!(
+        "The project '{}' doesn't have packages to publish to '{}'.",
+        context.project.name, registry
+      );
+      return Ok(None);
+    }
+
+    // Without the 'cargo' module the manifest keeps the previous version
+    let project_manifest: PathBuf = context.project_path().join(MANIFEST);
+    if let Some(package) = packages
+      .iter()
+      .find(|v: &&Package| v.manifest_path == project_manifest && v.version != version)
+    {
+      return Err(error(&format!(
+        "The package '{}' has the version '{}' instead of '{}'. Add the module 'cargo' before '{}' to update it.",
+        package.name, package.version, version, NAME
+      )));
+    }
+
+    let index_url: Option<String> = self.get_index_url();
+    if index_url.is_none() {
+      info!(
+        "The index of '{}' isn't a known sparse index, so the published versions aren't waited for.",
+        registry
+      );
+    }
+
+    for package in &packages {
+      if let Some(index_url) = &index_url {
+        if self.is_published(index_url, package)? {
+          info!(
+            "The version '{}' of the package '{}' is already on '{}', so it's skipped.",
+            package.version, package.name, registry
+          );
+          continue;
+        }
+      }
+
+      self.run_publish(package)?;
+      info!(
+        "Published the version '{}' of the package '{}' to '{}'.",
+        package.version, package.name, registry
+      );
+      self.published.push(package.name.clone());
+
+      if let Some(index_url) = index_url.as_ref().filter(|_| self.options.timeout > 0) {
+        self.wait(index_url, package)?;
+      }
+    }
+
+    let url: Option<String> = match (registry.as_str(), packages.as_slice()) {
+      (CRATES_IO, [package]) => Some(format!(
+        "https://crates.io/crates/{}/{}",
+        package.name, package.version
+      )),
+      _ => None,
+    };
+
+    Ok(Some(ReleaseInfo {
+      module: NAME.to_string(),
+      name: match registry.as_str() {
+        CRATES_IO => "crates.io package".to_string(),
+        _ => format!("{} package", registry),
+      },
+      url,
+      channel: next_release.channel.clone(),
+    }))
+  }
+
+  // Crates can only be yanked, which keeps their versions taken, so the published ones are left
+  // to clean up by hand
+  fn rollback(&mut self, context: &Context) -> Result<Option<String>, FastReleaseError> {
+    let index_url: Option<String> = self.get_index_url();
+
+    let mut published: Vec<String> = Vec::new();
+    for package in self.get_packages(context)? {
+      let is_published: bool = match &index_url {
+        Some(index_url) => self.is_published(index_url, &package)?,
+        None => self.published.contains(&package.name),
+      };
+      if is_published {
+        published.push(format!("'{}@{}'", package.name, package.version));
+      }
+    }
+
+    if published.is_empty() {
+      return Ok(None);
+    }
+
+    Err(error(&format!(
+      "The packages {} stay on '{}'. Yank them with 'cargo yank' if they must not be used.",
+      published.join(", "),
+      self.get_registry()
+    )))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{config::Config, testing};
+  use std::os::unix::fs::PermissionsExt;
+  use tempfile::TempDir;
+
+  const CONFIG: &str = "
+version: 1
+tag_format: v${version}
+branches: [main]
+projects:
+  - name: app
+    path: .
+";
+
+  // A 'cargo' that logs the published packages and adds them to the index, like a registry
+  const CARGO: &str = r#"#!/bin/sh
+name=$(basename "$PWD")
+echo "$name" >> "$LOG"
+path="$INDEX/$(echo "$name" | cut -c1-2)/$(echo "$name" | cut -c3-4)"
+mkdir -p "$path"
+echo '{"name":"'"$name"'","vers":"1.0.0"}' >> "$path/$name"
+"#;
+
+  fn write(dir: &Path, path: &str, content: &str) {
+    let path: PathBuf = dir.join(path);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+  }
+
+  // A workspace where 'client' depends on 'core', and 'internal' isn't published
+  fn create_workspace(dir: &Path) {
+    write(
+      dir,
+      "Cargo.toml",
+      "[workspace]\nmembers = [\"client\", \"core\", \"internal\"]\n\n[workspace.package]\nversion = \"1.0.0\"\n",
+    );
+    write(
+      dir,
+      "client/Cargo.toml",
+      "[package]\nname = \"client\"\nversion.workspace = true\n\n[dependencies]\ncore = { path = \"../core\", version = \"1.0.0\" }\n",
+    );
+    write(
+      dir,
+      "core/Cargo.toml",
+      "[package]\nname = \"core\"\nversion.workspace = true\n",
+    );
+    write(
+      dir,
+      "internal/Cargo.toml",
+      "[package]\nname = \"internal\"\nversion = \"1.0.0\"\npublish = false\n",
+    );
+  }
+
+  fn publish(dir: &TempDir, options: &str) -> Result<Option<ReleaseInfo>, FastReleaseError> {
+    let bin: PathBuf = dir.path().join("bin");
+    write(&bin, "cargo", CARGO);
+    fs::set_permissions(bin.join("cargo"), fs::Permissions::from_mode(0o755)).unwrap();
+
+    let workspace: PathBuf = dir.path().join("workspace");
+    let config: Config = testing::get_config(CONFIG);
+    let mut context: Context = testing::get_context(&config, &workspace);
+    context.next_release = Some(testing::get_next_release("v1.0.0", "1.0.0"));
+
+    let index: PathBuf = dir.path().join("index");
+    let mut module: Box<dyn Module> = CratesModule::create(&testing::get_module(&format!(
+      "{{ name: crates, index: 'sparse+file://{}', {} }}",
+      index.display(),
+      options
+    )))?;
+
+    let path: String = format!("{}:{}", bin.display(), env::var("PATH").unwrap_or_default());
+    testing::with_env(
+      &[
+        ("PATH", Some(&path)),
+        ("LOG", dir.path().join("log").to_str()),
+        ("INDEX", index.to_str()),
+      ],
+      || module.publish(&context),
+    )
+  }
+
+  fn get_log(dir: &TempDir) -> Vec<String> {
+    fs::read_to_string(dir.path().join("log"))
+      .unwrap_or_default()
+      .lines()
+      .map(|v: &str| v.to_string())
+      .collect()
+  }
+
+  #[test]
+  fn publishes_the_dependencies_first() {
+    let dir: TempDir = TempDir::new().unwrap();
+    create_workspace(&dir.path().join("workspace"));
+
+    let release: Option<ReleaseInfo> = publish(&dir, "").unwrap();
+
+    assert_eq!(get_log(&dir), ["core", "client"]);
+    assert_eq!(
+      release.map(|v: ReleaseInfo| v.name),
+      Some(format!(
+        "sparse+file://{} package",
+        dir.path().join("index").display()
+      ))
+    );
+  }
+
+  #[test]
+  fn skips_the_versions_on_the_index() {
+    let dir: TempDir = TempDir::new().unwrap();
+    create_workspace(&dir.path().join("workspace"));
+    write(
+      &dir.path().join("index"),
+      "co/re/core",
+      "{\"name\":\"core\",\"vers\":\"0.9.0\"}\n{\"name\":\"core\",\"vers\":\"1.0.0\"}\n",
+    );
+
+    publish(&dir, "").unwrap();
+
+    assert_eq!(get_log(&dir), ["client"]);
+  }
+
+  #[test]
+  fn fails_when_the_index_doesnt_get_the_version() {
+    let dir: TempDir = TempDir::new().unwrap();
+    let module: CratesModule = CratesModule {
+      options: serde_yaml::from_str("{ timeout: 0 }").unwrap(),
+      agent: http::agent(),
+      published: Vec::new(),
+    };
+    let package: Package = Package {
+      name: "core".to_string(),
+      version: "1.0.0".to_string(),
+      manifest_path: PathBuf::from("Cargo.toml"),
+      dependencies: Vec::new(),
+    };
+    let index_url: String = format!("file://{}/", dir.path().display());
+
+    let error: FastReleaseError = module.wait(&index_url, &package).unwrap_err();
+    assert_eq!(
+      error.message,
+      "The version '1.0.0' of the package 'core' didn't show up on the index after 0 seconds."
+    );
+
+    write(dir.path(), "co/re/core", "{\"vers\":\"1.0.0\"}\n");
+    assert!(module.wait(&index_url, &package).is_ok());
+  }
+
+  #[test]
+  fn gets_the_index_paths() {
+    assert_eq!(get_index_path("a"), "1/a");
+    assert_eq!(get_index_path("ab"), "2/ab");
+    assert_eq!(get_index_path("abc"), "3/a/abc");
+    assert_eq!(get_index_path("Serde"), "se/rd/serde");
+  }
+}
@@ -0,0 +1,259 @@
+// Copyright (c) Toolbi Software. All rights reserved.
+// Check the README file in the project root for more information.
+
+use super::{get_options, Context, Module, NextRelease, ReleaseInfo, Step};
+use crate::{
+  analyzer::rules::ReleaseType,
+  config::ConfigModule,
+  error::{FastReleaseError, FastReleaseErrorBuilder},
+};
+use serde::Deserialize;
+use std::{
+  io::Error,
+  path::PathBuf,
+  process::{Command, Output},
+};
+use tracing::debug;
+
+pub const NAME: &str = "exec";
+
+// Every step runs its own shell command. The commands of 'analyze_commits' and
+// 'generate_notes' return their result through the standard output.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ExecOptions {
+  shell: Option<String>,
+  cwd: Option<String>,
+  verify_conditions: Option<String>,
+  analyze_commits: Option<String>,
+  verify_release: Option<String>,
+  generate_notes: Option<String>,
+  prepare: Option<String>,
+  publish: Option<String>,
+  add_channel: Option<String>,
+  success: Option<String>,
+  fail: Option<String>,
+  rollback: Option<String>,
+}
+
+pub struct ExecModule {
+  options: ExecOptions,
+}
+
+impl ExecModule {
+  pub fn create(module: &ConfigModule) -> Result<Box<dyn Module>, FastReleaseError> {
+    let options: ExecOptions = get_options(module)?;
+
+    Ok(Box::new(Self { options }))
+  }
+
+  fn get_command(&self, step: Step) -> Option<&String> {
+    match step {
+      Step::VerifyConditions => self.options.verify_conditions.as_ref(),
+      Step::AnalyzeCommits => self.options.analyze_commits.as_ref(),
+      Step::VerifyRelease => self.options.verify_release.as_ref(),
+      Step::GenerateNotes => self.options.generate_notes.as_ref(),
+      Step::Prepare => self.options.prepare.as_ref(),
+      Step::Publish => self.options.publish.as_ref(),
+      Step::AddChannel => self.options.add_channel.as_ref(),
+      Step::Success => self.options.success.as_ref(),
+      Step::Fail => self.options.fail.as_ref(),
+      Step::Rollback => self.options.rollback.as_ref(),
+    }
+  }
+
+  fn get_env(context: &Context, step: Step) -> Vec<(String, String)> {
+    let mut result: Vec<(String, String)> = vec![
+      ("FAST_RELEASE_STEP".into(), step.to_string()),
+      ("FAST_RELEASE_BRANCH".into(), context.branch.name.clone()),
+      ("FAST_RELEASE_PROJECT".into(), context.project.name.clone()),
+      (
+        "FAST_RELEASE_PROJECT_PATH".into(),
+        context.project.path.clone(),
+      ),
+      ("FAST_RELEASE_DRY_RUN".into(), context.dry_run.to_string()),
+    ];
+
+    if let Some(channel) = &context.branch.channel {
+      result.push(("FAST_RELEASE_CHANNEL".into(), channel.clone()));
+    }
+    if let Some(last_release) = &context.last_release {
+      result.push((
+        "FAST_RELEASE_LAST_VERSION".into(),
+        last_release.version.to_string(),
+      ));
+      result.push(("FAST_RELEASE_LAST_TAG".into(), last_release.tag.clone()));
+    }
+    if let Some(next_release) = &context.next_release {
+      result.push((
+        "FAST_RELEASE_NEXT_VERSION".into(),
+        next_release.version.to_string(),
+      ));
+      result.push(("FAST_RELEASE_NEXT_TAG".into(), next_release.tag.clone()));
+      result.push((
+        "FAST_RELEASE_RELEASE_TYPE".into(),
+        next_release.release_type.to_string(),
+      ));
+      result.push(("FAST_RELEASE_NOTES".into(), next_release.notes.clone()));
+    }
+
+    result
+  }
+
+  // Runs the command of the step, returning its trimmed standard output
+  fn run(&self, context: &Context, step: Step) -> Result<Option<String>, FastReleaseError> {
+    let command: &String = match self.get_command(step) {
+      Some(command) => command,
+      None => return Ok(None),
+    };
+
+    let shell: &str = self.options.shell.as_deref().unwrap_or("sh");
+    let cwd: PathBuf = match &self.options.cwd {
+      Some(cwd) => context.cwd.join(cwd),
+      None => context.project_path(),
+    };
+
+    debug!(
+      message = format!("Running the command '{}' on the step '{}'.", command, step),
+      category = "EXEC"
+    );
+
+    let output: Output = match Command::new(shell)
+      .arg("-c")
+      .arg(command)
+      .current_dir(&cwd)
+      .envs(Self::get_env(context, step))
+      .output()
+    {
+      Ok(output) => output,
+      Err(error) => {
+        return Err(
+          FastReleaseErrorBuilder::new(&format!(
+            "Failed to run the command '{}' on the step '{}'.",
+            command, step
+          ))
+          .category("EXEC")
+          .error(error)
+          .get(),
+        )
+      }
+    };
+
+    if !output.status.success() {
+      return Err(
+        FastReleaseErrorBuilder::new(&format!(
+          "The command '{}' on the step '{}' failed with the status '{}'.",
+          command, step, output.status
+        ))
+        .category("EXEC")
+        .error(Error::other(
+          String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ))
+        .get(),
+      );
+    }
+
+    Ok(Some(
+      String::from_utf8_lossy(&output.stdout).trim().to_string(),
+    ))
+  }
+}
+
+impl Module for ExecModule {
+  fn name(&self) -> &str {
+    NAME
+  }
+
+  fn verify_conditions(&mut self, context: &Context) -> Result<(), FastReleaseError> {
+    self.run(context, Step::VerifyConditions).map(|_| ())
+  }
+
+  fn analyze_commits(
+    &mut self,
+    context: &Context,
+  ) -> Result<Option<ReleaseType>, FastReleaseError> {
+    let output: String = match self.run(context, Step::AnalyzeCommits)? {
+      Some(output) if !output.is_empty() => output,
+      _ => return Ok(None),
+    };
+
+    match serde_yaml::from_str::<ReleaseType>(&output) {
+      Ok(release_type) => Ok(Some(release_type)),
+      Err(error) => Err(
+        FastReleaseErrorBuilder::new(&format!(
+          "The command of the step '{}' returned the invalid release type '{}'.",
+          Step::AnalyzeCommits,
+          output
+        ))
+        .category("EXEC")
+        .error(Error::other(error))
+        .get(),
+      ),
+    }
+  }
+
+  fn verify_release(&mut self, context: &Context) -> Result<(), FastReleaseError> {
+    self.run(context, Step::VerifyRelease).map(|_| ())
+  }
+
+  fn generate_notes(&mut self, context: &Context) -> Result<Option<String>, FastReleaseError> {
+    self.run(context, Step::GenerateNotes)
+  }
+
+  fn prepare(&mut self, context: &Context) -> Result<(), FastReleaseError> {
+    self.run(context, Step::Prepare).map(|_| ())
+  }
+
+  fn publish(&mut self, context: &Context) -> Result<Option<ReleaseInfo>, FastReleaseError> {
+    self.run(context, Step::Publish).map(|v: Option<String>| {
+      v.map(|_| ReleaseInfo {
+        module: NAME.into(),
+        name: context
+          .next_release
+          .as_ref()
+          .map(|v: &NextRelease| v.tag.clone())
+          .unwrap_or_default(),
+        url: None,
+        channel: context.branch.channel.clone(),
+      })
+    })
+  }
+
+  fn add_channel(&mut self, context: &Context) -> Result<Option<ReleaseInfo>, FastReleaseError> {
+    self.run(context, Step::AddChannel).map(|_| None)
+  }
+
+  fn success(&mut self, context: &Context) -> Result<(), FastReleaseError> {
+    self.run(context, Step::Success).map(|_| ())
+  }
+
+  fn fail(
+    &mut self,
+    context: &Context,
+    _errors: &[FastReleaseError],
+  ) -> Result<(), FastReleaseError> {
+    self.run(context, Step::Fail).map(|_| ())
+  }
+
+  // Without a 'rollback' command, what the 'publish' command did is cleaned up by hand
+  fn rollback(&mut self, context: &Context) -> Result<Option<String>, FastReleaseError> {
+    let command: &String = match (&self.options.rollback, &self.options.publish) {
+      (Some(command), _) => command,
+      (None, Some(command)) => {
+        return Err(
+          FastReleaseErrorBuilder::new(&format!(
+            "The module '{}' has no 'rollback' command to take back what '{}' published.",
+            NAME, command
+          ))
+          .category("EXEC")
+          .get(),
+        )
+      }
+      (None, None) => return Ok(None),
+    };
+
+    self.run(context, Step::Rollback)?;
+
+    Ok(Some(format!("Ran the 'rollback' command '{}'.", command)))
+  }
+}
@@ -0,0 +1,301 @@
+// Copyright (c) Toolbi Software. All rights reserved.
+// Check the README file in the project root for more information.
+
+use super::{get_options, Context, Module};
+use crate::{
+  config::ConfigModule,
+  error::{FastReleaseError, FastReleaseErrorBuilder},
+  git::{self, repo},
+};
+use git2::{Repository, Signature};
+use glob::Pattern;
+use serde::Deserialize;
+use std::{
+  io::Error,
+  path::{Path, PathBuf},
+};
+use tracing::{debug, info};
+
+pub const NAME: &str = "git";
+
+const DEFAULT_MESSAGE: &str = "chore(release): ${tag} [skip ci]\n\n${notes}";
+
+// Assets starting with '/' are relative to the root of the repository, the rest to the project
+const DEFAULT_ASSETS: [&str; 6] = [
+  "CHANGELOG.md",
+  "Cargo.toml",
+  "/Cargo.lock",
+  "package.json",
+  "package-lock.json",
+  "npm-shrinkwrap.json",
+];
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GitAuthor {
+  name: String,
+  email: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GitOptions {
+  assets: Option<Vec<String>>,
+  message: Option<String>,
+  author: Option<GitAuthor>,
+}
+
+pub struct GitModule {
+  options: GitOptions,
+  assets: Vec<String>,
+}
+
+fn error(message: &str) -> FastReleaseError {
+  FastReleaseErrorBuilder::new(message).category("GIT").get()
+}
+
+impl GitModule {
+  pub fn create(module: &ConfigModule) -> Result<Box<dyn Module>, FastReleaseError> {
+    let options: GitOptions = get_options(module)?;
+    let assets: Vec<String> = options.assets.clone().unwrap_or_else(|| {
+      DEFAULT_ASSETS
+        .iter()
+        .map(|v: &&str| v.to_string())
+        .collect()
+    });
+
+    for asset in &assets {
+      if let Err(error) = Pattern::new(asset) {
+        return Err(
+          FastReleaseErrorBuilder::new(&format!(
+            "The asset '{}' of the module '{}' is not a valid glob pattern.",
+            asset, NAME
+          ))
+          .category("CONFIG")
+          .error(Error::other(error))
+          .get(),
+        );
+      }
+    }
+
+    Ok(Box::new(Self { options, assets }))
+  }
+
+  // The files that match the assets, relative to the root of the repository
+  fn get_files(&self, context: &Context, root: &Path) -> Vec<PathBuf> {
+    let mut result: Vec<PathBuf> = Vec::new();
+
+    for asset in &self.assets {
+      for path in context.find_files(asset) {
+        if let Ok(relative) = path.strip_prefix(root) {
+          if !result.iter().any(|v: &PathBuf| v == relative) {
+            result.push(relative.to_path_buf());
+          }
+        }
+      }
+    }
+
+    result
+  }
+
+  fn get_author(&self, repo: &Repository) -> Result<Signature<'static>, FastReleaseError> {
+    match &self.options.author {
+      Some(author) => Signature::now(&author.name, &author.email)
+        .map_err(|v: git2::Error| git::error("The author of the module 'git' is not valid.", v)),
+      None => repo::get_signature(repo),
+    }
+  }
+
+  // Fails before committing when the branch got new commits on the remote, as the push would be
+  // rejected anyway
+  fn verify_remote(&self, repo: &Repository, context: &Context) -> Result<(), FastReleaseError> {
+    let head: String = repo::get_head(repo)?;
+    let remote_hash: Option<String> =
+      repo::get_remote_hash(repo, &context.git.remote, &context.branch.name)?;
+
+    if let Some(remote_hash) = remote_hash {
+      if !repo::is_ancestor(repo, &remote_hash, &head) {
+        return Err(error(&format!(
+          "The branch '{}' has new commits on the remote '{}' since the release started. Pull them and run the release again.",
+          context.branch.name, context.git.remote
+        )));
+      }
+    }
+
+    Ok(())
+  }
+}
+
+impl Module for GitModule {
+  fn name(&self) -> &str {
+    NAME
+  }
+
+  fn prepare(&mut self, context: &Context) -> Result<(), FastReleaseError> {
+    context.get_next_release(NAME)?;
+    let repo: Repository = Repository::discover(&context.cwd)
+      .map_err(|v: git2::Error| git::error("Failed to open the git repository.", v))?;
+    let root: PathBuf = repo::get_root(&repo);
+
+    let files: Vec<PathBuf> = self.get_files(context, &root);
+    if files.is_empty() {
+      info!(
+        "There are no assets to commit for the project '{}'.",
+        context.project.name
+      );
+      return Ok(());
+    }
+    debug!(
+      message = format!(
+        "Committing the assets {}.",
+        files
+          .iter()
+          .map(|v: &PathBuf| format!("'{}'", v.display()))
+          .collect::<Vec<String>>()
+          .join(", ")
+      ),
+      category = "GIT"
+    );
+
+    if context.git.push {
+      self.verify_remote(&repo, context)?;
+    }
+
+    let hash: String = match repo::commit(
+      &repo,
+      &files,
+      context
+        .render(self.options.message.as_deref().unwrap_or(DEFAULT_MESSAGE))
+        .trim_end(),
+      &self.get_author(&repo)?,
+    )? {
+      Some(hash) => hash,
+      None => {
+        info!(
+          "The assets of the project '{}' didn't change, so there is nothing to commit.",
+          context.project.name
+        );
+        return Ok(());
+      }
+    };
+
+    if !context.git.push {
+      info!("Created the release commit '{}' without pushing it.", hash);
+      return Ok(());
+    }
+
+    // The commit is taken back when the push fails, so the release can run again
+    let refspec: String = format!("HEAD:refs/heads/{}", context.branch.name);
+    if let Err(push_error) = repo::push(&repo, &context.git.remote, &[refspec]) {
+      repo::undo_commit(&repo, &hash)?;
+      return Err(push_error);
+    }
+
+    info!(
+      "Created the release commit '{}' and pushed it to the branch '{}'.",
+      hash, context.branch.name
+    );
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    config::Config,
+    testing::{self, TestRepo},
+  };
+
+  const MODULE: &str = r#"
+name: git
+assets: [CHANGELOG.md, "docs/*.txt"]
+message: "chore(release): ${tag}\n\n${notes}"
+"#;
+
+  // A release of the repository whose assets changed since the last commit
+  fn prepare(test_repo: &TestRepo, git: &str) -> Result<(), FastReleaseError> {
+    test_repo.write("CHANGELOG.md", "# Changelog");
+    test_repo.write("docs/usage.txt", "usage");
+    test_repo.write("lib.rs", "lib");
+    test_repo.commit("feat: add the library");
+    test_repo.push();
+
+    test_repo.write("CHANGELOG.md", "# Changelog\n\n## 1.0.0");
+    test_repo.write("docs/usage.txt", "usage 2");
+    test_repo.write("lib.rs", "lib 2");
+
+    let config: Config = testing::get_config(&format!(
+      "
+version: 1
+tag_format: v${{version}}
+branches: [main]
+projects:
+  - name: app
+    path: .
+{}",
+      git
+    ));
+    let mut context: Context = testing::get_context(&config, &test_repo.path());
+    context.next_release = Some(testing::get_next_release("v1.0.0", "1.0.0"));
+
+    let mut module: Box<dyn Module> = GitModule::create(&testing::get_module(MODULE))?;
+    module.prepare(&context)
+  }
+
+  fn get_committed(test_repo: &TestRepo, path: &str) -> Option<String> {
+    let commit: git2::Commit = test_repo.repo.head().unwrap().peel_to_commit().unwrap();
+    let entry: git2::TreeEntry = commit.tree().unwrap().get_path(Path::new(path)).ok()?;
+    let blob: git2::Blob = test_repo.repo.find_blob(entry.id()).unwrap();
+    Some(String::from_utf8_lossy(blob.content()).to_string())
+  }
+
+  #[test]
+  fn commits_the_assets_with_the_message_of_the_release() {
+    let test_repo: TestRepo = TestRepo::new();
+    prepare(&test_repo, "").unwrap();
+
+    let commit: git2::Commit = test_repo.repo.head().unwrap().peel_to_commit().unwrap();
+    assert_eq!(
+      commit.message(),
+      Some("chore(release): v1.0.0\n\nThe notes of the release.")
+    );
+    assert_eq!(
+      get_committed(&test_repo, "CHANGELOG.md").as_deref(),
+      Some("# Changelog\n\n## 1.0.0")
+    );
+    assert_eq!(
+      get_committed(&test_repo, "docs/usage.txt").as_deref(),
+      Some("usage 2")
+    );
+    assert_eq!(get_committed(&test_repo, "lib.rs").as_deref(), Some("lib"));
+    assert_eq!(test_repo.remote_head(), Some(test_repo.head()));
+  }
+
+  #[test]
+  fn keeps_the_commit_local_when_pushing_is_off() {
+    let test_repo: TestRepo = TestRepo::new();
+    prepare(&test_repo, "git:\n  push: false\n").unwrap();
+
+    assert_ne!(test_repo.remote_head(), Some(test_repo.head()));
+    assert_eq!(
+      get_committed(&test_repo, "docs/usage.txt").as_deref(),
+      Some("usage 2")
+    );
+  }
+
+  #[test]
+  fn rejects_the_assets_that_arent_glob_patterns() {
+    let module: ConfigModule = testing::get_module("name: git\nassets: ['[']");
+
+    assert_eq!(
+      GitModule::create(&module)
+        .err()
+        .unwrap()
+        .category
+        .as_deref(),
+      Some("CONFIG")
+    );
+  }
+}
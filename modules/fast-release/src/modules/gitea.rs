@@ -0,0 +1,563 @@
+// Copyright (c) Toolbi Software. All rights reserved.
+// Check the README file in the project root for more information.
+
+use super::{get_options, Context, Module, NextRelease, ReleaseInfo};
+use crate::{
+  config::ConfigModule,
+  error::{FastReleaseError, FastReleaseErrorBuilder},
+  http,
+};
+use hosted_git_info::{FromUrlBuilder, HostType, HostedGitInfo};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{ffi::OsStr, fs, io::Error, path::PathBuf};
+use tracing::{debug, info};
+use ureq::{Agent, Request};
+use url::Url;
+
+pub const NAME: &str = "gitea";
+
+const DEFAULT_RELEASE_NAME: &str = "${tag}";
+const TOKEN_VARIABLES: [&str; 2] = ["GITEA_TOKEN", "FORGEJO_TOKEN"];
+// The base URL of the instance, like 'https://git.example.com'
+const URL_VARIABLES: [&str; 2] = ["GITEA_URL", "FORGEJO_URL"];
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AssetOptions {
+  path: String,
+  name: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum AssetConfig {
+  Path(String),
+  Asset(AssetOptions),
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GiteaOptions {
+  url: Option<String>,
+  api_url: Option<String>,
+  repository_url: Option<String>,
+  release_name: Option<String>,
+  #[serde(default)]
+  draft: bool,
+  #[serde(default)]
+  assets: Vec<AssetConfig>,
+}
+
+// The repository on the instance, resolved when verifying the conditions
+#[derive(Debug, Clone)]
+pub struct GiteaRepository {
+  pub api_url: String,
+  pub owner: String,
+  pub repo: String,
+  pub token: String,
+}
+
+impl GiteaRepository {
+  pub fn url(&self, path: &str) -> String {
+    format!(
+      "{}/repos/{}/{}{}",
+      self.api_url, self.owner, self.repo, path
+    )
+  }
+
+  pub fn request(&self, agent: &Agent, method: &str, url: &str) -> Request {
+    agent
+      .request(method, url)
+      .set("Authorization", &format!("token {}", self.token))
+      .set("Accept", "application/json")
+  }
+}
+
+#[derive(Debug, Deserialize)]
+struct RepositoryPermissions {
+  #[serde(default)]
+  push: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct RepositoryResponse {
+  permissions: Option<RepositoryPermissions>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReleaseResponse {
+  id: u64,
+  html_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TaggedRelease {
+  id: u64,
+  tag_name: String,
+}
+
+pub struct GiteaModule {
+  options: GiteaOptions,
+  repository: Option<GiteaRepository>,
+  agent: Agent,
+}
+
+fn error(message: &str) -> FastReleaseError {
+  FastReleaseErrorBuilder::new(message)
+    .category("GITEA")
+    .get()
+}
+
+impl GiteaModule {
+  pub fn create(module: &ConfigModule) -> Result<Box<dyn Module>, FastReleaseError> {
+    let options: GiteaOptions = get_options(module)?;
+
+    Ok(Box::new(Self {
+      options,
+      repository: None,
+      agent: http::agent(),
+    }))
+  }
+
+  // Gitea is mostly self-hosted, so only Codeberg is known without the URL of the instance
+  fn get_repository(&self, context: &Context) -> Result<GiteaRepository, FastReleaseError> {
+    let token: String = http::get_env(&TOKEN_VARIABLES).ok_or_else(|| {
+      error(&format!(
+        "The module '{}' needs a token on '{}' or '{}'.",
+        NAME, TOKEN_VARIABLES[0], TOKEN_VARIABLES[1]
+      ))
+    })?;
+
+    let repository_url: String = match &self.options.repository_url {
+      Some(url) => url.clone(),
+      None => context.get_remote_url()?,
+    };
+
+    let base_url: Option<String> = self
+      .options
+      .url
+      .clone()
+      .or_else(|| http::get_env(&URL_VARIABLES))
+      .map(|v: String| v.trim_end_matches('/').to_string());
+
+    let mut builder: FromUrlBuilder = FromUrlBuilder::new(&repository_url);
+    if let Some(domain) = base_url.as_deref().and_then(http::get_domain) {
+      builder = builder.host(&domain, HostType::Gitea);
+    }
+
+    let info: HostedGitInfo = hosted_git_info::from_url(&builder.get())
+      .filter(|v: &HostedGitInfo| v.host_type == HostType::Gitea)
+      .ok_or_else(|| {
+        error(&format!(
+          "The repository '{}' isn't on a known Gitea or Forgejo instance. Set the 'url' of the module '{}' or '{}'.",
+          repository_url, NAME, URL_VARIABLES[0]
+        ))
+      })?;
+
+    let api_url: String = match (&self.options.api_url, &base_url) {
+      (Some(api_url), _) => api_url.clone(),
+      (None, Some(base_url)) => format!("{}/api/v1", base_url),
+      (None, None) => format!("https://{}/api/v1", info.domain()),
+    };
+
+    Ok(GiteaRepository {
+      api_url: api_url.trim_end_matches('/').to_string(),
+      owner: info.user,
+      repo: info.project,
+      token,
+    })
+  }
+
+  // Drafts can't be found by their tag, so the release is looked up on the latest ones
+  fn find_release(
+    &self,
+    repository: &GiteaRepository,
+    tag: &str,
+  ) -> Result<Option<u64>, FastReleaseError> {
+    let response: ureq::Response = repository
+      .request(&self.agent, "GET", &repository.url("/releases"))
+      .query("limit", "50")
+      .call()
+      .map_err(|v: ureq::Error| http::error("Failed to get the Gitea releases.", "GITEA", v))?;
+    let releases: Vec<TaggedRelease> = http::read_json(response, "GITEA")?;
+
+    Ok(
+      releases
+        .into_iter()
+        .find(|v: &TaggedRelease| v.tag_name == tag)
+        .map(|v: TaggedRelease| v.id),
+    )
+  }
+
+  fn get_repository_ref(&self) -> Result<&GiteaRepository, FastReleaseError> {
+    self.repository.as_ref().ok_or_else(|| {
+      error(&format!(
+        "The module '{}' didn't verify its conditions.",
+        NAME
+      ))
+    })
+  }
+
+  fn upload_assets(
+    &self,
+    context: &Context,
+    repository: &GiteaRepository,
+    release: &ReleaseResponse,
+  ) -> Result<(), FastReleaseError> {
+    let upload_url: String = repository.url(&format!("/releases/{}/assets", release.id));
+
+    for asset in &self.options.assets {
+      let (pattern, name): (&str, Option<&String>) = match asset {
+        AssetConfig::Path(path) => (path, None),
+        AssetConfig::Asset(asset) => (&asset.path, asset.name.as_ref()),
+      };
+
+      let files: Vec<PathBuf> = context.find_files(pattern);
+      if files.is_empty() {
+        info!(
+          "The asset '{}' doesn't match any file, so it isn't uploaded.",
+          pattern
+        );
+        continue;
+      }
+
+      for file in &files {
+        let file_name: String = file
+          .file_name()
+          .map(|v: &OsStr| v.to_string_lossy().to_string())
+          .unwrap_or_default();
+        // A custom name only makes sense for a single file
+        let name: String = match (name, files.len()) {
+          (Some(name), 1) => context.render(name),
+          _ => file_name,
+        };
+
+        let mut url: Url = Url::parse(&upload_url).map_err(|v: url::ParseError| {
+          FastReleaseErrorBuilder::new(&format!("The upload URL '{}' is not valid.", upload_url))
+            .category("GITEA")
+            .error(Error::other(v))
+            .get()
+        })?;
+        url.query_pairs_mut().append_pair("name", &name);
+
+        let content: Vec<u8> = fs::read(file).map_err(|v: Error| {
+          FastReleaseErrorBuilder::new(&format!("Failed to read the asset '{}'.", file.display()))
+            .category("GITEA")
+            .error(v)
+            .get()
+        })?;
+        let (content_type, body): (String, Vec<u8>) =
+          http::multipart("attachment", &name, &content);
+
+        repository
+          .request(&self.agent, "POST", url.as_str())
+          .set("Content-Type", &content_type)
+          .send_bytes(&body)
+          .map_err(|v: ureq::Error| {
+            http::error(
+              &format!("Failed to upload the asset '{}'.", file.display()),
+              "GITEA",
+              v,
+            )
+          })?;
+
+        debug!(
+          message = format!("Uploaded the asset '{}' as '{}'.", file.display(), name),
+          category = "GITEA"
+        );
+      }
+    }
+
+    Ok(())
+  }
+}
+
+impl Module for GiteaModule {
+  fn name(&self) -> &str {
+    NAME
+  }
+
+  fn verify_conditions(&mut self, context: &Context) -> Result<(), FastReleaseError> {
+    let repository: GiteaRepository = self.get_repository(context)?;
+
+    let response: ureq::Response = repository
+      .request(&self.agent, "GET", &repository.url(""))
+      .call()
+      .map_err(|v: ureq::Error| {
+        let message: String = match http::status(&v) {
+          Some(401) => "The Gitea token is not valid.".to_string(),
+          Some(404) => format!(
+            "The repository '{}/{}' doesn't exist or the Gitea token can't access it.",
+            repository.owner, repository.repo
+          ),
+          _ => format!(
+            "Failed to get the repository '{}/{}'.",
+            repository.owner, repository.repo
+          ),
+        };
+        http::error(&message, "GITEA", v)
+      })?;
+    let details: RepositoryResponse = http::read_json(response, "GITEA")?;
+
+    if details
+      .permissions
+      .is_some_and(|v: RepositoryPermissions| !v.push)
+    {
+      return Err(error(&format!(
+        "The Gitea token can't push to the repository '{}/{}'.",
+        repository.owner, repository.repo
+      )));
+    }
+
+    debug!(
+      message = format!(
+        "Releasing on the Gitea repository '{}/{}' through '{}'.",
+        repository.owner, repository.repo, repository.api_url
+      ),
+      category = "GITEA"
+    );
+    self.repository = Some(repository);
+
+    Ok(())
+  }
+
+  // Like on GitHub, the release stays as a draft while the assets upload
+  fn publish(&mut self, context: &Context) -> Result<Option<ReleaseInfo>, FastReleaseError> {
+    let next_release: &NextRelease = context.get_next_release(NAME)?;
+    let repository: &GiteaRepository = self.get_repository_ref()?;
+    let draft_first: bool = !self.options.draft && !self.options.assets.is_empty();
+
+    let body: Value = json!({
+      "tag_name": next_release.tag,
+      "name": context.render(self.options.release_name.as_deref().unwrap_or(DEFAULT_RELEASE_NAME)),
+      "body": next_release.notes,
+      "draft": self.options.draft || draft_first,
+      "prerelease": context.branch.pre_release.is_some(),
+    });
+
+    let response: ureq::Response = repository
+      .request(&self.agent, "POST", &repository.url("/releases"))
+      .send_json(body)
+      .map_err(|v: ureq::Error| {
+        let message: String = match http::status(&v) {
+          Some(409) => format!("The Gitea release '{}' already exists.", next_release.tag),
+          _ => format!("Failed to create the Gitea release '{}'.", next_release.tag),
+        };
+        http::error(&message, "GITEA", v)
+      })?;
+    let mut release: ReleaseResponse = http::read_json(response, "GITEA")?;
+
+    self.upload_assets(context, repository, &release)?;
+
+    if draft_first {
+      let response: ureq::Response = repository
+        .request(
+          &self.agent,
+          "PATCH",
+          &repository.url(&format!("/releases/{}", release.id)),
+        )
+        .send_json(json!({ "draft": false }))
+        .map_err(|v: ureq::Error| {
+          http::error("Failed to publish the Gitea release.", "GITEA", v)
+        })?;
+      release = http::read_json(response, "GITEA")?;
+    }
+
+    info!(
+      "Published the Gitea release '{}' at '{}'.",
+      next_release.tag, release.html_url
+    );
+
+    Ok(Some(ReleaseInfo {
+      module: NAME.to_string(),
+      name: "Gitea release".to_string(),
+      url: Some(release.html_url),
+      channel: next_release.channel.clone(),
+    }))
+  }
+
+  // Deleting the release keeps its tag, which the lifecycle deletes
+  fn rollback(&mut self, context: &Context) -> Result<Option<String>, FastReleaseError> {
+    let next_release: &NextRelease = context.get_next_release(NAME)?;
+    let repository: &GiteaRepository = self.get_repository_ref()?;
+
+    let id: u64 = match self.find_release(repository, &next_release.tag)? {
+      Some(id) => id,
+      None => return Ok(None),
+    };
+    repository
+      .request(
+        &self.agent,
+        "DELETE",
+        &repository.url(&format!("/releases/{}", id)),
+      )
+      .call()
+      .map_err(|v: ureq::Error| {
+        http::error(
+          &format!("Failed to delete the Gitea release '{}'.", next_release.tag),
+          "GITEA",
+          v,
+        )
+      })?;
+
+    Ok(Some(format!(
+      "Deleted the Gitea release '{}'.",
+      next_release.tag
+    )))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    config::Config,
+    testing::{self, StubRequest, StubResponse, StubServer},
+  };
+  use std::path::Path;
+  use tempfile::TempDir;
+
+  const CONFIG: &str = "
+version: 1
+tag_format: v${version}
+branches: [main]
+projects:
+  - name: app
+    path: .
+";
+
+  // A Codeberg repository where the new releases get the id 1, or where the release already
+  // exists
+  fn start_server(exists: bool) -> StubServer {
+    StubServer::start(move |request: &StubRequest| {
+      let release = |id: u64| -> String {
+        json!({ "id": id, "html_url": format!("https://codeberg.org/acme/app/releases/{}", id) })
+          .to_string()
+      };
+
+      match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/repos/acme/app") => StubResponse::new(200, r#"{"permissions":{"push":true}}"#),
+        ("POST", "/repos/acme/app/releases") if exists => {
+          StubResponse::new(409, r#"{"message":"release already exists"}"#)
+        }
+        ("POST", "/repos/acme/app/releases") => StubResponse::new(201, &release(1)),
+        ("PATCH", "/repos/acme/app/releases/1") => StubResponse::new(200, &release(1)),
+        ("POST", path) if path.starts_with("/repos/acme/app/releases/1/assets?") => {
+          StubResponse::new(201, "{}")
+        }
+        _ => StubResponse::new(404, r#"{"message":"Not Found"}"#),
+      }
+    })
+  }
+
+  fn publish(
+    server: &StubServer,
+    cwd: &Path,
+    options: &str,
+  ) -> Result<Option<ReleaseInfo>, FastReleaseError> {
+    let config: Config = testing::get_config(CONFIG);
+    let mut context: Context = testing::get_context(&config, cwd);
+    context.next_release = Some(testing::get_next_release("v1.0.0", "1.0.0"));
+
+    let mut module: Box<dyn Module> = GiteaModule::create(&testing::get_module(&format!(
+      "{{ name: gitea, api_url: '{}', repository_url: 'https://codeberg.org/acme/app.git', {} }}",
+      server.url, options
+    )))?;
+
+    testing::with_env(
+      &[
+        ("GITEA_TOKEN", Some("secret")),
+        ("FORGEJO_TOKEN", None),
+        ("GITEA_URL", None),
+        ("FORGEJO_URL", None),
+      ],
+      || {
+        module.verify_conditions(&context)?;
+        module.publish(&context)
+      },
+    )
+  }
+
+  fn get_requests(server: &StubServer) -> Vec<String> {
+    server
+      .requests()
+      .iter()
+      .map(|v: &StubRequest| format!("{} {}", v.method, v.path))
+      .collect()
+  }
+
+  #[test]
+  fn creates_a_release() {
+    let server: StubServer = start_server(false);
+    let cwd: TempDir = TempDir::new().unwrap();
+
+    let release: Option<ReleaseInfo> = publish(&server, cwd.path(), "").unwrap();
+
+    assert_eq!(
+      release.and_then(|v: ReleaseInfo| v.url),
+      Some("https://codeberg.org/acme/app/releases/1".to_string())
+    );
+    assert_eq!(
+      get_requests(&server),
+      ["GET /repos/acme/app", "POST /repos/acme/app/releases"]
+    );
+    let requests: Vec<StubRequest> = server.requests();
+    assert_eq!(requests[1].header("authorization"), Some("token secret"));
+    assert_eq!(
+      requests[1].json(),
+      json!({
+        "tag_name": "v1.0.0",
+        "name": "v1.0.0",
+        "body": "The notes of the release.",
+        "draft": false,
+        "prerelease": false,
+      })
+    );
+  }
+
+  // The release is a draft until the assets are up
+  #[test]
+  fn uploads_the_assets() {
+    let server: StubServer = start_server(false);
+    let cwd: TempDir = TempDir::new().unwrap();
+    fs::create_dir(cwd.path().join("dist")).unwrap();
+    fs::write(cwd.path().join("dist/b.txt"), "b").unwrap();
+
+    publish(
+      &server,
+      cwd.path(),
+      "assets: [{ path: 'dist/*.txt', name: 'app-${version}.txt' }]",
+    )
+    .unwrap();
+
+    assert_eq!(
+      get_requests(&server),
+      [
+        "GET /repos/acme/app",
+        "POST /repos/acme/app/releases",
+        "POST /repos/acme/app/releases/1/assets?name=app-1.0.0.txt",
+        "PATCH /repos/acme/app/releases/1",
+      ]
+    );
+    let requests: Vec<StubRequest> = server.requests();
+    assert_eq!(requests[1].json()["draft"], json!(true));
+    assert!(requests[2]
+      .header("content-type")
+      .is_some_and(|v: &str| v.starts_with("multipart/form-data; boundary=")));
+    let body: String = String::from_utf8_lossy(&requests[2].body).to_string();
+    assert!(body.contains(r#"name="attachment"; filename="app-1.0.0.txt""#));
+    assert_eq!(requests[3].json(), json!({ "draft": false }));
+  }
+
+  #[test]
+  fn fails_when_the_release_exists() {
+    let server: StubServer = start_server(true);
+    let cwd: TempDir = TempDir::new().unwrap();
+
+    let error: FastReleaseError = publish(&server, cwd.path(), "").unwrap_err();
+
+    assert_eq!(error.message, "The Gitea release 'v1.0.0' already exists.");
+    assert_eq!(error.category.as_deref(), Some("GITEA"));
+  }
+}
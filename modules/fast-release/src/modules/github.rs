@@ -0,0 +1,1195 @@
+// Copyright (c) Toolbi Software. All rights reserved.
+// Check the README file in the project root for more information.
+
+use super::{get_options, Context, Module, NextRelease, ReleaseInfo};
+use crate::{
+  config::ConfigModule,
+  error::{FastReleaseError, FastReleaseErrorBuilder},
+  http,
+  notify::{self, Comment, DEFAULT_FAIL_COMMENT, DEFAULT_FAIL_TITLE, DEFAULT_SUCCESS_COMMENT},
+};
+use hosted_git_info::{FromUrlBuilder, HostType, HostedGitInfo};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+use std::{collections::BTreeSet, fs, io::Error, path::PathBuf};
+use tracing::{debug, info, warn};
+use ureq::{Agent, Request};
+use url::Url;
+
+pub const NAME: &str = "github";
+
+const DEFAULT_API_URL: &str = "https://api.github.com";
+const DEFAULT_RELEASE_NAME: &str = "${tag}";
+const TOKEN_VARIABLES: [&str; 2] = ["GH_TOKEN", "GITHUB_TOKEN"];
+// The base URL of GitHub Enterprise instances, like 'https://github.example.com'
+const URL_VARIABLES: [&str; 2] = ["GH_URL", "GITHUB_URL"];
+const API_URL_VARIABLES: [&str; 2] = ["GH_API_URL", "GITHUB_API_URL"];
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AssetOptions {
+  path: String,
+  name: Option<String>,
+  label: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum AssetConfig {
+  Path(String),
+  Asset(AssetOptions),
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GitHubOptions {
+  url: Option<String>,
+  api_url: Option<String>,
+  repository_url: Option<String>,
+  release_name: Option<String>,
+  #[serde(default)]
+  draft: bool,
+  discussion_category: Option<String>,
+  #[serde(default)]
+  assets: Vec<AssetConfig>,
+  // The comment on the issues and pull requests of the release
+  #[serde(default)]
+  success_comment: Comment,
+  #[serde(default = "notify::default_released_labels")]
+  released_labels: Vec<String>,
+  // The issue opened when the release fails, which is closed by the next successful release
+  #[serde(default)]
+  fail_comment: Comment,
+  fail_title: Option<String>,
+  #[serde(default = "notify::default_fail_labels")]
+  fail_labels: Vec<String>,
+}
+
+// The repository on GitHub, resolved when verifying the conditions
+#[derive(Debug, Clone)]
+pub struct GitHubRepository {
+  pub api_url: String,
+  pub owner: String,
+  pub repo: String,
+  pub token: String,
+}
+
+impl GitHubRepository {
+  pub fn url(&self, path: &str) -> String {
+    format!(
+      "{}/repos/{}/{}{}",
+      self.api_url, self.owner, self.repo, path
+    )
+  }
+
+  pub fn request(&self, agent: &Agent, method: &str, url: &str) -> Request {
+    agent
+      .request(method, url)
+      .set("Authorization", &format!("Bearer {}", self.token))
+      .set("Accept", "application/vnd.github+json")
+      .set("X-GitHub-Api-Version", "2022-11-28")
+  }
+}
+
+#[derive(Debug, Deserialize)]
+struct RepositoryPermissions {
+  #[serde(default)]
+  push: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct RepositoryResponse {
+  permissions: Option<RepositoryPermissions>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AssetResponse {
+  name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReleaseResponse {
+  id: u64,
+  tag_name: String,
+  html_url: String,
+  upload_url: String,
+  #[serde(default)]
+  assets: Vec<AssetResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PullRequestResponse {
+  number: u64,
+  merged_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IssueResponse {
+  number: u64,
+  title: String,
+  html_url: String,
+}
+
+pub struct GitHubModule {
+  options: GitHubOptions,
+  repository: Option<GitHubRepository>,
+  agent: Agent,
+}
+
+fn error(message: &str) -> FastReleaseError {
+  FastReleaseErrorBuilder::new(message)
+    .category("GITHUB")
+    .get()
+}
+
+impl GitHubModule {
+  pub fn create(module: &ConfigModule) -> Result<Box<dyn Module>, FastReleaseError> {
+    let options: GitHubOptions = get_options(module)?;
+
+    Ok(Box::new(Self {
+      options,
+      repository: None,
+      agent: http::agent(),
+    }))
+  }
+
+  // The owner and the name of the repository come from its URL, which is the one of the remote
+  // unless it's configured
+  fn get_repository(&self, context: &Context) -> Result<GitHubRepository, FastReleaseError> {
+    let token: String = http::get_env(&TOKEN_VARIABLES).ok_or_else(|| {
+      error(&format!(
+        "The module '{}' needs a GitHub token on '{}' or '{}'.",
+        NAME, TOKEN_VARIABLES[0], TOKEN_VARIABLES[1]
+      ))
+    })?;
+
+    let repository_url: String = match &self.options.repository_url {
+      Some(url) => url.clone(),
+      None => context.get_remote_url()?,
+    };
+
+    let base_url: Option<String> = self
+      .options
+      .url
+      .clone()
+      .or_else(|| http::get_env(&URL_VARIABLES))
+      .map(|v: String| v.trim_end_matches('/').to_string());
+
+    let mut builder: FromUrlBuilder = FromUrlBuilder::new(&repository_url);
+    if let Some(domain) = base_url.as_deref().and_then(http::get_domain) {
+      builder = builder.host(&domain, HostType::GitHub);
+    }
+
+    let info: HostedGitInfo = hosted_git_info::from_url(&builder.get())
+      .filter(|v: &HostedGitInfo| v.host_type == HostType::GitHub)
+      .ok_or_else(|| {
+        error(&format!(
+          "The repository '{}' isn't on GitHub. Set the 'url' of the module '{}' for GitHub Enterprise.",
+          repository_url, NAME
+        ))
+      })?;
+
+    let api_url: String = match (
+      &self.options.api_url,
+      http::get_env(&API_URL_VARIABLES),
+      &base_url,
+    ) {
+      (Some(api_url), _, _) => api_url.clone(),
+      (None, Some(api_url), _) => api_url,
+      (None, None, Some(base_url)) => format!("{}/api/v3", base_url),
+      (None, None, None) => DEFAULT_API_URL.to_string(),
+    };
+
+    Ok(GitHubRepository {
+      api_url: api_url.trim_end_matches('/').to_string(),
+      owner: info.user,
+      repo: info.project,
+      token,
+    })
+  }
+
+  // Goes through the pages of a list until an item matches
+  fn find_in_pages<T: DeserializeOwned>(
+    &self,
+    repository: &GitHubRepository,
+    request: Request,
+    message: &str,
+    matches: impl Fn(&T) -> bool,
+  ) -> Result<Option<T>, FastReleaseError> {
+    let mut request: Option<Request> = Some(request);
+
+    while let Some(current) = request {
+      let response: ureq::Response = current
+        .call()
+        .map_err(|v: ureq::Error| http::error(message, "GITHUB", v))?;
+      let next_page: Option<String> = http::get_next_page(&response);
+      let items: Vec<T> = http::read_json(response, "GITHUB")?;
+
+      if let Some(item) = items.into_iter().find(&matches) {
+        return Ok(Some(item));
+      }
+      request = next_page.map(|v: String| repository.request(&self.agent, "GET", &v));
+    }
+
+    Ok(None)
+  }
+
+  // Drafts can't be found by their tag, so the release is looked up on the list
+  fn find_release(
+    &self,
+    repository: &GitHubRepository,
+    tag: &str,
+  ) -> Result<Option<ReleaseResponse>, FastReleaseError> {
+    let request: Request = repository
+      .request(&self.agent, "GET", &repository.url("/releases"))
+      .query("per_page", "100");
+
+    self.find_in_pages(
+      repository,
+      request,
+      "Failed to get the GitHub releases.",
+      |v: &ReleaseResponse| v.tag_name == tag,
+    )
+  }
+
+  fn get_repository_ref(&self) -> Result<&GitHubRepository, FastReleaseError> {
+    self.repository.as_ref().ok_or_else(|| {
+      error(&format!(
+        "The module '{}' didn't verify its conditions.",
+        NAME
+      ))
+    })
+  }
+
+  fn update_release(
+    &self,
+    repository: &GitHubRepository,
+    id: u64,
+    body: Value,
+  ) -> Result<ReleaseResponse, FastReleaseError> {
+    let response: ureq::Response = repository
+      .request(
+        &self.agent,
+        "PATCH",
+        &repository.url(&format!("/releases/{}", id)),
+      )
+      .send_json(body)
+      .map_err(|v: ureq::Error| http::error("Failed to update the GitHub release.", "GITHUB", v))?;
+
+    http::read_json(response, "GITHUB")
+  }
+
+  // The pull requests merged with the commits of the release, and the issues their messages
+  // reference. Pull requests are issues on GitHub, so both share the numbers.
+  fn get_released_issues(&self, context: &Context, repository: &GitHubRepository) -> BTreeSet<u64> {
+    let mut result: BTreeSet<u64> = notify::get_references(&context.commits, "#");
+
+    for commit in &context.commits {
+      let pull_requests: Vec<PullRequestResponse> = match repository
+        .request(
+          &self.agent,
+          "GET",
+          &repository.url(&format!("/commits/{}/pulls", commit.hash)),
+        )
+        .call()
+        .map_err(|v: ureq::Error| {
+          http::error("Failed to get the pull requests of a commit.", "GITHUB", v)
+        })
+        .and_then(|v: ureq::Response| http::read_json(v, "GITHUB"))
+      {
+        Ok(pull_requests) => pull_requests,
+        Err(error) => {
+          warn!(
+            "Failed to get the pull requests of the commit '{}': {}",
+            commit.short_hash(),
+            error
+          );
+          continue;
+        }
+      };
+
+      result.extend(
+        pull_requests
+          .iter()
+          .filter(|v: &&PullRequestResponse| v.merged_at.is_some())
+          .map(|v: &PullRequestResponse| v.number),
+      );
+    }
+
+    result
+  }
+
+  fn comment(
+    &self,
+    repository: &GitHubRepository,
+    number: u64,
+    body: &str,
+  ) -> Result<(), FastReleaseError> {
+    repository
+      .request(
+        &self.agent,
+        "POST",
+        &repository.url(&format!("/issues/{}/comments", number)),
+      )
+      .send_json(json!({ "body": body }))
+      .map_err(|v: ureq::Error| {
+        http::error(
+          &format!("Failed to comment on the issue #{}.", number),
+          "GITHUB",
+          v,
+        )
+      })?;
+
+    Ok(())
+  }
+
+  fn add_labels(
+    &self,
+    repository: &GitHubRepository,
+    number: u64,
+    labels: &[String],
+  ) -> Result<(), FastReleaseError> {
+    repository
+      .request(
+        &self.agent,
+        "POST",
+        &repository.url(&format!("/issues/{}/labels", number)),
+      )
+      .send_json(json!({ "labels": labels }))
+      .map_err(|v: ureq::Error| {
+        http::error(
+          &format!("Failed to add the labels to the issue #{}.", number),
+          "GITHUB",
+          v,
+        )
+      })?;
+
+    Ok(())
+  }
+
+  fn close_issue(
+    &self,
+    repository: &GitHubRepository,
+    number: u64,
+  ) -> Result<(), FastReleaseError> {
+    repository
+      .request(
+        &self.agent,
+        "PATCH",
+        &repository.url(&format!("/issues/{}", number)),
+      )
+      .send_json(json!({ "state": "closed" }))
+      .map_err(|v: ureq::Error| {
+        http::error(
+          &format!("Failed to close the issue #{}.", number),
+          "GITHUB",
+          v,
+        )
+      })?;
+
+    Ok(())
+  }
+
+  // The open issue with the labels and the title of the failures
+  fn find_fail_issue(
+    &self,
+    repository: &GitHubRepository,
+    title: &str,
+  ) -> Result<Option<IssueResponse>, FastReleaseError> {
+    let mut request: Request = repository
+      .request(&self.agent, "GET", &repository.url("/issues"))
+      .query("state", "open")
+      .query("per_page", "100");
+    if !self.options.fail_labels.is_empty() {
+      request = request.query("labels", &self.options.fail_labels.join(","));
+    }
+
+    self.find_in_pages(
+      repository,
+      request,
+      "Failed to get the open issues.",
+      |v: &IssueResponse| v.title == title,
+    )
+  }
+
+  fn upload_assets(
+    &self,
+    context: &Context,
+    repository: &GitHubRepository,
+    release: &ReleaseResponse,
+  ) -> Result<(), FastReleaseError> {
+    // The upload URL is a template like '.../assets{?name,label}'
+    let upload_url: &str = release
+      .upload_url
+      .split('{')
+      .next()
+      .unwrap_or(&release.upload_url);
+
+    for asset in &self.options.assets {
+      let (pattern, name, label): (&str, Option<&String>, Option<&String>) = match asset {
+        AssetConfig::Path(path) => (path, None, None),
+        AssetConfig::Asset(asset) => (&asset.path, asset.name.as_ref(), asset.label.as_ref()),
+      };
+
+      let files: Vec<PathBuf> = context.find_files(pattern);
+      if files.is_empty() {
+        info!(
+          "The asset '{}' doesn't match any file, so it isn't uploaded.",
+          pattern
+        );
+        continue;
+      }
+
+      for file in &files {
+        let file_name: String = file
+          .file_name()
+          .map(|v: &std::ffi::OsStr| v.to_string_lossy().to_string())
+          .unwrap_or_default();
+        // A custom name only makes sense for a single file
+        let name: String = match (name, files.len()) {
+          (Some(name), 1) => context.render(name),
+          _ => file_name,
+        };
+        if release
+          .assets
+          .iter()
+          .any(|v: &AssetResponse| v.name == name)
+        {
+          debug!(
+            message = format!("The asset '{}' is already on the release.", name),
+            category = "GITHUB"
+          );
+          continue;
+        }
+
+        let mut url: Url = Url::parse(upload_url).map_err(|v: url::ParseError| {
+          FastReleaseErrorBuilder::new(&format!("The upload URL '{}' is not valid.", upload_url))
+            .category("GITHUB")
+            .error(Error::other(v))
+            .get()
+        })?;
+        url.query_pairs_mut().append_pair("name", &name);
+        if let Some(label) = label {
+          url
+            .query_pairs_mut()
+            .append_pair("label", &context.render(label));
+        }
+
+        let content: Vec<u8> = fs::read(file).map_err(|v: Error| {
+          FastReleaseErrorBuilder::new(&format!("Failed to read the asset '{}'.", file.display()))
+            .category("GITHUB")
+            .error(v)
+            .get()
+        })?;
+
+        repository
+          .request(&self.agent, "POST", url.as_str())
+          .set(
+            "Content-Type",
+            mime_guess::from_path(file).first_or_octet_stream().as_ref(),
+          )
+          .send_bytes(&content)
+          .map_err(|v: ureq::Error| {
+            http::error(
+              &format!("Failed to upload the asset '{}'.", file.display()),
+              "GITHUB",
+              v,
+            )
+          })?;
+
+        debug!(
+          message = format!("Uploaded the asset '{}' as '{}'.", file.display(), name),
+          category = "GITHUB"
+        );
+      }
+    }
+
+    Ok(())
+  }
+}
+
+impl Module for GitHubModule {
+  fn name(&self) -> &str {
+    NAME
+  }
+
+  fn verify_conditions(&mut self, context: &Context) -> Result<(), FastReleaseError> {
+    let repository: GitHubRepository = self.get_repository(context)?;
+
+    let response: ureq::Response = repository
+      .request(&self.agent, "GET", &repository.url(""))
+      .call()
+      .map_err(|v: ureq::Error| {
+        let message: String = match http::status(&v) {
+          Some(401) => "The GitHub token is not valid.".to_string(),
+          Some(404) => format!(
+            "The repository '{}/{}' doesn't exist or the GitHub token can't access it.",
+            repository.owner, repository.repo
+          ),
+          _ => format!(
+            "Failed to get the repository '{}/{}'.",
+            repository.owner, repository.repo
+          ),
+        };
+        http::error(&message, "GITHUB", v)
+      })?;
+    let details: RepositoryResponse = http::read_json(response, "GITHUB")?;
+
+    if details
+      .permissions
+      .is_some_and(|v: RepositoryPermissions| !v.push)
+    {
+      return Err(error(&format!(
+        "The GitHub token can't push to the repository '{}/{}'.",
+        repository.owner, repository.repo
+      )));
+    }
+
+    debug!(
+      message = format!(
+        "Releasing on the GitHub repository '{}/{}' through '{}'.",
+        repository.owner, repository.repo, repository.api_url
+      ),
+      category = "GITHUB"
+    );
+    self.repository = Some(repository);
+
+    Ok(())
+  }
+
+  // The release stays as a draft while the assets upload, so it never shows up incomplete
+  fn publish(&mut self, context: &Context) -> Result<Option<ReleaseInfo>, FastReleaseError> {
+    let next_release: &NextRelease = context.get_next_release(NAME)?;
+    let repository: &GitHubRepository = self.get_repository_ref()?;
+    let draft_first: bool = !self.options.draft && !self.options.assets.is_empty();
+
+    let mut body: Value = json!({
+      "tag_name": next_release.tag,
+      "name": context.render(self.options.release_name.as_deref().unwrap_or(DEFAULT_RELEASE_NAME)),
+      "body": next_release.notes,
+      "draft": self.options.draft || draft_first,
+      "prerelease": context.branch.pre_release.is_some(),
+    });
+    // Drafts can't start a discussion
+    if let Some(category) = self
+      .options
+      .discussion_category
+      .as_ref()
+      .filter(|_| !self.options.draft && !draft_first)
+    {
+      body["discussion_category_name"] = json!(category);
+    }
+
+    // A release resumed after failing halfway can have its GitHub release already, along with
+    // some of the assets
+    let mut release: ReleaseResponse = match self.find_release(repository, &next_release.tag)? {
+      Some(existing) => {
+        info!(
+          "The GitHub release '{}' already exists, so it's updated.",
+          next_release.tag
+        );
+        self.update_release(repository, existing.id, body)?
+      }
+      None => {
+        let response: ureq::Response = repository
+          .request(&self.agent, "POST", &repository.url("/releases"))
+          .send_json(body)
+          .map_err(|v: ureq::Error| {
+            http::error(
+              &format!(
+                "Failed to create the GitHub release '{}'.",
+                next_release.tag
+              ),
+              "GITHUB",
+              v,
+            )
+          })?;
+        http::read_json(response, "GITHUB")?
+      }
+    };
+
+    self.upload_assets(context, repository, &release)?;
+
+    if draft_first {
+      let mut body: Value = json!({ "draft": false });
+      if let Some(category) = &self.options.discussion_category {
+        body["discussion_category_name"] = json!(category);
+      }
+      release = self.update_release(repository, release.id, body)?;
+    }
+
+    info!(
+      "Published the GitHub release '{}' at '{}'.",
+      next_release.tag, release.html_url
+    );
+
+    Ok(Some(ReleaseInfo {
+      module: NAME.to_string(),
+      name: "GitHub release".to_string(),
+      url: Some(release.html_url),
+      channel: next_release.channel.clone(),
+    }))
+  }
+
+  // Deleting the release keeps its tag, which the lifecycle deletes
+  fn rollback(&mut self, context: &Context) -> Result<Option<String>, FastReleaseError> {
+    let next_release: &NextRelease = context.get_next_release(NAME)?;
+    let repository: &GitHubRepository = self.get_repository_ref()?;
+
+    let id: u64 = match self.find_release(repository, &next_release.tag)? {
+      Some(release) => release.id,
+      None => return Ok(None),
+    };
+    repository
+      .request(
+        &self.agent,
+        "DELETE",
+        &repository.url(&format!("/releases/{}", id)),
+      )
+      .call()
+      .map_err(|v: ureq::Error| {
+        http::error(
+          &format!(
+            "Failed to delete the GitHub release '{}'.",
+            next_release.tag
+          ),
+          "GITHUB",
+          v,
+        )
+      })?;
+
+    Ok(Some(format!(
+      "Deleted the GitHub release '{}'.",
+      next_release.tag
+    )))
+  }
+
+  // The release is already out, so the comments and the issue updates that fail are only logged
+  fn success(&mut self, context: &Context) -> Result<(), FastReleaseError> {
+    let repository: &GitHubRepository = self.get_repository_ref()?;
+
+    if let Some(template) = self.options.success_comment.get(DEFAULT_SUCCESS_COMMENT) {
+      let comment: String = notify::success_comment(context, template);
+      let labels: Vec<String> = self
+        .options
+        .released_labels
+        .iter()
+        .map(|v: &String| context.render(v))
+        .collect();
+
+      for number in self.get_released_issues(context, repository) {
+        let result: Result<(), FastReleaseError> = self
+          .comment(repository, number, &comment)
+          .and_then(|_| match labels.is_empty() {
+            true => Ok(()),
+            false => self.add_labels(repository, number, &labels),
+          });
+
+        match result {
+          Ok(_) => info!("Commented on the GitHub issue #{}.", number),
+          Err(error) => warn!("{}", error),
+        }
+      }
+    }
+
+    if self
+      .options
+      .fail_comment
+      .get(DEFAULT_FAIL_COMMENT)
+      .is_some()
+    {
+      let title: String = context.render(
+        self
+          .options
+          .fail_title
+          .as_deref()
+          .unwrap_or(DEFAULT_FAIL_TITLE),
+      );
+
+      let result: Result<Option<IssueResponse>, FastReleaseError> = self
+        .find_fail_issue(repository, &title)
+        .and_then(|v: Option<IssueResponse>| match v {
+          Some(issue) => self
+            .close_issue(repository, issue.number)
+            .map(|_| Some(issue)),
+          None => Ok(None),
+        });
+
+      match result {
+        Ok(Some(issue)) => info!(
+          "Closed the GitHub issue of the failures at '{}'.",
+          issue.html_url
+        ),
+        Ok(None) => {}
+        Err(error) => warn!("{}", error),
+      }
+    }
+
+    Ok(())
+  }
+
+  // The failures are on a single issue, which gets a comment for every new failure
+  fn fail(
+    &mut self,
+    context: &Context,
+    errors: &[FastReleaseError],
+  ) -> Result<(), FastReleaseError> {
+    let template: &str = match self.options.fail_comment.get(DEFAULT_FAIL_COMMENT) {
+      Some(template) => template,
+      None => return Ok(()),
+    };
+    // The release can fail before this module verifies its conditions
+    let repository: GitHubRepository = match &self.repository {
+      Some(repository) => repository.clone(),
+      None => self.get_repository(context)?,
+    };
+
+    let title: String = context.render(
+      self
+        .options
+        .fail_title
+        .as_deref()
+        .unwrap_or(DEFAULT_FAIL_TITLE),
+    );
+    let body: String = notify::fail_comment(context, template, errors);
+
+    if let Some(issue) = self.find_fail_issue(&repository, &title)? {
+      self.comment(&repository, issue.number, &body)?;
+      info!(
+        "Added the failures to the GitHub issue at '{}'.",
+        issue.html_url
+      );
+      return Ok(());
+    }
+
+    let response: ureq::Response = repository
+      .request(&self.agent, "POST", &repository.url("/issues"))
+      .send_json(json!({
+        "title": title,
+        "body": body,
+        "labels": self.options.fail_labels,
+      }))
+      .map_err(|v: ureq::Error| {
+        http::error("Failed to open the issue of the failures.", "GITHUB", v)
+      })?;
+    let issue: IssueResponse = http::read_json(response, "GITHUB")?;
+    info!(
+      "Opened a GitHub issue with the failures at '{}'.",
+      issue.html_url
+    );
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    config::Config,
+    testing::{self, StubRequest, StubResponse, StubServer},
+  };
+  use std::path::Path;
+  use tempfile::TempDir;
+
+  const CONFIG: &str = "
+version: 1
+tag_format: v${version}
+branches: [main]
+projects:
+  - name: app
+    path: .
+";
+
+  // A GitHub repository with the releases listed, where the new ones get the id 1
+  fn start_server(releases: &'static str) -> StubServer {
+    StubServer::start(move |request: &StubRequest| {
+      let release = |id: u64| -> String {
+        json!({
+          "id": id,
+          "tag_name": "v1.0.0",
+          "html_url": format!("https://github.com/acme/app/releases/{}", id),
+          "upload_url": format!(
+            "http://{}/uploads/{}/assets{{?name,label}}",
+            request.header("host").unwrap_or_default(),
+            id
+          ),
+          "assets": [{ "name": "a.txt" }],
+        })
+        .to_string()
+      };
+
+      match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/repos/acme/app") => StubResponse::new(200, r#"{"permissions":{"push":true}}"#),
+        ("GET", "/repos/acme/app/releases?per_page=100") => StubResponse::new(200, releases),
+        ("POST", "/repos/acme/app/releases") => StubResponse::new(201, &release(1)),
+        ("PATCH", path) if path.starts_with("/repos/acme/app/releases/") => {
+          let id: u64 = path.rsplit('/').next().unwrap().parse().unwrap();
+          StubResponse::new(200, &release(id))
+        }
+        ("POST", path) if path.starts_with("/uploads/") => StubResponse::new(201, "{}"),
+        _ => StubResponse::new(404, r#"{"message":"Not Found"}"#),
+      }
+    })
+  }
+
+  fn get_context(cwd: &Path) -> Context {
+    let config: Config = testing::get_config(CONFIG);
+    let mut context: Context = testing::get_context(&config, cwd);
+    context.next_release = Some(testing::get_next_release("v1.0.0", "1.0.0"));
+    context
+  }
+
+  // Runs the step after verifying the conditions, with the token of GitHub
+  fn run_step<T>(
+    server: &StubServer,
+    context: &Context,
+    options: &str,
+    step: impl FnOnce(&mut Box<dyn Module>, &Context) -> Result<T, FastReleaseError>,
+  ) -> Result<T, FastReleaseError> {
+    let mut module: Box<dyn Module> = GitHubModule::create(&testing::get_module(&format!(
+      "{{ name: github, api_url: '{}', repository_url: 'https://github.com/acme/app.git', {} }}",
+      server.url, options
+    )))?;
+
+    testing::with_env(
+      &[
+        ("GH_TOKEN", Some("secret")),
+        ("GH_URL", None),
+        ("GITHUB_URL", None),
+        ("GH_API_URL", None),
+        ("GITHUB_API_URL", None),
+      ],
+      || {
+        module.verify_conditions(context)?;
+        step(&mut module, context)
+      },
+    )
+  }
+
+  fn publish(
+    server: &StubServer,
+    cwd: &Path,
+    options: &str,
+  ) -> Result<Option<ReleaseInfo>, FastReleaseError> {
+    run_step(
+      server,
+      &get_context(cwd),
+      options,
+      |module: &mut Box<dyn Module>, context: &Context| module.publish(context),
+    )
+  }
+
+  fn get_requests(server: &StubServer) -> Vec<String> {
+    server
+      .requests()
+      .iter()
+      .map(|v: &StubRequest| format!("{} {}", v.method, v.path))
+      .collect()
+  }
+
+  #[test]
+  fn creates_a_release() {
+    let server: StubServer = start_server("[]");
+    let cwd: TempDir = TempDir::new().unwrap();
+
+    let release: Option<ReleaseInfo> = publish(&server, cwd.path(), "").unwrap();
+
+    assert_eq!(
+      release.and_then(|v: ReleaseInfo| v.url),
+      Some("https://github.com/acme/app/releases/1".to_string())
+    );
+    let requests: Vec<StubRequest> = server.requests();
+    let create: &StubRequest = requests.last().unwrap();
+    assert_eq!(create.path, "/repos/acme/app/releases");
+    assert_eq!(create.header("authorization"), Some("Bearer secret"));
+    assert_eq!(
+      create.json(),
+      json!({
+        "tag_name": "v1.0.0",
+        "name": "v1.0.0",
+        "body": "The notes of the release.",
+        "draft": false,
+        "prerelease": false,
+      })
+    );
+  }
+
+  // The release is a draft until the assets are up
+  #[test]
+  fn uploads_the_assets() {
+    let server: StubServer = start_server("[]");
+    let cwd: TempDir = TempDir::new().unwrap();
+    fs::create_dir(cwd.path().join("dist")).unwrap();
+    fs::write(cwd.path().join("dist/b.txt"), "b").unwrap();
+
+    publish(
+      &server,
+      cwd.path(),
+      "assets: [{ path: 'dist/*.txt', name: 'app-${version}.txt', label: 'App' }]",
+    )
+    .unwrap();
+
+    assert_eq!(
+      get_requests(&server),
+      [
+        "GET /repos/acme/app",
+        "GET /repos/acme/app/releases?per_page=100",
+        "POST /repos/acme/app/releases",
+        "POST /uploads/1/assets?name=app-1.0.0.txt&label=App",
+        "PATCH /repos/acme/app/releases/1",
+      ]
+    );
+    let requests: Vec<StubRequest> = server.requests();
+    assert_eq!(requests[2].json()["draft"], json!(true));
+    assert_eq!(requests[3].header("content-type"), Some("text/plain"));
+    assert_eq!(requests[3].body, b"b");
+    assert_eq!(requests[4].json(), json!({ "draft": false }));
+  }
+
+  // A resumed release updates the release it created before, without uploading its assets again
+  #[test]
+  fn updates_an_existing_release() {
+    let server: StubServer = start_server(
+      r#"[{"id":7,"tag_name":"v1.0.0","html_url":"","upload_url":"","assets":[{"name":"a.txt"}]}]"#,
+    );
+    let cwd: TempDir = TempDir::new().unwrap();
+    fs::write(cwd.path().join("a.txt"), "a").unwrap();
+    fs::write(cwd.path().join("b.txt"), "b").unwrap();
+
+    let release: Option<ReleaseInfo> = publish(&server, cwd.path(), "assets: ['*.txt']").unwrap();
+
+    assert_eq!(
+      release.and_then(|v: ReleaseInfo| v.url),
+      Some("https://github.com/acme/app/releases/7".to_string())
+    );
+    assert_eq!(
+      get_requests(&server),
+      [
+        "GET /repos/acme/app",
+        "GET /repos/acme/app/releases?per_page=100",
+        "PATCH /repos/acme/app/releases/7",
+        "POST /uploads/7/assets?name=b.txt",
+        "PATCH /repos/acme/app/releases/7",
+      ]
+    );
+    assert_eq!(server.requests()[2].json()["tag_name"], json!("v1.0.0"));
+  }
+
+  // The release is on the second page of the list
+  #[test]
+  fn finds_the_release_on_the_next_pages() {
+    let server: StubServer = StubServer::start(|request: &StubRequest| {
+      match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/repos/acme/app") => StubResponse::new(200, "{}"),
+        ("GET", "/repos/acme/app/releases?per_page=100") => {
+          let next: String = format!(
+            "<http://{}/repositories/1/releases?per_page=100&page=2>; rel=\"next\"",
+            request.header("host").unwrap_or_default()
+          );
+          StubResponse::new(
+            200,
+            r#"[{"id":8,"tag_name":"v1.1.0","html_url":"","upload_url":""}]"#,
+          )
+          .header("Link", &next)
+        }
+        ("GET", "/repositories/1/releases?per_page=100&page=2") => StubResponse::new(
+          200,
+          r#"[{"id":7,"tag_name":"v1.0.0","html_url":"","upload_url":""}]"#,
+        ),
+        ("PATCH", "/repos/acme/app/releases/7") => StubResponse::new(
+          200,
+          r#"{"id":7,"tag_name":"v1.0.0","html_url":"https://github.com/acme/app/releases/7","upload_url":""}"#,
+        ),
+        _ => StubResponse::new(404, r#"{"message":"Not Found"}"#),
+      }
+    });
+    let cwd: TempDir = TempDir::new().unwrap();
+
+    publish(&server, cwd.path(), "").unwrap();
+
+    assert_eq!(
+      get_requests(&server),
+      [
+        "GET /repos/acme/app",
+        "GET /repos/acme/app/releases?per_page=100",
+        "GET /repositories/1/releases?per_page=100&page=2",
+        "PATCH /repos/acme/app/releases/7",
+      ]
+    );
+  }
+
+  #[test]
+  fn reports_the_body_of_the_errors() {
+    let server: StubServer =
+      StubServer::start(|request: &StubRequest| match request.method.as_str() {
+        "GET" if request.path.contains("/releases") => StubResponse::new(200, "[]"),
+        "GET" => StubResponse::new(200, "{}"),
+        _ => StubResponse::new(422, r#"{"message":"Validation Failed"}"#),
+      });
+    let cwd: TempDir = TempDir::new().unwrap();
+
+    let error: FastReleaseError = publish(&server, cwd.path(), "").unwrap_err();
+
+    assert_eq!(
+      error.message,
+      "Failed to create the GitHub release 'v1.0.0'."
+    );
+    assert_eq!(error.category.as_deref(), Some("GITHUB"));
+    assert!(error
+      .to_string()
+      .contains(r#"The server responded with the status 422: {"message":"Validation Failed"}"#));
+  }
+
+  // A repository where the commit '1111...' belongs to the merged pull request #14, with the
+  // failure issue #3 open when there is one
+  fn start_issues_server(has_fail_issue: bool) -> StubServer {
+    StubServer::start(move |request: &StubRequest| {
+      match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/repos/acme/app") => StubResponse::new(200, "{}"),
+        ("GET", path) if path.starts_with("/repos/acme/app/commits/") => StubResponse::new(
+          200,
+          r#"[{"number":14,"merged_at":"2024-01-01T00:00:00Z"},{"number":15,"merged_at":null}]"#,
+        ),
+        ("GET", path) if path.starts_with("/repos/acme/app/issues?") => match has_fail_issue {
+          true => StubResponse::new(
+            200,
+            r#"[{"number":3,"title":"The automated release of 'app' is failing","html_url":"https://github.com/acme/app/issues/3"}]"#,
+          ),
+          false => StubResponse::new(200, "[]"),
+        },
+        ("POST", "/repos/acme/app/issues") => StubResponse::new(
+          201,
+          r#"{"number":4,"title":"","html_url":"https://github.com/acme/app/issues/4"}"#,
+        ),
+        ("POST", _) | ("PATCH", _) => StubResponse::new(200, "{}"),
+        _ => StubResponse::new(404, r#"{"message":"Not Found"}"#),
+      }
+    })
+  }
+
+  #[test]
+  fn comments_on_the_released_issues_and_closes_the_fail_issue() {
+    let server: StubServer = start_issues_server(true);
+    let cwd: TempDir = TempDir::new().unwrap();
+    let mut context: Context = get_context(cwd.path());
+    context.commits = vec![crate::analyzer::commit::Commit::new(
+      &"1".repeat(40),
+      "fix: stop the crash (#12)",
+      "Tester",
+      "tester@example.com",
+      0,
+    )];
+
+    run_step(
+      &server,
+      &context,
+      "",
+      |module: &mut Box<dyn Module>, context: &Context| module.success(context),
+    )
+    .unwrap();
+
+    assert_eq!(
+      get_requests(&server),
+      [
+        "GET /repos/acme/app",
+        "GET /repos/acme/app/commits/1111111111111111111111111111111111111111/pulls",
+        "POST /repos/acme/app/issues/12/comments",
+        "POST /repos/acme/app/issues/12/labels",
+        "POST /repos/acme/app/issues/14/comments",
+        "POST /repos/acme/app/issues/14/labels",
+        "GET /repos/acme/app/issues?state=open&per_page=100&labels=fast-release",
+        "PATCH /repos/acme/app/issues/3",
+      ]
+    );
+    let requests: Vec<StubRequest> = server.requests();
+    assert_eq!(
+      requests[2].json(),
+      json!({ "body": notify::success_comment(&context, DEFAULT_SUCCESS_COMMENT) })
+    );
+    assert_eq!(requests[3].json(), json!({ "labels": ["released"] }));
+    assert_eq!(requests[7].json(), json!({ "state": "closed" }));
+  }
+
+  // The release is already out, so the search of the fail issue only logs its failure
+  #[test]
+  fn succeeds_when_the_fail_issue_cant_be_found() {
+    let server: StubServer =
+      StubServer::start(|request: &StubRequest| match request.method.as_str() {
+        "GET" if request.path == "/repos/acme/app" => StubResponse::new(200, "{}"),
+        _ => StubResponse::new(500, r#"{"message":"Server Error"}"#),
+      });
+    let cwd: TempDir = TempDir::new().unwrap();
+
+    run_step(
+      &server,
+      &get_context(cwd.path()),
+      "",
+      |module: &mut Box<dyn Module>, context: &Context| module.success(context),
+    )
+    .unwrap();
+
+    assert_eq!(
+      get_requests(&server),
+      [
+        "GET /repos/acme/app",
+        "GET /repos/acme/app/issues?state=open&per_page=100&labels=fast-release",
+      ]
+    );
+  }
+
+  #[test]
+  fn opens_an_issue_with_the_failures() {
+    let server: StubServer = start_issues_server(false);
+    let cwd: TempDir = TempDir::new().unwrap();
+    let context: Context = get_context(cwd.path());
+    let errors: Vec<FastReleaseError> = vec![error("The release failed.")];
+
+    run_step(
+      &server,
+      &context,
+      "fail_labels: [release, bug]",
+      |module: &mut Box<dyn Module>, context: &Context| module.fail(context, &errors),
+    )
+    .unwrap();
+
+    let requests: Vec<StubRequest> = server.requests();
+    assert_eq!(
+      requests[1].path,
+      "/repos/acme/app/issues?state=open&per_page=100&labels=release%2Cbug"
+    );
+    assert_eq!(requests[2].path, "/repos/acme/app/issues");
+    assert_eq!(
+      requests[2].json(),
+      json!({
+        "title": "The automated release of 'app' is failing",
+        "body": notify::fail_comment(&context, DEFAULT_FAIL_COMMENT, &errors),
+        "labels": ["release", "bug"],
+      })
+    );
+  }
+
+  #[test]
+  fn comments_on_the_open_fail_issue() {
+    let server: StubServer = start_issues_server(true);
+    let cwd: TempDir = TempDir::new().unwrap();
+    let context: Context = get_context(cwd.path());
+    let errors: Vec<FastReleaseError> = vec![error("The release failed.")];
+
+    run_step(
+      &server,
+      &context,
+      "",
+      |module: &mut Box<dyn Module>, context: &Context| module.fail(context, &errors),
+    )
+    .unwrap();
+
+    let requests: Vec<StubRequest> = server.requests();
+    assert_eq!(requests.len(), 3);
+    assert_eq!(requests[2].path, "/repos/acme/app/issues/3/comments");
+    assert!(requests[2].json()["body"]
+      .as_str()
+      .unwrap()
+      .contains("### The release failed."));
+  }
+}
@@ -0,0 +1,1131 @@
+// Copyright (c) Toolbi Software. All rights reserved.
+// Check the README file in the project root for more information.
+
+use super::{get_options, Context, Module, NextRelease, ReleaseInfo};
+use crate::{
+  config::ConfigModule,
+  error::{FastReleaseError, FastReleaseErrorBuilder},
+  http,
+  notify::{self, Comment, DEFAULT_FAIL_COMMENT, DEFAULT_FAIL_TITLE, DEFAULT_SUCCESS_COMMENT},
+};
+use env_ci::CiServices;
+use hosted_git_info::{FromUrlBuilder, HostType, HostedGitInfo};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+  collections::BTreeSet,
+  ffi::OsStr,
+  fs,
+  io::Error,
+  path::{Path, PathBuf},
+};
+use tracing::{debug, info, warn};
+use ureq::{Agent, Request};
+use url::Url;
+
+pub const NAME: &str = "gitlab";
+
+const DEFAULT_URL: &str = "https://gitlab.com";
+const DEFAULT_RELEASE_NAME: &str = "${tag}";
+const DEFAULT_PACKAGE_NAME: &str = "release";
+const TOKEN_VARIABLES: [&str; 2] = ["GL_TOKEN", "GITLAB_TOKEN"];
+// The base URL of self-managed instances, like 'https://gitlab.example.com'
+const URL_VARIABLES: [&str; 2] = ["GL_URL", "GITLAB_URL"];
+const API_URL_VARIABLES: [&str; 2] = ["GL_API_URL", "GITLAB_API_URL"];
+// Developers can create releases, unless the tags are protected
+const MIN_ACCESS_LEVEL: u64 = 30;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum LinkType {
+  Other,
+  Runbook,
+  Image,
+  Package,
+}
+
+// Files given by 'path' are uploaded to the Generic Package Registry, while 'url' links to
+// something that is already published
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AssetOptions {
+  path: Option<String>,
+  url: Option<String>,
+  name: Option<String>,
+  link_type: Option<LinkType>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum AssetConfig {
+  Path(String),
+  Asset(AssetOptions),
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GitLabOptions {
+  url: Option<String>,
+  api_url: Option<String>,
+  repository_url: Option<String>,
+  release_name: Option<String>,
+  package_name: Option<String>,
+  #[serde(default)]
+  assets: Vec<AssetConfig>,
+  // The comment on the issues and merge requests of the release
+  #[serde(default)]
+  success_comment: Comment,
+  #[serde(default = "notify::default_released_labels")]
+  released_labels: Vec<String>,
+  // The issue opened when the release fails, which is closed by the next successful release
+  #[serde(default)]
+  fail_comment: Comment,
+  fail_title: Option<String>,
+  #[serde(default = "notify::default_fail_labels")]
+  fail_labels: Vec<String>,
+}
+
+// Issues and merge requests have their own numbers, referenced as '#1' and '!1'
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum IssueKind {
+  Issue,
+  MergeRequest,
+}
+
+impl IssueKind {
+  fn segment(&self) -> &str {
+    match self {
+      IssueKind::Issue => "issues",
+      IssueKind::MergeRequest => "merge_requests",
+    }
+  }
+
+  fn prefix(&self) -> &str {
+    match self {
+      IssueKind::Issue => "#",
+      IssueKind::MergeRequest => "!",
+    }
+  }
+}
+
+// The project on GitLab, resolved when verifying the conditions
+#[derive(Debug, Clone)]
+pub struct GitLabProject {
+  pub api_url: Url,
+  // The full path with the groups, like 'group/subgroup/project'
+  pub path: String,
+  pub token: String,
+}
+
+impl GitLabProject {
+  // The path is encoded as a single segment, which the API takes in place of the ID
+  pub fn url(&self, segments: &[&str]) -> String {
+    let mut url: Url = self.api_url.clone();
+    if let Ok(mut path) = url.path_segments_mut() {
+      path
+        .pop_if_empty()
+        .push("projects")
+        .push(&self.path)
+        .extend(segments);
+    }
+
+    url.to_string()
+  }
+
+  pub fn request(&self, agent: &Agent, method: &str, url: &str) -> Request {
+    agent.request(method, url).set("PRIVATE-TOKEN", &self.token)
+  }
+}
+
+#[derive(Debug, Serialize)]
+struct ReleaseLink {
+  name: String,
+  url: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  link_type: Option<LinkType>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProjectAccess {
+  access_level: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProjectPermissions {
+  project_access: Option<ProjectAccess>,
+  group_access: Option<ProjectAccess>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProjectResponse {
+  permissions: Option<ProjectPermissions>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReleaseLinks {
+  #[serde(rename = "self")]
+  self_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReleaseResponse {
+  #[serde(rename = "_links")]
+  links: Option<ReleaseLinks>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MergeRequestResponse {
+  iid: u64,
+  state: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct IssueResponse {
+  iid: u64,
+  title: String,
+  web_url: String,
+}
+
+pub struct GitLabModule {
+  options: GitLabOptions,
+  project: Option<GitLabProject>,
+  agent: Agent,
+}
+
+fn error(message: &str) -> FastReleaseError {
+  FastReleaseErrorBuilder::new(message)
+    .category("GITLAB")
+    .get()
+}
+
+impl GitLabModule {
+  pub fn create(module: &ConfigModule) -> Result<Box<dyn Module>, FastReleaseError> {
+    let options: GitLabOptions = get_options(module)?;
+
+    for asset in &options.assets {
+      if let AssetConfig::Asset(asset) = asset {
+        if asset.path.is_some() == asset.url.is_some() {
+          return Err(
+            FastReleaseErrorBuilder::new(&format!(
+              "The assets of the module '{}' need either a 'path' or a 'url'.",
+              NAME
+            ))
+            .category("CONFIG")
+            .get(),
+          );
+        }
+      }
+    }
+
+    Ok(Box::new(Self {
+      options,
+      project: None,
+      agent: http::agent(),
+    }))
+  }
+
+  // Outside of the options and the environment, the instance running the pipeline is used
+  fn get_urls(&self, context: &Context) -> (Option<String>, String) {
+    let is_gitlab_ci: bool = matches!(context.ci_env.service, CiServices::GitLab);
+
+    let base_url: Option<String> = self
+      .options
+      .url
+      .clone()
+      .or_else(|| http::get_env(&URL_VARIABLES))
+      .or_else(|| context.ci_env.server_url.clone().filter(|_| is_gitlab_ci))
+      .map(|v: String| v.trim_end_matches('/').to_string());
+
+    let api_url: String = self
+      .options
+      .api_url
+      .clone()
+      .or_else(|| http::get_env(&API_URL_VARIABLES))
+      .or_else(|| context.ci_env.api_url.clone().filter(|_| is_gitlab_ci))
+      .unwrap_or_else(|| format!("{}/api/v4", base_url.as_deref().unwrap_or(DEFAULT_URL)));
+
+    (base_url, api_url)
+  }
+
+  fn get_project(&self, context: &Context) -> Result<GitLabProject, FastReleaseError> {
+    let token: String = http::get_env(&TOKEN_VARIABLES).ok_or_else(|| {
+      error(&format!(
+        "The module '{}' needs a GitLab token on '{}' or '{}'.",
+        NAME, TOKEN_VARIABLES[0], TOKEN_VARIABLES[1]
+      ))
+    })?;
+
+    let repository_url: String = match &self.options.repository_url {
+      Some(url) => url.clone(),
+      None => context.get_remote_url()?,
+    };
+
+    let (base_url, api_url): (Option<String>, String) = self.get_urls(context);
+
+    let mut builder: FromUrlBuilder = FromUrlBuilder::new(&repository_url);
+    if let Some(domain) = base_url.as_deref().and_then(http::get_domain) {
+      builder = builder.host(&domain, HostType::GitLab);
+    }
+
+    let info: HostedGitInfo = hosted_git_info::from_url(&builder.get())
+      .filter(|v: &HostedGitInfo| v.host_type == HostType::GitLab)
+      .ok_or_else(|| {
+        error(&format!(
+          "The repository '{}' isn't on GitLab. Set the 'url' of the module '{}' for self-managed instances.",
+          repository_url, NAME
+        ))
+      })?;
+
+    let api_url: Url = Url::parse(&api_url)
+      .ok()
+      .filter(|v: &Url| !v.cannot_be_a_base())
+      .ok_or_else(|| error(&format!("The GitLab API URL '{}' is not valid.", api_url)))?;
+
+    Ok(GitLabProject {
+      api_url,
+      path: format!("{}/{}", info.user, info.project),
+      token,
+    })
+  }
+
+  fn get_project_ref(&self) -> Result<&GitLabProject, FastReleaseError> {
+    self.project.as_ref().ok_or_else(|| {
+      error(&format!(
+        "The module '{}' didn't verify its conditions.",
+        NAME
+      ))
+    })
+  }
+
+  // The merge requests with the commits of the release, and the issues and merge requests their
+  // messages reference
+  fn get_released_issues(
+    &self,
+    context: &Context,
+    project: &GitLabProject,
+  ) -> BTreeSet<(IssueKind, u64)> {
+    let mut result: BTreeSet<(IssueKind, u64)> = BTreeSet::new();
+    for kind in [IssueKind::Issue, IssueKind::MergeRequest] {
+      result.extend(
+        notify::get_references(&context.commits, kind.prefix())
+          .into_iter()
+          .map(|v: u64| (kind, v)),
+      );
+    }
+
+    for commit in &context.commits {
+      let merge_requests: Vec<MergeRequestResponse> = match project
+        .request(
+          &self.agent,
+          "GET",
+          &project.url(&["repository", "commits", &commit.hash, "merge_requests"]),
+        )
+        .call()
+        .map_err(|v: ureq::Error| {
+          http::error("Failed to get the merge requests of a commit.", "GITLAB", v)
+        })
+        .and_then(|v: ureq::Response| http::read_json(v, "GITLAB"))
+      {
+        Ok(merge_requests) => merge_requests,
+        Err(error) => {
+          warn!(
+            "Failed to get the merge requests of the commit '{}': {}",
+            commit.short_hash(),
+            error
+          );
+          continue;
+        }
+      };
+
+      result.extend(
+        merge_requests
+          .iter()
+          .filter(|v: &&MergeRequestResponse| v.state == "merged")
+          .map(|v: &MergeRequestResponse| (IssueKind::MergeRequest, v.iid)),
+      );
+    }
+
+    result
+  }
+
+  fn comment(
+    &self,
+    project: &GitLabProject,
+    kind: IssueKind,
+    iid: u64,
+    body: &str,
+  ) -> Result<(), FastReleaseError> {
+    project
+      .request(
+        &self.agent,
+        "POST",
+        &project.url(&[kind.segment(), &iid.to_string(), "notes"]),
+      )
+      .send_json(json!({ "body": body }))
+      .map_err(|v: ureq::Error| {
+        http::error(
+          &format!("Failed to comment on {}{}.", kind.prefix(), iid),
+          "GITLAB",
+          v,
+        )
+      })?;
+
+    Ok(())
+  }
+
+  fn update_issue(
+    &self,
+    project: &GitLabProject,
+    kind: IssueKind,
+    iid: u64,
+    body: Value,
+  ) -> Result<(), FastReleaseError> {
+    project
+      .request(
+        &self.agent,
+        "PUT",
+        &project.url(&[kind.segment(), &iid.to_string()]),
+      )
+      .send_json(body)
+      .map_err(|v: ureq::Error| {
+        http::error(
+          &format!("Failed to update {}{}.", kind.prefix(), iid),
+          "GITLAB",
+          v,
+        )
+      })?;
+
+    Ok(())
+  }
+
+  // The open issue with the labels and the title of the failures
+  fn find_fail_issue(
+    &self,
+    project: &GitLabProject,
+    title: &str,
+  ) -> Result<Option<IssueResponse>, FastReleaseError> {
+    let mut request: Request = project
+      .request(&self.agent, "GET", &project.url(&["issues"]))
+      .query("state", "opened")
+      .query("search", title)
+      .query("in", "title")
+      .query("per_page", "100");
+    if !self.options.fail_labels.is_empty() {
+      request = request.query("labels", &self.options.fail_labels.join(","));
+    }
+
+    let response: ureq::Response = request
+      .call()
+      .map_err(|v: ureq::Error| http::error("Failed to get the open issues.", "GITLAB", v))?;
+    let issues: Vec<IssueResponse> = http::read_json(response, "GITLAB")?;
+
+    Ok(
+      issues
+        .into_iter()
+        .find(|v: &IssueResponse| v.title == title),
+    )
+  }
+
+  // Uploads a file to the Generic Package Registry and returns its download URL
+  fn upload_file(
+    &self,
+    project: &GitLabProject,
+    package: &str,
+    version: &str,
+    file: &Path,
+    name: &str,
+  ) -> Result<String, FastReleaseError> {
+    let url: String = project.url(&["packages", "generic", package, version, name]);
+
+    let content: Vec<u8> = fs::read(file).map_err(|v: Error| {
+      FastReleaseErrorBuilder::new(&format!("Failed to read the asset '{}'.", file.display()))
+        .category("GITLAB")
+        .error(v)
+        .get()
+    })?;
+
+    project
+      .request(&self.agent, "PUT", &url)
+      .set("Content-Type", "application/octet-stream")
+      .send_bytes(&content)
+      .map_err(|v: ureq::Error| {
+        http::error(
+          &format!(
+            "Failed to upload the asset '{}' to the package '{}'.",
+            file.display(),
+            package
+          ),
+          "GITLAB",
+          v,
+        )
+      })?;
+
+    debug!(
+      message = format!(
+        "Uploaded the asset '{}' to the package '{}' as '{}'.",
+        file.display(),
+        package,
+        name
+      ),
+      category = "GITLAB"
+    );
+
+    Ok(url)
+  }
+
+  fn get_links(
+    &self,
+    context: &Context,
+    project: &GitLabProject,
+  ) -> Result<Vec<ReleaseLink>, FastReleaseError> {
+    let next_release: &NextRelease = context.get_next_release(NAME)?;
+    let version: String = next_release.version.to_string();
+    let package: String = context.render(
+      self
+        .options
+        .package_name
+        .as_deref()
+        .unwrap_or(DEFAULT_PACKAGE_NAME),
+    );
+    let mut result: Vec<ReleaseLink> = Vec::new();
+
+    for asset in &self.options.assets {
+      let (path, url, name, link_type): (
+        Option<&String>,
+        Option<&String>,
+        Option<&String>,
+        Option<LinkType>,
+      ) = match asset {
+        AssetConfig::Path(path) => (Some(path), None, None, None),
+        AssetConfig::Asset(asset) => (
+          asset.path.as_ref(),
+          asset.url.as_ref(),
+          asset.name.as_ref(),
+          asset.link_type,
+        ),
+      };
+
+      if let Some(url) = url {
+        let url: String = context.render(url);
+        result.push(ReleaseLink {
+          name: name.map_or_else(|| url.clone(), |v: &String| context.render(v)),
+          url,
+          link_type,
+        });
+        continue;
+      }
+
+      let pattern: &str = path.map_or("", |v: &String| v.as_str());
+      let files: Vec<PathBuf> = context.find_files(pattern);
+      if files.is_empty() {
+        info!(
+          "The asset '{}' doesn't match any file, so it isn't uploaded.",
+          pattern
+        );
+        continue;
+      }
+
+      for file in &files {
+        let file_name: String = file
+          .file_name()
+          .map(|v: &OsStr| v.to_string_lossy().to_string())
+          .unwrap_or_default();
+        // A custom name only makes sense for a single file
+        let name: String = match (name, files.len()) {
+          (Some(name), 1) => context.render(name),
+          _ => file_name.clone(),
+        };
+
+        let url: String = self.upload_file(project, &package, &version, file, &file_name)?;
+        result.push(ReleaseLink {
+          name,
+          url,
+          link_type: link_type.or(Some(LinkType::Package)),
+        });
+      }
+    }
+
+    Ok(result)
+  }
+}
+
+impl Module for GitLabModule {
+  fn name(&self) -> &str {
+    NAME
+  }
+
+  fn verify_conditions(&mut self, context: &Context) -> Result<(), FastReleaseError> {
+    let project: GitLabProject = self.get_project(context)?;
+
+    let response: ureq::Response = project
+      .request(&self.agent, "GET", &project.url(&[]))
+      .call()
+      .map_err(|v: ureq::Error| {
+        let message: String = match http::status(&v) {
+          Some(401) => "The GitLab token is not valid.".to_string(),
+          Some(404) => format!(
+            "The project '{}' doesn't exist or the GitLab token can't access it.",
+            project.path
+          ),
+          _ => format!("Failed to get the project '{}'.", project.path),
+        };
+        http::error(&message, "GITLAB", v)
+      })?;
+    let details: ProjectResponse = http::read_json(response, "GITLAB")?;
+
+    // The access inherited from parent groups isn't listed, so only a known low level fails
+    let access_level: Option<u64> = details.permissions.and_then(|v: ProjectPermissions| {
+      [v.project_access, v.group_access]
+        .into_iter()
+        .flatten()
+        .map(|v: ProjectAccess| v.access_level)
+        .max()
+    });
+    if access_level.is_some_and(|v: u64| v < MIN_ACCESS_LEVEL) {
+      return Err(error(&format!(
+        "The GitLab token needs at least the Developer role on the project '{}' to create releases.",
+        project.path
+      )));
+    }
+
+    debug!(
+      message = format!(
+        "Releasing on the GitLab project '{}' through '{}'.",
+        project.path, project.api_url
+      ),
+      category = "GITLAB"
+    );
+    self.project = Some(project);
+
+    Ok(())
+  }
+
+  fn publish(&mut self, context: &Context) -> Result<Option<ReleaseInfo>, FastReleaseError> {
+    let next_release: &NextRelease = context.get_next_release(NAME)?;
+    let project: &GitLabProject = self.get_project_ref()?;
+
+    let links: Vec<ReleaseLink> = self.get_links(context, project)?;
+    let body: Value = json!({
+      "tag_name": next_release.tag,
+      "name": context.render(self.options.release_name.as_deref().unwrap_or(DEFAULT_RELEASE_NAME)),
+      "description": next_release.notes,
+      "assets": { "links": links },
+    });
+
+    let response: ureq::Response = project
+      .request(&self.agent, "POST", &project.url(&["releases"]))
+      .send_json(body)
+      .map_err(|v: ureq::Error| {
+        let message: String = match http::status(&v) {
+          Some(409) => format!("The GitLab release '{}' already exists.", next_release.tag),
+          _ => format!(
+            "Failed to create the GitLab release '{}'.",
+            next_release.tag
+          ),
+        };
+        http::error(&message, "GITLAB", v)
+      })?;
+    let release: ReleaseResponse = http::read_json(response, "GITLAB")?;
+    let url: Option<String> = release.links.and_then(|v: ReleaseLinks| v.self_url);
+
+    info!(
+      "Published the GitLab release '{}'{}.",
+      next_release.tag,
+      url
+        .as_ref()
+        .map(|v: &String| format!(" at '{}'", v))
+        .unwrap_or_default()
+    );
+
+    Ok(Some(ReleaseInfo {
+      module: NAME.to_string(),
+      name: "GitLab release".to_string(),
+      url,
+      channel: next_release.channel.clone(),
+    }))
+  }
+
+  // Deleting the release keeps its tag, which the lifecycle deletes
+  fn rollback(&mut self, context: &Context) -> Result<Option<String>, FastReleaseError> {
+    let next_release: &NextRelease = context.get_next_release(NAME)?;
+    let project: &GitLabProject = self.get_project_ref()?;
+
+    match project
+      .request(
+        &self.agent,
+        "DELETE",
+        &project.url(&["releases", &next_release.tag]),
+      )
+      .call()
+    {
+      Ok(_) => Ok(Some(format!(
+        "Deleted the GitLab release '{}'.",
+        next_release.tag
+      ))),
+      Err(ureq::Error::Status(404, _)) => Ok(None),
+      Err(error) => Err(http::error(
+        &format!(
+          "Failed to delete the GitLab release '{}'.",
+          next_release.tag
+        ),
+        "GITLAB",
+        error,
+      )),
+    }
+  }
+
+  // The release is already out, so the comments and the issue updates that fail are only logged
+  fn success(&mut self, context: &Context) -> Result<(), FastReleaseError> {
+    let project: &GitLabProject = self.get_project_ref()?;
+
+    if let Some(template) = self.options.success_comment.get(DEFAULT_SUCCESS_COMMENT) {
+      let comment: String = notify::success_comment(context, template);
+      let labels: Vec<String> = self
+        .options
+        .released_labels
+        .iter()
+        .map(|v: &String| context.render(v))
+        .collect();
+
+      for (kind, iid) in self.get_released_issues(context, project) {
+        let result: Result<(), FastReleaseError> = self
+          .comment(project, kind, iid, &comment)
+          .and_then(|_| match labels.is_empty() {
+            true => Ok(()),
+            false => self.update_issue(
+              project,
+              kind,
+              iid,
+              json!({ "add_labels": labels.join(",") }),
+            ),
+          });
+
+        match result {
+          Ok(_) => info!("Commented on {}{} of GitLab.", kind.prefix(), iid),
+          Err(error) => warn!("{}", error),
+        }
+      }
+    }
+
+    if self
+      .options
+      .fail_comment
+      .get(DEFAULT_FAIL_COMMENT)
+      .is_some()
+    {
+      let title: String = context.render(
+        self
+          .options
+          .fail_title
+          .as_deref()
+          .unwrap_or(DEFAULT_FAIL_TITLE),
+      );
+
+      let result: Result<Option<IssueResponse>, FastReleaseError> = self
+        .find_fail_issue(project, &title)
+        .and_then(|v: Option<IssueResponse>| match v {
+          Some(issue) => self
+            .update_issue(
+              project,
+              IssueKind::Issue,
+              issue.iid,
+              json!({ "state_event": "close" }),
+            )
+            .map(|_| Some(issue)),
+          None => Ok(None),
+        });
+
+      match result {
+        Ok(Some(issue)) => info!(
+          "Closed the GitLab issue of the failures at '{}'.",
+          issue.web_url
+        ),
+        Ok(None) => {}
+        Err(error) => warn!("{}", error),
+      }
+    }
+
+    Ok(())
+  }
+
+  // The failures are on a single issue, which gets a comment for every new failure
+  fn fail(
+    &mut self,
+    context: &Context,
+    errors: &[FastReleaseError],
+  ) -> Result<(), FastReleaseError> {
+    let template: &str = match self.options.fail_comment.get(DEFAULT_FAIL_COMMENT) {
+      Some(template) => template,
+      None => return Ok(()),
+    };
+    // The release can fail before this module verifies its conditions
+    let project: GitLabProject = match &self.project {
+      Some(project) => project.clone(),
+      None => self.get_project(context)?,
+    };
+
+    let title: String = context.render(
+      self
+        .options
+        .fail_title
+        .as_deref()
+        .unwrap_or(DEFAULT_FAIL_TITLE),
+    );
+    let body: String = notify::fail_comment(context, template, errors);
+
+    if let Some(issue) = self.find_fail_issue(&project, &title)? {
+      self.comment(&project, IssueKind::Issue, issue.iid, &body)?;
+      info!(
+        "Added the failures to the GitLab issue at '{}'.",
+        issue.web_url
+      );
+      return Ok(());
+    }
+
+    let response: ureq::Response = project
+      .request(&self.agent, "POST", &project.url(&["issues"]))
+      .send_json(json!({
+        "title": title,
+        "description": body,
+        "labels": self.options.fail_labels.join(","),
+      }))
+      .map_err(|v: ureq::Error| {
+        http::error("Failed to open the issue of the failures.", "GITLAB", v)
+      })?;
+    let issue: IssueResponse = http::read_json(response, "GITLAB")?;
+    info!(
+      "Opened a GitLab issue with the failures at '{}'.",
+      issue.web_url
+    );
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    config::Config,
+    testing::{self, StubRequest, StubResponse, StubServer},
+  };
+  use tempfile::TempDir;
+
+  const CONFIG: &str = "
+version: 1
+tag_format: v${version}
+branches: [main]
+projects:
+  - name: app
+    path: .
+";
+
+  fn start_server() -> StubServer {
+    StubServer::start(|request: &StubRequest| {
+      match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/api/v4/projects/acme%2Fapp") | ("GET", "/api/v4/projects/group%2Fsub%2Fapp") => {
+          StubResponse::new(
+            200,
+            r#"{"permissions":{"project_access":{"access_level":40}}}"#,
+          )
+        }
+        ("POST", "/api/v4/projects/acme%2Fapp/releases") => StubResponse::new(
+          201,
+          r#"{"_links":{"self":"https://gitlab.com/acme/app/-/releases/v1.0.0"}}"#,
+        ),
+        ("PUT", path) if path.contains("/packages/generic/") => StubResponse::new(201, "{}"),
+        _ => StubResponse::new(404, r#"{"message":"404 Not Found"}"#),
+      }
+    })
+  }
+
+  // A project where the commit '1111...' belongs to the merged merge request !14, with the
+  // failure issue #3 open when there is one
+  fn start_issues_server(has_fail_issue: bool) -> StubServer {
+    StubServer::start(move |request: &StubRequest| {
+      match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/api/v4/projects/acme%2Fapp") => StubResponse::new(
+          200,
+          r#"{"permissions":{"project_access":{"access_level":40}}}"#,
+        ),
+        ("GET", path) if path.ends_with("/merge_requests") => StubResponse::new(
+          200,
+          r#"[{"iid":14,"state":"merged"},{"iid":15,"state":"opened"}]"#,
+        ),
+        ("GET", path) if path.starts_with("/api/v4/projects/acme%2Fapp/issues?") => {
+          match has_fail_issue {
+            true => StubResponse::new(
+              200,
+              r#"[{"iid":3,"title":"The automated release of 'app' is failing","web_url":"https://gitlab.com/acme/app/-/issues/3"}]"#,
+            ),
+            false => StubResponse::new(200, "[]"),
+          }
+        }
+        ("POST", "/api/v4/projects/acme%2Fapp/issues") => StubResponse::new(
+          201,
+          r#"{"iid":4,"title":"","web_url":"https://gitlab.com/acme/app/-/issues/4"}"#,
+        ),
+        ("POST", _) | ("PUT", _) => StubResponse::new(200, "{}"),
+        _ => StubResponse::new(404, r#"{"message":"404 Not Found"}"#),
+      }
+    })
+  }
+
+  fn get_requests(server: &StubServer) -> Vec<String> {
+    server
+      .requests()
+      .iter()
+      .map(|v: &StubRequest| format!("{} {}", v.method, v.path))
+      .collect()
+  }
+
+  fn get_context(cwd: &Path) -> Context {
+    let config: Config = testing::get_config(CONFIG);
+    let mut context: Context = testing::get_context(&config, cwd);
+    context.next_release = Some(testing::get_next_release("v1.0.0", "1.0.0"));
+    context
+  }
+
+  fn get_module(options: &str) -> Box<dyn Module> {
+    GitLabModule::create(&testing::get_module(&format!(
+      "{{ name: gitlab, repository_url: 'https://gitlab.com/acme/app.git', {} }}",
+      options
+    )))
+    .unwrap()
+  }
+
+  fn with_env<T>(run: impl FnOnce() -> T) -> T {
+    testing::with_env(
+      &[
+        ("GL_TOKEN", Some("secret")),
+        ("GL_URL", None),
+        ("GITLAB_URL", None),
+        ("GL_API_URL", None),
+        ("GITLAB_API_URL", None),
+      ],
+      run,
+    )
+  }
+
+  #[test]
+  fn creates_a_release_with_links() {
+    let server: StubServer = start_server();
+    let cwd: TempDir = TempDir::new().unwrap();
+    let context: Context = get_context(cwd.path());
+    let mut module: Box<dyn Module> = get_module(&format!(
+      "api_url: '{}/api/v4', assets: [{{ url: 'https://example.com/app-${{version}}.zip', name: 'App', link_type: image }}]",
+      server.url
+    ));
+
+    let release: Option<ReleaseInfo> = with_env(|| {
+      module.verify_conditions(&context)?;
+      module.publish(&context)
+    })
+    .unwrap();
+
+    assert_eq!(
+      release.and_then(|v: ReleaseInfo| v.url),
+      Some("https://gitlab.com/acme/app/-/releases/v1.0.0".to_string())
+    );
+    let requests: Vec<StubRequest> = server.requests();
+    assert_eq!(requests[0].header("private-token"), Some("secret"));
+    assert_eq!(
+      requests[1].json(),
+      json!({
+        "tag_name": "v1.0.0",
+        "name": "v1.0.0",
+        "description": "The notes of the release.",
+        "assets": { "links": [{
+          "name": "App",
+          "url": "https://example.com/app-1.0.0.zip",
+          "link_type": "image",
+        }] },
+      })
+    );
+  }
+
+  #[test]
+  fn uploads_the_files_to_the_generic_package_registry() {
+    let server: StubServer = start_server();
+    let cwd: TempDir = TempDir::new().unwrap();
+    fs::create_dir(cwd.path().join("dist")).unwrap();
+    fs::write(cwd.path().join("dist/app.bin"), "binary").unwrap();
+    let context: Context = get_context(cwd.path());
+    let mut module: Box<dyn Module> = get_module(&format!(
+      "api_url: '{}/api/v4', package_name: '${{project}}', assets: ['dist/*.bin']",
+      server.url
+    ));
+
+    with_env(|| {
+      module.verify_conditions(&context)?;
+      module.publish(&context)
+    })
+    .unwrap();
+
+    let requests: Vec<StubRequest> = server.requests();
+    let package_url: String =
+      "/api/v4/projects/acme%2Fapp/packages/generic/app/1.0.0/app.bin".into();
+    assert_eq!(requests[1].method, "PUT");
+    assert_eq!(requests[1].path, package_url);
+    assert_eq!(requests[1].body, b"binary");
+    assert_eq!(
+      requests[2].json()["assets"]["links"],
+      json!([{
+        "name": "app.bin",
+        "url": format!("{}{}", server.url, package_url),
+        "link_type": "package",
+      }])
+    );
+  }
+
+  #[test]
+  fn reports_an_existing_release() {
+    let server: StubServer =
+      StubServer::start(|request: &StubRequest| match request.method.as_str() {
+        "GET" => StubResponse::new(200, "{}"),
+        _ => StubResponse::new(409, r#"{"message":"Release already exists"}"#),
+      });
+    let cwd: TempDir = TempDir::new().unwrap();
+    let context: Context = get_context(cwd.path());
+    let mut module: Box<dyn Module> = get_module(&format!("api_url: '{}/api/v4'", server.url));
+
+    let error: FastReleaseError = with_env(|| {
+      module.verify_conditions(&context)?;
+      module.publish(&context)
+    })
+    .unwrap_err();
+
+    assert_eq!(error.message, "The GitLab release 'v1.0.0' already exists.");
+    assert!(error.to_string().contains("Release already exists"));
+  }
+
+  // The instance running the pipeline is the default one, but only on GitLab CI
+  #[test]
+  fn uses_the_urls_of_gitlab_ci() {
+    let server: StubServer = start_server();
+    let cwd: TempDir = TempDir::new().unwrap();
+    let mut context: Context = get_context(cwd.path());
+    context.ci_env.server_url = Some("https://gitlab.example.com/".to_string());
+    context.ci_env.api_url = Some(format!("{}/api/v4", server.url));
+    let mut module: Box<dyn Module> = GitLabModule::create(&testing::get_module(
+      "{ name: gitlab, repository_url: 'https://gitlab.example.com/group/sub/app.git' }",
+    ))
+    .unwrap();
+
+    let defaults: GitLabModule = GitLabModule {
+      options: GitLabOptions::default(),
+      project: None,
+      agent: http::agent(),
+    };
+    assert_eq!(
+      with_env(|| defaults.get_urls(&context)),
+      (None, "https://gitlab.com/api/v4".to_string())
+    );
+
+    context.ci_env.service = CiServices::GitLab;
+    assert_eq!(
+      with_env(|| defaults.get_urls(&context)),
+      (
+        Some("https://gitlab.example.com".to_string()),
+        format!("{}/api/v4", server.url)
+      )
+    );
+
+    with_env(|| module.verify_conditions(&context)).unwrap();
+    assert_eq!(
+      server.requests()[0].path,
+      "/api/v4/projects/group%2Fsub%2Fapp"
+    );
+  }
+
+  #[test]
+  fn comments_on_the_released_issues_and_closes_the_fail_issue() {
+    let server: StubServer = start_issues_server(true);
+    let cwd: TempDir = TempDir::new().unwrap();
+    let mut context: Context = get_context(cwd.path());
+    context.commits = vec![crate::analyzer::commit::Commit::new(
+      &"1".repeat(40),
+      "fix: stop the crash\n\nCloses #12",
+      "Tester",
+      "tester@example.com",
+      0,
+    )];
+    let mut module: Box<dyn Module> = get_module(&format!("api_url: '{}/api/v4'", server.url));
+
+    with_env(|| {
+      module.verify_conditions(&context)?;
+      module.success(&context)
+    })
+    .unwrap();
+
+    let requests: Vec<String> = get_requests(&server);
+    assert_eq!(
+      requests[..8],
+      [
+        "GET /api/v4/projects/acme%2Fapp",
+        "GET /api/v4/projects/acme%2Fapp/repository/commits/1111111111111111111111111111111111111111/merge_requests",
+        "POST /api/v4/projects/acme%2Fapp/issues/12/notes",
+        "PUT /api/v4/projects/acme%2Fapp/issues/12",
+        "POST /api/v4/projects/acme%2Fapp/merge_requests/14/notes",
+        "PUT /api/v4/projects/acme%2Fapp/merge_requests/14",
+        "GET /api/v4/projects/acme%2Fapp/issues?state=opened&search=The+automated+release+of+%27app%27+is+failing&in=title&per_page=100&labels=fast-release",
+        "PUT /api/v4/projects/acme%2Fapp/issues/3",
+      ]
+    );
+    let requests: Vec<StubRequest> = server.requests();
+    assert_eq!(
+      requests[2].json(),
+      json!({ "body": notify::success_comment(&context, DEFAULT_SUCCESS_COMMENT) })
+    );
+    assert_eq!(requests[3].json(), json!({ "add_labels": "released" }));
+    assert_eq!(requests[7].json(), json!({ "state_event": "close" }));
+  }
+
+  #[test]
+  fn opens_an_issue_with_the_failures() {
+    let server: StubServer = start_issues_server(false);
+    let cwd: TempDir = TempDir::new().unwrap();
+    let context: Context = get_context(cwd.path());
+    let errors: Vec<FastReleaseError> = vec![error("The release failed.")];
+    let mut module: Box<dyn Module> = get_module(&format!(
+      "api_url: '{}/api/v4', fail_labels: [release, bug]",
+      server.url
+    ));
+
+    with_env(|| module.fail(&context, &errors)).unwrap();
+
+    let requests: Vec<StubRequest> = server.requests();
+    assert!(requests[0].path.ends_with("&labels=release%2Cbug"));
+    assert_eq!(requests[1].path, "/api/v4/projects/acme%2Fapp/issues");
+    assert_eq!(
+      requests[1].json(),
+      json!({
+        "title": "The automated release of 'app' is failing",
+        "description": notify::fail_comment(&context, DEFAULT_FAIL_COMMENT, &errors),
+        "labels": "release,bug",
+      })
+    );
+  }
+
+  #[test]
+  fn comments_on_the_open_fail_issue() {
+    let server: StubServer = start_issues_server(true);
+    let cwd: TempDir = TempDir::new().unwrap();
+    let context: Context = get_context(cwd.path());
+    let errors: Vec<FastReleaseError> = vec![error("The release failed.")];
+    let mut module: Box<dyn Module> = get_module(&format!("api_url: '{}/api/v4'", server.url));
+
+    with_env(|| module.fail(&context, &errors)).unwrap();
+
+    let requests: Vec<StubRequest> = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(
+      requests[1].path,
+      "/api/v4/projects/acme%2Fapp/issues/3/notes"
+    );
+    assert!(requests[1].json()["body"]
+      .as_str()
+      .unwrap()
+      .contains("### The release failed."));
+  }
+}
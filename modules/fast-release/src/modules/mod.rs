@@ -0,0 +1,279 @@
+// Copyright (c) Toolbi Software. All rights reserved.
+// Check the README file in the project root for more information.
+
+pub mod cargo;
+pub mod changelog;
+pub mod crates;
+pub mod exec;
+pub mod git;
+pub mod gitea;
+pub mod github;
+pub mod gitlab;
+pub mod notes;
+pub mod npm;
+pub mod plugin;
+pub mod version_files;
+pub mod webhook;
+
+use crate::{
+  analyzer::{commit::Commit, rules::ReleaseType},
+  branches::Branch,
+  config::{ConfigGit, ConfigModule, ConfigProject},
+  error::{FastReleaseError, FastReleaseErrorBuilder},
+  release::LastRelease,
+  tag_format::{self, PROJECT_PLACEHOLDER, VERSION_PLACEHOLDER},
+};
+use env_ci::CiEnv;
+use git2::Repository;
+use semver::Version;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_yaml::Value;
+use std::{io::Error, path::PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+  VerifyConditions,
+  AnalyzeCommits,
+  VerifyRelease,
+  GenerateNotes,
+  Prepare,
+  Publish,
+  AddChannel,
+  Success,
+  Fail,
+  Rollback,
+}
+
+impl std::fmt::Display for Step {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let value: &str = match self {
+      Step::VerifyConditions => "verify_conditions",
+      Step::AnalyzeCommits => "analyze_commits",
+      Step::VerifyRelease => "verify_release",
+      Step::GenerateNotes => "generate_notes",
+      Step::Prepare => "prepare",
+      Step::Publish => "publish",
+      Step::AddChannel => "add_channel",
+      Step::Success => "success",
+      Step::Fail => "fail",
+      Step::Rollback => "rollback",
+    };
+
+    write!(f, "{}", value)
+  }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NextRelease {
+  pub version: Version,
+  pub tag: String,
+  pub channel: Option<String>,
+  pub release_type: ReleaseType,
+  pub hash: String,
+  pub notes: String,
+}
+
+// A release published by a module, like a GitHub release or a registry package
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReleaseInfo {
+  pub module: String,
+  pub name: String,
+  pub url: Option<String>,
+  pub channel: Option<String>,
+}
+
+// What the rollback of a failed release took back, and what is left to clean up by hand
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Rollback {
+  pub done: Vec<String>,
+  pub manual: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Context {
+  pub cwd: PathBuf,
+  pub dry_run: bool,
+  pub ci_env: CiEnv,
+  pub git: ConfigGit,
+  pub branch: Branch,
+  pub project: ConfigProject,
+  pub commits: Vec<Commit>,
+  pub last_release: Option<LastRelease>,
+  pub next_release: Option<NextRelease>,
+  pub releases: Vec<ReleaseInfo>,
+  // Only on the 'fail' step of a release that was rolled back
+  pub rollback: Option<Rollback>,
+}
+
+impl Context {
+  // The directory of the project, which is the root of the repository for single projects
+  pub fn project_path(&self) -> PathBuf {
+    self.cwd.join(&self.project.path)
+  }
+
+  // The URL of the remote, which tells where the repository is hosted
+  pub fn get_remote_url(&self) -> Result<String, FastReleaseError> {
+    let repo: Repository = Repository::discover(&self.cwd)
+      .map_err(|v: git2::Error| crate::git::error("Failed to open the git repository.", v))?;
+
+    crate::git::repo::get_remote_url(&repo, &self.git.remote)
+  }
+
+  pub fn get_next_release(&self, module: &str) -> Result<&NextRelease, FastReleaseError> {
+    self.next_release.as_ref().ok_or_else(|| {
+      FastReleaseErrorBuilder::new("There is no release to work with on this step.")
+        .category(module)
+        .get()
+    })
+  }
+
+  // Fills the placeholders of the templates, like the tag messages or the release names
+  pub fn render(&self, template: &str) -> String {
+    let next_release: Option<&NextRelease> = self.next_release.as_ref();
+    let version: String = next_release
+      .map(|v: &NextRelease| v.version.to_string())
+      .unwrap_or_default();
+
+    tag_format::render_placeholders(
+      template,
+      &[
+        (VERSION_PLACEHOLDER, &version),
+        (PROJECT_PLACEHOLDER, &self.project.name),
+        ("tag", next_release.map_or("", |v: &NextRelease| &v.tag)),
+        (
+          "channel",
+          next_release
+            .and_then(|v: &NextRelease| v.channel.as_deref())
+            .unwrap_or(""),
+        ),
+        ("branch", &self.branch.name),
+        ("notes", next_release.map_or("", |v: &NextRelease| &v.notes)),
+      ],
+    )
+  }
+
+  // The files matching a glob pattern. Patterns starting with '/' are relative to the root of the
+  // repository, the rest to the project.
+  pub fn find_files(&self, pattern: &str) -> Vec<PathBuf> {
+    let pattern: PathBuf = match pattern.strip_prefix('/') {
+      Some(pattern) => self.cwd.join(pattern),
+      None => self.project_path().join(pattern),
+    };
+
+    match glob::glob(&pattern.to_string_lossy()) {
+      Ok(paths) => paths.flatten().filter(|v: &PathBuf| v.is_file()).collect(),
+      Err(_) => Vec::new(),
+    }
+  }
+}
+
+// Every step is optional. The steps after 'generate_notes' don't run on dry runs.
+pub trait Module {
+  fn name(&self) -> &str;
+
+  fn verify_conditions(&mut self, _context: &Context) -> Result<(), FastReleaseError> {
+    Ok(())
+  }
+
+  fn analyze_commits(
+    &mut self,
+    _context: &Context,
+  ) -> Result<Option<ReleaseType>, FastReleaseError> {
+    Ok(None)
+  }
+
+  fn verify_release(&mut self, _context: &Context) -> Result<(), FastReleaseError> {
+    Ok(())
+  }
+
+  fn generate_notes(&mut self, _context: &Context) -> Result<Option<String>, FastReleaseError> {
+    Ok(None)
+  }
+
+  fn prepare(&mut self, _context: &Context) -> Result<(), FastReleaseError> {
+    Ok(())
+  }
+
+  fn publish(&mut self, _context: &Context) -> Result<Option<ReleaseInfo>, FastReleaseError> {
+    Ok(None)
+  }
+
+  fn add_channel(&mut self, _context: &Context) -> Result<Option<ReleaseInfo>, FastReleaseError> {
+    Ok(None)
+  }
+
+  fn success(&mut self, _context: &Context) -> Result<(), FastReleaseError> {
+    Ok(())
+  }
+
+  // Takes back what the 'publish' step did when a release fails, telling what it took back. The
+  // errors are what must be cleaned up by hand.
+  fn rollback(&mut self, _context: &Context) -> Result<Option<String>, FastReleaseError> {
+    Ok(None)
+  }
+
+  fn fail(
+    &mut self,
+    _context: &Context,
+    _errors: &[FastReleaseError],
+  ) -> Result<(), FastReleaseError> {
+    Ok(())
+  }
+}
+
+//
+
+type ModuleConstructor = fn(&ConfigModule) -> Result<Box<dyn Module>, FastReleaseError>;
+
+fn get_constructor(name: &str) -> Option<ModuleConstructor> {
+  match name {
+    cargo::NAME => Some(cargo::CargoModule::create),
+    changelog::NAME => Some(changelog::ChangelogModule::create),
+    crates::NAME => Some(crates::CratesModule::create),
+    exec::NAME => Some(exec::ExecModule::create),
+    git::NAME => Some(git::GitModule::create),
+    gitea::NAME => Some(gitea::GiteaModule::create),
+    github::NAME => Some(github::GitHubModule::create),
+    gitlab::NAME => Some(gitlab::GitLabModule::create),
+    notes::NAME => Some(notes::NotesModule::create),
+    npm::NAME => Some(npm::NpmModule::create),
+    version_files::NAME => Some(version_files::VersionFilesModule::create),
+    webhook::NAME => Some(webhook::WebhookModule::create),
+    _ => None,
+  }
+}
+
+// Deserializes the options of a module into its own configuration type
+pub fn get_options<T: DeserializeOwned>(module: &ConfigModule) -> Result<T, FastReleaseError> {
+  match serde_yaml::from_value::<T>(Value::Mapping(module.options.clone())) {
+    Ok(options) => Ok(options),
+    Err(error) => Err(
+      FastReleaseErrorBuilder::new(&format!(
+        "The configuration of the module '{}' is not valid.",
+        module.name
+      ))
+      .category("CONFIG")
+      .error(Error::other(error))
+      .get(),
+    ),
+  }
+}
+
+pub fn load(module: &ConfigModule) -> Result<Box<dyn Module>, FastReleaseError> {
+  if module.plugin.is_some() {
+    return plugin::PluginModule::create(module);
+  }
+
+  match get_constructor(&module.name) {
+    Some(constructor) => constructor(module),
+    None => Err(
+      FastReleaseErrorBuilder::new(&format!("The module '{}' doesn't exist.", module.name))
+        .category("CONFIG")
+        .get(),
+    ),
+  }
+}
+
+pub fn validate(module: &ConfigModule) -> Result<(), FastReleaseError> {
+  load(module).map(|_| ())
+}
@@ -0,0 +1,571 @@
+// Copyright (c) Toolbi Software. All rights reserved.
+// Check the README file in the project root for more information.
+
+use super::{get_options, Context, Module, NextRelease};
+use crate::{
+  analyzer::{
+    self,
+    commit::{Commit, ConventionalCommit},
+  },
+  config::ConfigModule,
+  error::{FastReleaseError, FastReleaseErrorBuilder},
+  git,
+  release::LastRelease,
+};
+use chrono::Local;
+use git2::Repository;
+use handlebars::Handlebars;
+use hosted_git_info::{FromUrlBuilder, HostedGitInfo};
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use std::io::Error;
+use tracing::debug;
+
+pub const NAME: &str = "notes";
+
+const HEADER_TEMPLATE: &str =
+  "{{#if compare_url}}## [{{version}}]({{compare_url}}){{else}}## {{version}}{{/if}} ({{date}})";
+const COMMIT_TEMPLATE: &str = "* {{#if scope}}**{{scope}}:** {{/if}}{{subject}}{{#if commit_url}} ([{{short_hash}}]({{commit_url}})){{else}} ({{short_hash}}){{/if}}";
+const FOOTER_TEMPLATE: &str = "";
+const BREAKING_SECTION: &str = "⚠ BREAKING CHANGES";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NotesType {
+  #[serde(rename = "type")]
+  commit_type: String,
+  section: String,
+  #[serde(default)]
+  hidden: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NotesTemplates {
+  header: Option<String>,
+  commit: Option<String>,
+  footer: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NotesOptions {
+  repository_url: Option<String>,
+  types: Option<Vec<NotesType>>,
+  #[serde(default)]
+  templates: NotesTemplates,
+  breaking_section: Option<String>,
+  issue_prefixes: Option<Vec<String>>,
+}
+
+fn default_types() -> Vec<NotesType> {
+  [
+    ("feat", "Features"),
+    ("fix", "Bug Fixes"),
+    ("perf", "Performance Improvements"),
+    ("revert", "Reverts"),
+  ]
+  .iter()
+  .map(|(commit_type, section): &(&str, &str)| NotesType {
+    commit_type: commit_type.to_string(),
+    section: section.to_string(),
+    hidden: false,
+  })
+  .collect()
+}
+
+// The data every template gets
+#[derive(Debug, Serialize)]
+struct ReleaseData {
+  project: String,
+  version: String,
+  tag: String,
+  previous_version: Option<String>,
+  previous_tag: Option<String>,
+  date: String,
+  repository_url: Option<String>,
+  compare_url: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct CommitData {
+  hash: String,
+  short_hash: String,
+  commit_url: Option<String>,
+  #[serde(rename = "type")]
+  commit_type: String,
+  scope: Option<String>,
+  subject: String,
+  author_name: String,
+  author_email: String,
+}
+
+pub struct NotesModule {
+  options: NotesOptions,
+  types: Vec<NotesType>,
+  issue_regex: Regex,
+  templates: Handlebars<'static>,
+}
+
+fn config_error(message: &str, error: Option<Error>) -> FastReleaseError {
+  let builder: FastReleaseErrorBuilder = FastReleaseErrorBuilder::new(message).category("CONFIG");
+  match error {
+    Some(error) => builder.error(error).get(),
+    None => builder.get(),
+  }
+}
+
+impl NotesModule {
+  pub fn create(module: &ConfigModule) -> Result<Box<dyn Module>, FastReleaseError> {
+    Ok(Box::new(Self::new(module)?))
+  }
+
+  pub fn new(module: &ConfigModule) -> Result<Self, FastReleaseError> {
+    let options: NotesOptions = get_options(module)?;
+    let types: Vec<NotesType> = options.types.clone().unwrap_or_else(default_types);
+
+    let prefixes: Vec<String> = options
+      .issue_prefixes
+      .clone()
+      .unwrap_or_else(|| vec!["#".into()]);
+    if prefixes.iter().any(|v: &String| v.is_empty()) {
+      return Err(config_error(
+        &format!(
+          "The issue prefixes of the module '{}' can't be empty.",
+          NAME
+        ),
+        None,
+      ));
+    }
+    let issue_regex: Regex = Regex::new(&format!(
+      r"(^|[\s(])({})(\d+)\b",
+      prefixes
+        .iter()
+        .map(|v: &String| regex::escape(v))
+        .collect::<Vec<String>>()
+        .join("|")
+    ))
+    .unwrap();
+
+    let mut templates: Handlebars<'static> = Handlebars::new();
+    templates.register_escape_fn(handlebars::no_escape);
+    for (name, template) in [
+      (
+        "header",
+        options
+          .templates
+          .header
+          .as_deref()
+          .unwrap_or(HEADER_TEMPLATE),
+      ),
+      (
+        "commit",
+        options
+          .templates
+          .commit
+          .as_deref()
+          .unwrap_or(COMMIT_TEMPLATE),
+      ),
+      (
+        "footer",
+        options
+          .templates
+          .footer
+          .as_deref()
+          .unwrap_or(FOOTER_TEMPLATE),
+      ),
+    ] {
+      if let Err(error) = templates.register_template_string(name, template) {
+        return Err(config_error(
+          &format!(
+            "The '{}' template of the module '{}' is not valid.",
+            name, NAME
+          ),
+          Some(Error::other(error)),
+        ));
+      }
+    }
+
+    Ok(Self {
+      options,
+      types,
+      issue_regex,
+      templates,
+    })
+  }
+
+  // Uses the configured repository URL, or the URL of the remote of the repository
+  fn get_host(&self, context: &Context) -> Option<HostedGitInfo> {
+    let url: String = match &self.options.repository_url {
+      Some(url) => url.clone(),
+      None => {
+        let repo: Repository = Repository::discover(&context.cwd).ok()?;
+        git::repo::get_remote_url(&repo, &context.git.remote).ok()?
+      }
+    };
+
+    let result: Option<HostedGitInfo> = hosted_git_info::from_url(&FromUrlBuilder::new(&url).get());
+    if result.is_none() {
+      debug!(
+        message = format!(
+          "The repository URL '{}' isn't a known host, so the notes don't have links.",
+          url
+        ),
+        category = "NOTES"
+      );
+    }
+
+    result
+  }
+
+  fn render<T: Serialize>(&self, template: &str, data: &T) -> Result<String, FastReleaseError> {
+    self
+      .templates
+      .render(template, data)
+      .map_err(|v: handlebars::RenderError| {
+        FastReleaseErrorBuilder::new(&format!("Failed to render the '{}' template.", template))
+          .category("NOTES")
+          .error(Error::other(v))
+          .get()
+      })
+  }
+
+  fn link_issues(&self, text: &str, host: Option<&HostedGitInfo>) -> String {
+    let host: &HostedGitInfo = match host {
+      Some(host) => host,
+      None => return text.to_string(),
+    };
+
+    self
+      .issue_regex
+      .replace_all(text, |captures: &Captures| match host.issue(&captures[3]) {
+        Some(url) => format!(
+          "{}[{}{}]({})",
+          &captures[1], &captures[2], &captures[3], url
+        ),
+        None => captures[0].to_string(),
+      })
+      .to_string()
+  }
+
+  fn get_commit_data(
+    &self,
+    commit: &Commit,
+    conventional: &ConventionalCommit,
+    subject: &str,
+    host: Option<&HostedGitInfo>,
+  ) -> CommitData {
+    CommitData {
+      hash: commit.hash.clone(),
+      short_hash: commit.short_hash().to_string(),
+      commit_url: host.map(|v: &HostedGitInfo| v.commit(&commit.hash)),
+      commit_type: conventional.commit_type.clone(),
+      scope: conventional.scope.clone(),
+      subject: self.link_issues(subject, host),
+      author_name: commit.author_name.clone(),
+      author_email: commit.author_email.clone(),
+    }
+  }
+
+  fn render_section(
+    &self,
+    title: &str,
+    mut lines: Vec<(Option<String>, CommitData)>,
+  ) -> Result<Option<String>, FastReleaseError> {
+    if lines.is_empty() {
+      return Ok(None);
+    }
+
+    // Commits with the same scope end up next to each other
+    lines
+      .sort_by(|a: &(Option<String>, CommitData), b: &(Option<String>, CommitData)| a.0.cmp(&b.0));
+
+    let mut result: Vec<String> = vec![format!("### {}", title), String::new()];
+    for (_, data) in &lines {
+      result.push(self.render("commit", data)?);
+    }
+
+    Ok(Some(result.join("\n")))
+  }
+
+  // Renders the notes of a release made on the given date, formatted as 'YYYY-MM-DD'
+  pub fn generate(
+    &self,
+    context: &Context,
+    next_release: &NextRelease,
+    date: &str,
+  ) -> Result<String, FastReleaseError> {
+    let host: Option<HostedGitInfo> = self.get_host(context);
+    let commits: Vec<(&Commit, &ConventionalCommit)> = analyzer::filter_reverted(&context.commits)
+      .into_iter()
+      .filter_map(|v: &Commit| v.conventional.as_ref().map(|c: &ConventionalCommit| (v, c)))
+      .collect();
+
+    let release: ReleaseData = ReleaseData {
+      project: context.project.name.clone(),
+      version: next_release.version.to_string(),
+      tag: next_release.tag.clone(),
+      previous_version: context
+        .last_release
+        .as_ref()
+        .map(|v: &LastRelease| v.version.to_string()),
+      previous_tag: context
+        .last_release
+        .as_ref()
+        .map(|v: &LastRelease| v.tag.clone()),
+      date: date.to_string(),
+      repository_url: host.as_ref().map(|v: &HostedGitInfo| v.browse()),
+      compare_url: match (&host, &context.last_release) {
+        (Some(host), Some(last_release)) => host.compare(&last_release.tag, &next_release.tag),
+        _ => None,
+      },
+    };
+
+    let mut sections: Vec<String> = vec![self.render("header", &release)?];
+
+    // Breaking changes come first, using their notes when there are any
+    let mut breaking: Vec<(Option<String>, CommitData)> = Vec::new();
+    for (commit, conventional) in commits
+      .iter()
+      .filter(|(_, conventional): &&(&Commit, &ConventionalCommit)| conventional.breaking)
+    {
+      let notes: Vec<&str> = if conventional.breaking_notes.is_empty() {
+        vec![conventional.subject.as_str()]
+      } else {
+        conventional
+          .breaking_notes
+          .iter()
+          .map(|v: &String| v.as_str())
+          .collect()
+      };
+      for note in notes {
+        breaking.push((
+          conventional.scope.clone(),
+          self.get_commit_data(commit, conventional, note, host.as_ref()),
+        ));
+      }
+    }
+    let breaking_section: &str = self
+      .options
+      .breaking_section
+      .as_deref()
+      .unwrap_or(BREAKING_SECTION);
+    sections.extend(self.render_section(breaking_section, breaking)?);
+
+    let mut section_names: Vec<&str> = Vec::new();
+    for notes_type in self.types.iter().filter(|v: &&NotesType| !v.hidden) {
+      if !section_names.contains(&notes_type.section.as_str()) {
+        section_names.push(&notes_type.section);
+      }
+    }
+
+    // Types that share a section are rendered together
+    for section in section_names {
+      let lines: Vec<(Option<String>, CommitData)> = commits
+        .iter()
+        .filter(|(_, conventional): &&(&Commit, &ConventionalCommit)| {
+          self.types.iter().any(|v: &NotesType| {
+            !v.hidden && v.section == section && v.commit_type == conventional.commit_type
+          })
+        })
+        .map(|(commit, conventional): &(&Commit, &ConventionalCommit)| {
+          (
+            conventional.scope.clone(),
+            self.get_commit_data(commit, conventional, &conventional.subject, host.as_ref()),
+          )
+        })
+        .collect();
+      sections.extend(self.render_section(section, lines)?);
+    }
+
+    let footer: String = self.render("footer", &release)?;
+    if !footer.trim().is_empty() {
+      sections.push(footer);
+    }
+
+    Ok(sections.join("\n\n"))
+  }
+}
+
+impl Module for NotesModule {
+  fn name(&self) -> &str {
+    NAME
+  }
+
+  fn generate_notes(&mut self, context: &Context) -> Result<Option<String>, FastReleaseError> {
+    let next_release: &NextRelease = context.get_next_release(NAME)?;
+    let date: String = Local::now().format("%Y-%m-%d").to_string();
+    let notes: String = self.generate(context, next_release, &date)?;
+
+    Ok(Some(notes))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{config::Config, testing};
+  use semver::Version;
+  use tempfile::TempDir;
+
+  const CONFIG: &str = "
+version: 1
+tag_format: v${version}
+branches: [main]
+projects:
+  - name: app
+    path: .
+";
+
+  // A repository with the given 'origin' remote, so the notes link to its host
+  fn get_context(remote_url: &str, messages: &[&str]) -> (TempDir, Context) {
+    let cwd: TempDir = TempDir::new().unwrap();
+    let repo: Repository = Repository::init(cwd.path()).unwrap();
+    repo.remote("origin", remote_url).unwrap();
+
+    let config: Config = testing::get_config(CONFIG);
+    let mut context: Context = testing::get_context(&config, cwd.path());
+    context.commits = messages
+      .iter()
+      .enumerate()
+      .map(|(index, message): (usize, &&str)| {
+        Commit::new(
+          &format!("{}{}", index + 1, "0".repeat(39)),
+          message,
+          "Tester",
+          "tester@example.com",
+          0,
+        )
+      })
+      .collect();
+    context.last_release = Some(LastRelease {
+      version: Version::parse("1.0.0").unwrap(),
+      tag: "v1.0.0".to_string(),
+      hash: "f".repeat(40),
+    });
+
+    (cwd, context)
+  }
+
+  fn generate(module: &str, context: &Context) -> String {
+    NotesModule::new(&testing::get_module(module))
+      .unwrap()
+      .generate(
+        context,
+        &testing::get_next_release("v1.1.0", "1.1.0"),
+        "2024-05-01",
+      )
+      .unwrap()
+  }
+
+  #[test]
+  fn groups_the_commits_with_the_breaking_changes_first() {
+    let (_cwd, context): (TempDir, Context) = get_context(
+      "/tmp/app.git",
+      &[
+        "fix(core): stop the crash",
+        "feat: add the option",
+        "chore: update the dependencies",
+        "feat(api)!: drop the old endpoint",
+        "fix(api): check the input",
+        "perf: cache the results\n\nBREAKING CHANGE: the cache needs a directory",
+      ],
+    );
+
+    assert_eq!(
+      generate("{ name: notes }", &context),
+      "## 1.1.0 (2024-05-01)
+
+### ⚠ BREAKING CHANGES
+
+* the cache needs a directory (6000000)
+* **api:** drop the old endpoint (4000000)
+
+### Features
+
+* add the option (2000000)
+* **api:** drop the old endpoint (4000000)
+
+### Bug Fixes
+
+* **api:** check the input (5000000)
+* **core:** stop the crash (1000000)
+
+### Performance Improvements
+
+* cache the results (6000000)"
+    );
+  }
+
+  #[test]
+  fn renders_the_custom_templates() {
+    let (_cwd, context): (TempDir, Context) = get_context(
+      "/tmp/app.git",
+      &[
+        "feat: add the option",
+        "fix: stop the crash",
+        "docs: explain the option",
+      ],
+    );
+
+    assert_eq!(
+      generate(
+        r##"
+name: notes
+types:
+  - { type: feat, section: Changes }
+  - { type: fix, section: Changes }
+  - { type: docs, section: Documentation, hidden: true }
+breaking_section: Breaking
+templates:
+  header: "# {{project}} {{version}} (from {{previous_version}})"
+  commit: "- {{type}}: {{subject}} by {{author_name}}"
+  footer: "Released as {{tag}}."
+"##,
+        &context
+      ),
+      "# app 1.1.0 (from 1.0.0)
+
+### Changes
+
+- feat: add the option by Tester
+- fix: stop the crash by Tester
+
+Released as v1.1.0."
+    );
+  }
+
+  #[test]
+  fn links_to_the_github_remote() {
+    let (_cwd, context): (TempDir, Context) = get_context(
+      "git@github.com:acme/app.git",
+      &["fix: stop the crash (#12)"],
+    );
+
+    assert_eq!(
+      generate("{ name: notes }", &context),
+      "## [1.1.0](https://github.com/acme/app/compare/v1.0.0...v1.1.0) (2024-05-01)
+
+### Bug Fixes
+
+* stop the crash ([#12](https://github.com/acme/app/issues/12)) ([1000000](https://github.com/acme/app/commit/1000000000000000000000000000000000000000))"
+    );
+  }
+
+  #[test]
+  fn links_to_the_gitlab_remote() {
+    let (_cwd, context): (TempDir, Context) = get_context(
+      "https://gitlab.com/acme/app.git",
+      &["fix: stop the crash (#12)"],
+    );
+
+    assert_eq!(
+      generate("{ name: notes }", &context),
+      "## [1.1.0](https://gitlab.com/acme/app/-/compare/v1.0.0...v1.1.0) (2024-05-01)
+
+### Bug Fixes
+
+* stop the crash ([#12](https://gitlab.com/acme/app/-/issues/12)) ([1000000](https://gitlab.com/acme/app/-/commit/1000000000000000000000000000000000000000))"
+    );
+  }
+}
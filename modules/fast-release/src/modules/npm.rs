@@ -0,0 +1,608 @@
+// Copyright (c) Toolbi Software. All rights reserved.
+// Check the README file in the project root for more information.
+
+use super::{get_options, Context, Module, NextRelease, ReleaseInfo};
+use crate::{
+  config::ConfigModule,
+  error::{FastReleaseError, FastReleaseErrorBuilder},
+  http,
+};
+use serde::Deserialize;
+use serde_json::Value;
+use std::{
+  env, fs,
+  io::Error,
+  path::PathBuf,
+  process::{self, Command, Output},
+};
+use tracing::{debug, info};
+use url::Url;
+
+pub const NAME: &str = "npm";
+
+const PACKAGE_FILE: &str = "package.json";
+const DEFAULT_REGISTRY: &str = "https://registry.npmjs.org/";
+const DEFAULT_DIST_TAG: &str = "latest";
+const TOKEN_VARIABLE: &str = "NPM_TOKEN";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NpmOptions {
+  registry: Option<String>,
+  // The folder with the 'package.json' to publish, relative to the project
+  pkg_root: Option<String>,
+  // 'public' or 'restricted', which scoped packages need on their first version
+  access: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+struct Package {
+  name: String,
+  private: bool,
+  path: PathBuf,
+  registry: String,
+}
+
+pub struct NpmModule {
+  options: NpmOptions,
+  package: Option<Package>,
+  // The npm configuration with the token of the registry, which replaces the one of the user
+  npmrc: Option<PathBuf>,
+}
+
+fn error(message: &str) -> FastReleaseError {
+  FastReleaseErrorBuilder::new(message).category("NPM").get()
+}
+
+// The registry key of the token on the npm configuration, like '//registry.npmjs.org/'
+fn get_registry_key(registry: &str) -> Option<String> {
+  let url: Url = Url::parse(registry).ok()?;
+  let port: String = url
+    .port()
+    .map(|v: u16| format!(":{}", v))
+    .unwrap_or_default();
+  let path: &str = url.path().trim_end_matches('/');
+
+  Some(format!("//{}{}{}/", url.host_str()?, port, path))
+}
+
+fn is_not_found(error: &FastReleaseError) -> bool {
+  error
+    .error
+    .as_ref()
+    .is_some_and(|v: &Error| v.to_string().contains("E404"))
+}
+
+// Every channel is a dist-tag, where the default channel is 'latest'
+fn get_dist_tag(next_release: &NextRelease) -> &str {
+  next_release.channel.as_deref().unwrap_or(DEFAULT_DIST_TAG)
+}
+
+impl NpmModule {
+  pub fn create(module: &ConfigModule) -> Result<Box<dyn Module>, FastReleaseError> {
+    let options: NpmOptions = get_options(module)?;
+
+    Ok(Box::new(Self {
+      options,
+      package: None,
+      npmrc: None,
+    }))
+  }
+
+  fn read_package(&self, context: &Context) -> Result<Package, FastReleaseError> {
+    let path: PathBuf = match &self.options.pkg_root {
+      Some(pkg_root) => context.project_path().join(pkg_root),
+      None => context.project_path(),
+    };
+    let file: PathBuf = path.join(PACKAGE_FILE);
+
+    let content: String = fs::read_to_string(&file).map_err(|v: Error| {
+      FastReleaseErrorBuilder::new(&format!(
+        "The project '{}' doesn't have a package at '{}'.",
+        context.project.name,
+        file.display()
+      ))
+      .category("NPM")
+      .error(v)
+      .get()
+    })?;
+    let package: Value = serde_json::from_str(&content).map_err(|v: serde_json::Error| {
+      FastReleaseErrorBuilder::new(&format!("Failed to parse the file '{}'.", file.display()))
+        .category("NPM")
+        .error(Error::other(v))
+        .get()
+    })?;
+
+    let name: String = package
+      .get("name")
+      .and_then(|v: &Value| v.as_str())
+      .ok_or_else(|| {
+        error(&format!(
+          "The package '{}' doesn't have a name.",
+          file.display()
+        ))
+      })?
+      .to_string();
+
+    let registry: String = self
+      .options
+      .registry
+      .clone()
+      .or_else(|| {
+        package
+          .pointer("/publishConfig/registry")
+          .and_then(|v: &Value| v.as_str())
+          .map(|v: &str| v.to_string())
+      })
+      .or_else(|| http::get_env(&["NPM_CONFIG_REGISTRY", "npm_config_registry"]))
+      .unwrap_or_else(|| DEFAULT_REGISTRY.to_string());
+
+    Ok(Package {
+      name,
+      private: package
+        .get("private")
+        .and_then(|v: &Value| v.as_bool())
+        .unwrap_or(false),
+      path,
+      registry,
+    })
+  }
+
+  fn get_package(&self) -> Result<&Package, FastReleaseError> {
+    self.package.as_ref().ok_or_else(|| {
+      error(&format!(
+        "The module '{}' didn't verify its conditions.",
+        NAME
+      ))
+    })
+  }
+
+  // The token stays on the environment, as npm expands the variable when it reads the file
+  fn write_npmrc(&self, context: &Context, package: &Package) -> Result<PathBuf, FastReleaseError> {
+    let registry_key: String = get_registry_key(&package.registry).ok_or_else(|| {
+      error(&format!(
+        "The registry '{}' is not a valid URL.",
+        package.registry
+      ))
+    })?;
+
+    // Every project has its own module, which removes the file when it's dropped
+    let project: String = context
+      .project
+      .name
+      .replace(|v: char| !v.is_ascii_alphanumeric(), "-");
+    let path: PathBuf =
+      env::temp_dir().join(format!("fast-release-{}-{}.npmrc", process::id(), project));
+    let content: String = format!(
+      "registry={}\n{}:_authToken=${{{}}}\n",
+      package.registry, registry_key, TOKEN_VARIABLE
+    );
+
+    fs::write(&path, content).map_err(|v: Error| {
+      FastReleaseErrorBuilder::new(&format!("Failed to write the file '{}'.", path.display()))
+        .category("NPM")
+        .error(v)
+        .get()
+    })?;
+
+    Ok(path)
+  }
+
+  fn run(&self, package: &Package, args: &[&str]) -> Result<String, FastReleaseError> {
+    debug!(
+      message = format!("Running 'npm {}'.", args.join(" ")),
+      category = "NPM"
+    );
+
+    let mut command: Command = Command::new("npm");
+    command
+      .args(args)
+      .arg("--registry")
+      .arg(&package.registry)
+      .current_dir(&package.path);
+    if let Some(npmrc) = &self.npmrc {
+      command.env("NPM_CONFIG_USERCONFIG", npmrc);
+    }
+
+    let output: Output = command.output().map_err(|v: Error| {
+      FastReleaseErrorBuilder::new(&format!("Failed to run 'npm {}'.", args[0]))
+        .category("NPM")
+        .error(v)
+        .get()
+    })?;
+
+    if !output.status.success() {
+      return Err(
+        FastReleaseErrorBuilder::new(&format!("The command 'npm {}' failed.", args.join(" ")))
+          .category("NPM")
+          .error(Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+          ))
+          .get(),
+      );
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+  }
+
+  fn get_release_info(&self, package: &Package, next_release: &NextRelease) -> ReleaseInfo {
+    let url: Option<String> = match package.registry.as_str() {
+      DEFAULT_REGISTRY => Some(format!(
+        "https://www.npmjs.com/package/{}/v/{}",
+        package.name, next_release.version
+      )),
+      _ => None,
+    };
+
+    ReleaseInfo {
+      module: NAME.to_string(),
+      name: format!("npm package (@{} dist-tag)", get_dist_tag(next_release)),
+      url,
+      channel: next_release.channel.clone(),
+    }
+  }
+}
+
+impl Drop for NpmModule {
+  fn drop(&mut self) {
+    if let Some(npmrc) = &self.npmrc {
+      let _ = fs::remove_file(npmrc);
+    }
+  }
+}
+
+impl Module for NpmModule {
+  fn name(&self) -> &str {
+    NAME
+  }
+
+  fn verify_conditions(&mut self, context: &Context) -> Result<(), FastReleaseError> {
+    let package: Package = self.read_package(context)?;
+
+    // Private packages only get the new version
+    if !package.private {
+      if http::get_env(&[TOKEN_VARIABLE]).is_none() {
+        return Err(error(&format!(
+          "The module '{}' needs a registry token on '{}'.",
+          NAME, TOKEN_VARIABLE
+        )));
+      }
+
+      self.npmrc = Some(self.write_npmrc(context, &package)?);
+      let user: String = self.run(&package, &["whoami"]).map_err(|_| {
+        error(&format!(
+          "The token on '{}' is not valid for the registry '{}'.",
+          TOKEN_VARIABLE, package.registry
+        ))
+      })?;
+
+      debug!(
+        message = format!(
+          "Publishing the package '{}' to '{}' as '{}'.",
+          package.name, package.registry, user
+        ),
+        category = "NPM"
+      );
+    }
+
+    self.package = Some(package);
+
+    Ok(())
+  }
+
+  fn prepare(&mut self, context: &Context) -> Result<(), FastReleaseError> {
+    let next_release: &NextRelease = context.get_next_release(NAME)?;
+    let package: &Package = self.get_package()?;
+    let version: String = next_release.version.to_string();
+
+    self.run(
+      package,
+      &[
+        "version",
+        &version,
+        "--no-git-tag-version",
+        "--allow-same-version",
+      ],
+    )?;
+    info!(
+      "Updated the package '{}' to the version '{}'.",
+      package.name, version
+    );
+
+    Ok(())
+  }
+
+  fn publish(&mut self, context: &Context) -> Result<Option<ReleaseInfo>, FastReleaseError> {
+    let next_release: &NextRelease = context.get_next_release(NAME)?;
+    let package: &Package = self.get_package()?;
+
+    if package.private {
+      info!(
+        "The package '{}' is private, so it isn't published.",
+        package.name
+      );
+      return Ok(None);
+    }
+
+    let dist_tag: &str = get_dist_tag(next_release);
+    let mut args: Vec<&str> = vec!["publish", ".", "--tag", dist_tag];
+    if let Some(access) = &self.options.access {
+      args.extend(["--access", access]);
+    }
+    self.run(package, &args)?;
+
+    info!(
+      "Published the version '{}' of the package '{}' to '{}' on the dist-tag '{}'.",
+      next_release.version, package.name, package.registry, dist_tag
+    );
+
+    Ok(Some(self.get_release_info(package, next_release)))
+  }
+
+  fn add_channel(&mut self, context: &Context) -> Result<Option<ReleaseInfo>, FastReleaseError> {
+    let next_release: &NextRelease = context.get_next_release(NAME)?;
+    let package: &Package = self.get_package()?;
+
+    if package.private {
+      return Ok(None);
+    }
+
+    let dist_tag: &str = get_dist_tag(next_release);
+    let spec: String = format!("{}@{}", package.name, next_release.version);
+    self.run(package, &["dist-tag", "add", &spec, dist_tag])?;
+
+    info!(
+      "Added the version '{}' of the package '{}' to the dist-tag '{}'.",
+      next_release.version, package.name, dist_tag
+    );
+
+    Ok(Some(self.get_release_info(package, next_release)))
+  }
+
+  // Registries don't take a version back for good, as an unpublished version can't be published
+  // again, so a published version is left to clean up by hand
+  fn rollback(&mut self, context: &Context) -> Result<Option<String>, FastReleaseError> {
+    let next_release: &NextRelease = context.get_next_release(NAME)?;
+    let package: &Package = self.get_package()?;
+
+    if package.private {
+      return Ok(None);
+    }
+
+    // npm fails with 'E404' when the package never got to the registry
+    let spec: String = format!("{}@{}", package.name, next_release.version);
+    match self.run(package, &["view", &spec, "version"]) {
+      Ok(version) if version.is_empty() => return Ok(None),
+      Ok(_) => {}
+      Err(error) if is_not_found(&error) => return Ok(None),
+      Err(error) => return Err(error),
+    }
+
+    Err(error(&format!(
+      "The version '{}' of the package '{}' stays on '{}'. Deprecate it with 'npm deprecate' if it must not be used.",
+      next_release.version, package.name, package.registry
+    )))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    config::Config,
+    testing::{self, StubRequest, StubResponse, StubServer},
+  };
+  use std::path::Path;
+  use tempfile::TempDir;
+
+  const CONFIG: &str = "
+version: 1
+tag_format: v${version}
+branches: [main]
+projects:
+  - name: app
+    path: .
+";
+
+  // The tests that run npm are skipped where it isn't installed
+  fn has_npm() -> bool {
+    Command::new("npm").arg("--version").output().is_ok()
+  }
+
+  // A registry with the versions of the package 'app'
+  fn start_registry(versions: &'static [&'static str]) -> StubServer {
+    StubServer::start(move |request: &StubRequest| {
+      match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/-/whoami") => StubResponse::new(200, r#"{"username":"tester"}"#),
+        ("GET", "/app") if !versions.is_empty() => StubResponse::new(
+          200,
+          &serde_json::json!({
+            "name": "app",
+            "dist-tags": { "latest": versions[versions.len() - 1] },
+            "versions": versions
+              .iter()
+              .map(|v: &&str| (v.to_string(), serde_json::json!({ "name": "app", "version": v })))
+              .collect::<serde_json::Map<String, Value>>(),
+          })
+          .to_string(),
+        ),
+        ("GET", "/-/package/app/dist-tags") => StubResponse::new(200, r#"{"latest":"0.9.0"}"#),
+        ("PUT", _) => StubResponse::new(200, r#"{"ok":true}"#),
+        _ => StubResponse::new(404, r#"{"error":"Not found"}"#),
+      }
+    })
+  }
+
+  fn get_context(cwd: &Path, channel: Option<&str>) -> Context {
+    let config: Config = testing::get_config(CONFIG);
+    let mut context: Context = testing::get_context(&config, cwd);
+    let mut next_release: NextRelease = testing::get_next_release("v1.0.0", "1.0.0");
+    next_release.channel = channel.map(|v: &str| v.to_string());
+    context.next_release = Some(next_release);
+    context
+  }
+
+  // Runs the step after verifying the conditions, with the token of the registry
+  fn run_step<T>(
+    server: &StubServer,
+    cwd: &Path,
+    channel: Option<&str>,
+    step: impl FnOnce(&mut Box<dyn Module>, &Context) -> Result<T, FastReleaseError>,
+  ) -> Result<T, FastReleaseError> {
+    fs::write(
+      cwd.join(PACKAGE_FILE),
+      r#"{ "name": "app", "version": "1.0.0" }"#,
+    )
+    .unwrap();
+    let context: Context = get_context(cwd, channel);
+    let mut module: Box<dyn Module> = NpmModule::create(&testing::get_module(&format!(
+      "{{ name: npm, registry: '{}/' }}",
+      server.url
+    )))?;
+
+    testing::with_env(
+      &[
+        ("NPM_TOKEN", Some("secret")),
+        ("NPM_CONFIG_REGISTRY", None),
+        ("npm_config_registry", None),
+      ],
+      || {
+        module.verify_conditions(&context)?;
+        step(&mut module, &context)
+      },
+    )
+  }
+
+  #[test]
+  fn rolls_back_nothing_when_the_version_isnt_on_the_registry() {
+    if !has_npm() {
+      return;
+    }
+    let cwd: TempDir = TempDir::new().unwrap();
+
+    let server: StubServer = start_registry(&[]);
+    let result: Option<String> = run_step(
+      &server,
+      cwd.path(),
+      None,
+      |module: &mut Box<dyn Module>, context: &Context| module.rollback(context),
+    )
+    .unwrap();
+    assert_eq!(result, None);
+
+    let server: StubServer = start_registry(&["0.9.0"]);
+    let result: Option<String> = run_step(
+      &server,
+      cwd.path(),
+      None,
+      |module: &mut Box<dyn Module>, context: &Context| module.rollback(context),
+    )
+    .unwrap();
+    assert_eq!(result, None);
+  }
+
+  #[test]
+  fn leaves_a_published_version_to_clean_up_by_hand() {
+    if !has_npm() {
+      return;
+    }
+    let cwd: TempDir = TempDir::new().unwrap();
+    let server: StubServer = start_registry(&["0.9.0", "1.0.0"]);
+
+    let error: FastReleaseError = run_step(
+      &server,
+      cwd.path(),
+      None,
+      |module: &mut Box<dyn Module>, context: &Context| module.rollback(context),
+    )
+    .unwrap_err();
+
+    assert!(error
+      .message
+      .starts_with("The version '1.0.0' of the package 'app' stays on"));
+  }
+
+  #[test]
+  fn gets_the_dist_tag_of_the_channel() {
+    let mut next_release: NextRelease = testing::get_next_release("v1.0.0", "1.0.0");
+    assert_eq!(get_dist_tag(&next_release), "latest");
+
+    next_release.channel = Some("next".to_string());
+    assert_eq!(get_dist_tag(&next_release), "next");
+  }
+
+  #[test]
+  fn gets_the_registry_key() {
+    assert_eq!(
+      get_registry_key("https://registry.npmjs.org/").as_deref(),
+      Some("//registry.npmjs.org/")
+    );
+    assert_eq!(
+      get_registry_key("http://localhost:4873").as_deref(),
+      Some("//localhost:4873/")
+    );
+    assert_eq!(
+      get_registry_key("https://npm.example.com/api/npm/packages/").as_deref(),
+      Some("//npm.example.com/api/npm/packages/")
+    );
+    assert_eq!(get_registry_key("registry.npmjs.org"), None);
+  }
+
+  #[test]
+  fn publishes_on_the_dist_tag_of_the_channel() {
+    if !has_npm() {
+      return;
+    }
+    let cwd: TempDir = TempDir::new().unwrap();
+    let server: StubServer = start_registry(&[]);
+
+    let release: Option<ReleaseInfo> = run_step(
+      &server,
+      cwd.path(),
+      Some("next"),
+      |module: &mut Box<dyn Module>, context: &Context| module.publish(context),
+    )
+    .unwrap();
+
+    assert_eq!(
+      release.map(|v: ReleaseInfo| v.name),
+      Some("npm package (@next dist-tag)".to_string())
+    );
+    let requests: Vec<StubRequest> = server.requests();
+    let publish: &StubRequest = requests
+      .iter()
+      .find(|v: &&StubRequest| v.method == "PUT")
+      .unwrap();
+    assert_eq!(publish.path, "/app");
+    assert_eq!(publish.header("authorization"), Some("Bearer secret"));
+    assert_eq!(
+      publish.json()["dist-tags"],
+      serde_json::json!({ "next": "1.0.0" })
+    );
+  }
+
+  #[test]
+  fn adds_the_version_to_the_dist_tag_of_the_channel() {
+    if !has_npm() {
+      return;
+    }
+    let cwd: TempDir = TempDir::new().unwrap();
+    let server: StubServer = start_registry(&["1.0.0"]);
+
+    run_step(
+      &server,
+      cwd.path(),
+      Some("next"),
+      |module: &mut Box<dyn Module>, context: &Context| module.add_channel(context),
+    )
+    .unwrap();
+
+    let requests: Vec<StubRequest> = server.requests();
+    let add: &StubRequest = requests
+      .iter()
+      .find(|v: &&StubRequest| v.method == "PUT")
+      .unwrap();
+    assert_eq!(add.path, "/-/package/app/dist-tags/next");
+    assert_eq!(add.json(), serde_json::json!("1.0.0"));
+  }
+}
@@ -0,0 +1,479 @@
+// Copyright (c) Toolbi Software. All rights reserved.
+// Check the README file in the project root for more information.
+
+// External plugins are executables called once per step as '<plugin> <step>'. They get the
+// context as JSON on the standard input and answer with one JSON message per line on the
+// standard output:
+//   {"type": "log", "level": "info", "message": "..."}
+//   {"type": "result", "value": ...}
+//   {"type": "error", "message": "..."}
+// Lines that aren't JSON messages are logged as they are. The result of the 'rollback' step tells
+// what the plugin took back, where no result means there was nothing to take back.
+
+use super::{Context, Module, NextRelease, ReleaseInfo, Rollback, Step};
+use crate::{
+  analyzer::{commit::Commit, rules::ReleaseType},
+  branches::{Branch, BranchRange, BranchType},
+  config::{ConfigModule, ConfigProject},
+  error::{FastReleaseError, FastReleaseErrorBuilder},
+  release::LastRelease,
+};
+use env_ci::CiEnv;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serde_yaml::Mapping;
+use std::{
+  io::{Error, Write},
+  path::{PathBuf, MAIN_SEPARATOR},
+  process::{Child, ChildStdin, Command, Output, Stdio},
+  thread::{self, JoinHandle},
+};
+use tracing::{debug, error, info, warn};
+
+#[derive(Debug, Serialize)]
+struct PluginBranch<'a> {
+  name: &'a str,
+  #[serde(rename = "type")]
+  branch_type: &'a str,
+  prerelease: Option<&'a str>,
+  channel: Option<&'a str>,
+  range: Option<String>,
+}
+
+impl<'a> PluginBranch<'a> {
+  fn new(branch: &'a Branch) -> Self {
+    Self {
+      name: &branch.name,
+      branch_type: match branch.branch_type {
+        BranchType::Release => "release",
+        BranchType::Maintenance => "maintenance",
+        BranchType::PreRelease => "prerelease",
+      },
+      prerelease: branch.pre_release.as_deref(),
+      channel: branch.channel.as_deref(),
+      range: branch.range.as_ref().map(|v: &BranchRange| v.to_string()),
+    }
+  }
+}
+
+#[derive(Debug, Serialize)]
+struct PluginConfig<'a> {
+  dry_run: bool,
+  options: &'a Mapping,
+}
+
+#[derive(Debug, Serialize)]
+struct PluginInput<'a> {
+  step: String,
+  cwd: String,
+  config: PluginConfig<'a>,
+  ci_env: &'a CiEnv,
+  branch: PluginBranch<'a>,
+  project: &'a ConfigProject,
+  commits: &'a [Commit],
+  last_release: Option<&'a LastRelease>,
+  next_release: Option<&'a NextRelease>,
+  releases: &'a [ReleaseInfo],
+  errors: Vec<String>,
+  rollback: Option<&'a Rollback>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum PluginMessage {
+  Log {
+    level: Option<String>,
+    message: String,
+  },
+  Result {
+    value: Value,
+  },
+  Error {
+    message: String,
+  },
+}
+
+#[derive(Debug, Deserialize)]
+struct PluginRelease {
+  name: String,
+  url: Option<String>,
+  channel: Option<String>,
+}
+
+pub struct PluginModule {
+  name: String,
+  path: String,
+  options: Mapping,
+}
+
+impl PluginModule {
+  pub fn create(module: &ConfigModule) -> Result<Box<dyn Module>, FastReleaseError> {
+    let path: String = module.plugin.clone().unwrap_or_default();
+    if path.trim().is_empty() {
+      return Err(
+        FastReleaseErrorBuilder::new(&format!(
+          "The plugin path of the module '{}' is empty.",
+          module.name
+        ))
+        .category("CONFIG")
+        .get(),
+      );
+    }
+
+    Ok(Box::new(Self {
+      name: module.name.clone(),
+      path,
+      options: module.options.clone(),
+    }))
+  }
+
+  fn error(&self, message: &str, error: Option<Error>) -> FastReleaseError {
+    let builder: FastReleaseErrorBuilder =
+      FastReleaseErrorBuilder::new(message).category(&self.name);
+    match error {
+      Some(error) => builder.error(error).get(),
+      None => builder.get(),
+    }
+  }
+
+  fn log(&self, level: Option<&str>, message: &str) {
+    match level.unwrap_or("info") {
+      "error" => error!(message = message, category = self.name),
+      "warn" => warn!(message = message, category = self.name),
+      "debug" | "trace" => debug!(message = message, category = self.name),
+      _ => info!(message = message, category = self.name),
+    }
+  }
+
+  // Paths are relative to the repository, while names like 'my-plugin' are looked up on the PATH
+  fn get_path(&self, context: &Context) -> PathBuf {
+    match self.path.contains(['/', MAIN_SEPARATOR]) {
+      true => context.cwd.join(&self.path),
+      false => PathBuf::from(&self.path),
+    }
+  }
+
+  // Runs the plugin for a step, returning the value of its result message
+  fn run(
+    &self,
+    context: &Context,
+    step: Step,
+    errors: &[FastReleaseError],
+  ) -> Result<Option<Value>, FastReleaseError> {
+    let input: String = match serde_json::to_string(&PluginInput {
+      step: step.to_string(),
+      cwd: context.cwd.to_string_lossy().to_string(),
+      config: PluginConfig {
+        dry_run: context.dry_run,
+        options: &self.options,
+      },
+      ci_env: &context.ci_env,
+      branch: PluginBranch::new(&context.branch),
+      project: &context.project,
+      commits: &context.commits,
+      last_release: context.last_release.as_ref(),
+      next_release: context.next_release.as_ref(),
+      releases: &context.releases,
+      errors: errors
+        .iter()
+        .map(|v: &FastReleaseError| v.to_string())
+        .collect(),
+      rollback: context.rollback.as_ref(),
+    }) {
+      Ok(input) => input,
+      Err(error) => {
+        return Err(self.error(
+          "Failed to serialize the context for the plugin.",
+          Some(Error::other(error)),
+        ))
+      }
+    };
+
+    let path: PathBuf = self.get_path(context);
+    debug!(
+      message = format!(
+        "Running the plugin '{}' on the step '{}'.",
+        path.display(),
+        step
+      ),
+      category = self.name
+    );
+
+    let mut child: Child = match Command::new(&path)
+      .arg(step.to_string())
+      .current_dir(context.project_path())
+      .stdin(Stdio::piped())
+      .stdout(Stdio::piped())
+      .stderr(Stdio::piped())
+      .spawn()
+    {
+      Ok(child) => child,
+      Err(error) => {
+        return Err(self.error(
+          &format!("Failed to run the plugin '{}'.", path.display()),
+          Some(error),
+        ))
+      }
+    };
+
+    // The input is written while the output is read, so a plugin that writes before reading
+    // doesn't block on a full pipe. Plugins that don't read the input close the pipe early, which
+    // isn't an error.
+    let writer: Option<JoinHandle<()>> = child.stdin.take().map(|mut stdin: ChildStdin| {
+      thread::spawn(move || {
+        let _ = stdin.write_all(input.as_bytes());
+      })
+    });
+
+    let output: Output = match child.wait_with_output() {
+      Ok(output) => output,
+      Err(error) => {
+        return Err(self.error(
+          &format!("Failed to run the plugin '{}'.", path.display()),
+          Some(error),
+        ))
+      }
+    };
+    if let Some(writer) = writer {
+      let _ = writer.join();
+    }
+
+    let mut result: Option<Value> = None;
+    let mut plugin_error: Option<String> = None;
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+      if line.trim().is_empty() {
+        continue;
+      }
+
+      match serde_json::from_str::<PluginMessage>(line) {
+        Ok(PluginMessage::Log { level, message }) => self.log(level.as_deref(), &message),
+        Ok(PluginMessage::Result { value }) => result = Some(value),
+        Ok(PluginMessage::Error { message }) => plugin_error = Some(message),
+        Err(_) => self.log(None, line),
+      }
+    }
+
+    if let Some(message) = plugin_error {
+      return Err(self.error(&message, None));
+    }
+
+    if !output.status.success() {
+      let stderr: String = String::from_utf8_lossy(&output.stderr).trim().to_string();
+      return Err(self.error(
+        &format!(
+          "The plugin failed on the step '{}' with the status '{}'.",
+          step, output.status
+        ),
+        (!stderr.is_empty()).then(|| Error::other(stderr)),
+      ));
+    }
+
+    Ok(result.filter(|v: &Value| !v.is_null()))
+  }
+
+  fn parse<T: serde::de::DeserializeOwned>(
+    &self,
+    step: Step,
+    value: Option<Value>,
+  ) -> Result<Option<T>, FastReleaseError> {
+    match value {
+      Some(value) => match serde_json::from_value::<T>(value) {
+        Ok(value) => Ok(Some(value)),
+        Err(error) => Err(self.error(
+          &format!(
+            "The plugin returned an invalid result on the step '{}'.",
+            step
+          ),
+          Some(Error::other(error)),
+        )),
+      },
+      None => Ok(None),
+    }
+  }
+
+  fn release(
+    &self,
+    step: Step,
+    value: Option<Value>,
+  ) -> Result<Option<ReleaseInfo>, FastReleaseError> {
+    Ok(
+      self
+        .parse::<PluginRelease>(step, value)?
+        .map(|v: PluginRelease| ReleaseInfo {
+          module: self.name.clone(),
+          name: v.name,
+          url: v.url,
+          channel: v.channel,
+        }),
+    )
+  }
+}
+
+impl Module for PluginModule {
+  fn name(&self) -> &str {
+    &self.name
+  }
+
+  fn verify_conditions(&mut self, context: &Context) -> Result<(), FastReleaseError> {
+    self.run(context, Step::VerifyConditions, &[]).map(|_| ())
+  }
+
+  fn analyze_commits(
+    &mut self,
+    context: &Context,
+  ) -> Result<Option<ReleaseType>, FastReleaseError> {
+    let value: Option<Value> = self.run(context, Step::AnalyzeCommits, &[])?;
+    self.parse(Step::AnalyzeCommits, value)
+  }
+
+  fn verify_release(&mut self, context: &Context) -> Result<(), FastReleaseError> {
+    self.run(context, Step::VerifyRelease, &[]).map(|_| ())
+  }
+
+  fn generate_notes(&mut self, context: &Context) -> Result<Option<String>, FastReleaseError> {
+    let value: Option<Value> = self.run(context, Step::GenerateNotes, &[])?;
+    self.parse(Step::GenerateNotes, value)
+  }
+
+  fn prepare(&mut self, context: &Context) -> Result<(), FastReleaseError> {
+    self.run(context, Step::Prepare, &[]).map(|_| ())
+  }
+
+  fn publish(&mut self, context: &Context) -> Result<Option<ReleaseInfo>, FastReleaseError> {
+    let value: Option<Value> = self.run(context, Step::Publish, &[])?;
+    self.release(Step::Publish, value)
+  }
+
+  fn add_channel(&mut self, context: &Context) -> Result<Option<ReleaseInfo>, FastReleaseError> {
+    let value: Option<Value> = self.run(context, Step::AddChannel, &[])?;
+    self.release(Step::AddChannel, value)
+  }
+
+  fn success(&mut self, context: &Context) -> Result<(), FastReleaseError> {
+    self.run(context, Step::Success, &[]).map(|_| ())
+  }
+
+  fn fail(
+    &mut self,
+    context: &Context,
+    errors: &[FastReleaseError],
+  ) -> Result<(), FastReleaseError> {
+    self.run(context, Step::Fail, errors).map(|_| ())
+  }
+
+  fn rollback(&mut self, context: &Context) -> Result<Option<String>, FastReleaseError> {
+    let value: Option<Value> = self.run(context, Step::Rollback, &[])?;
+    self.parse(Step::Rollback, value)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{config::Config, testing};
+  use std::{fs, os::unix::fs::PermissionsExt, path::Path};
+  use tempfile::TempDir;
+
+  const CONFIG: &str = "
+version: 1
+tag_format: v${version}
+branches: [main]
+projects:
+  - name: app
+    path: .
+";
+
+  fn write_plugin(path: &Path, script: &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755)).unwrap();
+  }
+
+  fn generate_notes(
+    cwd: &Path,
+    plugin: &str,
+    commits: usize,
+  ) -> Result<Option<String>, FastReleaseError> {
+    let config: Config = testing::get_config(CONFIG);
+    let mut context: Context = testing::get_context(&config, cwd);
+    context.next_release = Some(testing::get_next_release("v1.0.0", "1.0.0"));
+    context.commits = (0..commits)
+      .map(|v: usize| {
+        Commit::new(
+          &format!("{:040}", v),
+          &"feat: add a feature".repeat(10),
+          "Tester",
+          "tester@example.com",
+          0,
+        )
+      })
+      .collect();
+
+    let mut module: Box<dyn Module> = PluginModule::create(&testing::get_module(&format!(
+      "{{ name: notes, plugin: '{}' }}",
+      plugin
+    )))?;
+    module.generate_notes(&context)
+  }
+
+  // Both pipes are fuller than their buffers, so writing the whole input first would block
+  #[test]
+  fn writes_the_input_while_reading_the_output() {
+    let cwd: TempDir = TempDir::new().unwrap();
+    write_plugin(
+      &cwd.path().join("plugins/notes"),
+      r#"head -c 200000 /dev/zero | tr '\0' ' '
+echo
+echo "{\"type\": \"result\", \"value\": \"$(wc -c | tr -d ' ')\"}""#,
+    );
+
+    let notes: String = generate_notes(cwd.path(), "plugins/notes", 1000)
+      .unwrap()
+      .unwrap();
+
+    assert!(notes.parse::<usize>().unwrap() > 200000);
+  }
+
+  #[test]
+  fn finds_the_plugins_on_the_path() {
+    let cwd: TempDir = TempDir::new().unwrap();
+    let bin: TempDir = TempDir::new().unwrap();
+    write_plugin(
+      &bin.path().join("notes-plugin"),
+      r#"echo "{\"type\": \"result\", \"value\": \"From the PATH on $1\"}""#,
+    );
+    let path: String = format!(
+      "{}:{}",
+      bin.path().display(),
+      std::env::var("PATH").unwrap_or_default()
+    );
+
+    let notes: Option<String> = testing::with_env(&[("PATH", Some(&path))], || {
+      generate_notes(cwd.path(), "notes-plugin", 0)
+    })
+    .unwrap();
+
+    assert_eq!(notes.as_deref(), Some("From the PATH on generate_notes"));
+  }
+
+  #[test]
+  fn reports_the_errors_of_the_plugins() {
+    let cwd: TempDir = TempDir::new().unwrap();
+    write_plugin(
+      &cwd.path().join("plugins/notes"),
+      r#"echo '{"type": "error", "message": "The notes failed."}'"#,
+    );
+    write_plugin(
+      &cwd.path().join("plugins/crash"),
+      "echo 'Out of memory' >&2\nexit 3",
+    );
+
+    let error: FastReleaseError = generate_notes(cwd.path(), "./plugins/notes", 0).unwrap_err();
+    assert_eq!(error.message, "The notes failed.");
+    assert_eq!(error.category.as_deref(), Some("notes"));
+
+    let error: FastReleaseError = generate_notes(cwd.path(), "plugins/crash", 0).unwrap_err();
+    assert!(error.to_string().contains("Out of memory"));
+    assert!(generate_notes(cwd.path(), "missing-plugin", 0).is_err());
+  }
+}
@@ -0,0 +1,718 @@
+// Copyright (c) Toolbi Software. All rights reserved.
+// Check the README file in the project root for more information.
+
+use super::{cargo::set_string, get_options, Context, Module, NextRelease};
+use crate::{
+  config::ConfigModule,
+  error::{FastReleaseError, FastReleaseErrorBuilder},
+};
+use regex::{Captures, Match, Regex};
+use serde::{Deserialize, Serialize};
+use serde_json::ser::PrettyFormatter;
+use std::{
+  fs,
+  io::Error,
+  path::{Path, PathBuf},
+};
+use toml_edit::{DocumentMut, Item};
+use tracing::{debug, info};
+
+pub const NAME: &str = "version-files";
+
+const PACKAGE_JSON: &str = "package.json";
+const PACKAGE_LOCKFILES: [&str; 2] = ["package-lock.json", "npm-shrinkwrap.json"];
+const PYPROJECT: &str = "pyproject.toml";
+const CHART: &str = "Chart.yaml";
+const CHART_REGEX: &str = r#"(?m)^version:[ \t]*["']?(?P<version>[^"'\s#]+)"#;
+// The files that only hold the version
+const TEXT_FILES: [&str; 4] = ["VERSION", "VERSION.txt", "version", "version.txt"];
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileOptions {
+  path: String,
+  regex: Option<String>,
+  json_path: Option<String>,
+  toml_path: Option<String>,
+}
+
+// A file can be a path, that uses the handler of its format, or a path with its own replacement
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum FileConfig {
+  Path(String),
+  File(FileOptions),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct VersionFilesOptions {
+  files: Vec<FileConfig>,
+  #[serde(default = "default_true")]
+  lockfile: bool,
+}
+
+fn default_true() -> bool {
+  true
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PathSegment {
+  Key(String),
+  Index(usize),
+}
+
+// The paths of JSON and TOML replacements are candidates, every one found is updated and at
+// least one has to be found
+#[derive(Debug, Clone)]
+enum Replacement {
+  Regex(Regex),
+  Json(Vec<Vec<PathSegment>>),
+  Toml(Vec<Vec<PathSegment>>),
+  // Replaces the whole content of the file
+  Text,
+}
+
+#[derive(Debug, Clone)]
+struct VersionFile {
+  path: String,
+  replacement: Replacement,
+}
+
+pub struct VersionFilesModule {
+  files: Vec<VersionFile>,
+  lockfile: bool,
+}
+
+fn error(message: &str, error: Option<Error>) -> FastReleaseError {
+  let builder: FastReleaseErrorBuilder =
+    FastReleaseErrorBuilder::new(message).category("VERSION_FILES");
+  match error {
+    Some(error) => builder.error(error).get(),
+    None => builder.get(),
+  }
+}
+
+fn config_error(message: &str, error: Option<Error>) -> FastReleaseError {
+  let builder: FastReleaseErrorBuilder = FastReleaseErrorBuilder::new(message).category("CONFIG");
+  match error {
+    Some(error) => builder.error(error).get(),
+    None => builder.get(),
+  }
+}
+
+// Parses paths like '$.version', 'tool.poetry.version' or '$.packages[""].version'
+fn parse_path(path: &str) -> Option<Vec<PathSegment>> {
+  let path: &str = path.strip_prefix('$').unwrap_or(path);
+  let chars: Vec<char> = path.chars().collect();
+  let mut result: Vec<PathSegment> = Vec::new();
+  let mut index: usize = 0;
+
+  while index < chars.len() {
+    match chars[index] {
+      '.' if index + 1 < chars.len() && chars[index + 1] != '.' => index += 1,
+      '[' => {
+        let end: usize = index + chars[index..].iter().position(|v: &char| *v == ']')?;
+        let inner: String = chars[(index + 1)..end].iter().collect();
+        let quoted: Option<&str> = inner
+          .strip_prefix('"')
+          .and_then(|v: &str| v.strip_suffix('"'))
+          .or_else(|| {
+            inner
+              .strip_prefix('\'')
+              .and_then(|v: &str| v.strip_suffix('\''))
+          });
+        result.push(match quoted {
+          Some(key) => PathSegment::Key(key.to_string()),
+          None => PathSegment::Index(inner.trim().parse::<usize>().ok()?),
+        });
+        index = end + 1;
+        continue;
+      }
+      _ if index == 0 => {}
+      _ => return None,
+    }
+
+    let end: usize = chars[index..]
+      .iter()
+      .position(|v: &char| *v == '.' || *v == '[')
+      .map(|v: usize| index + v)
+      .unwrap_or(chars.len());
+    if end == index {
+      return None;
+    }
+    result.push(PathSegment::Key(chars[index..end].iter().collect()));
+    index = end;
+  }
+
+  if result.is_empty() {
+    return None;
+  }
+
+  Some(result)
+}
+
+fn get_builtin_replacement(path: &str) -> Option<Replacement> {
+  let file_name: &str = Path::new(path)
+    .file_name()
+    .and_then(|v: &std::ffi::OsStr| v.to_str())
+    .unwrap_or(path);
+
+  let paths = |values: &[&str]| -> Vec<Vec<PathSegment>> {
+    values.iter().filter_map(|v: &&str| parse_path(v)).collect()
+  };
+
+  match file_name {
+    PACKAGE_JSON => Some(Replacement::Json(paths(&["$.version"]))),
+    v if PACKAGE_LOCKFILES.contains(&v) => Some(Replacement::Json(paths(&[
+      "$.version",
+      "$.packages[\"\"].version",
+    ]))),
+    PYPROJECT => Some(Replacement::Toml(paths(&[
+      "project.version",
+      "tool.poetry.version",
+    ]))),
+    CHART => Some(Replacement::Regex(Regex::new(CHART_REGEX).unwrap())),
+    v if TEXT_FILES.contains(&v) => Some(Replacement::Text),
+    _ => None,
+  }
+}
+
+fn get_replacement(options: &FileOptions) -> Result<Replacement, FastReleaseError> {
+  let invalid = |kind: &str| -> FastReleaseError {
+    config_error(
+      &format!(
+        "The {} of the file '{}' of the module '{}' is not valid.",
+        kind, options.path, NAME
+      ),
+      None,
+    )
+  };
+
+  match (&options.regex, &options.json_path, &options.toml_path) {
+    (None, None, None) => get_builtin_replacement(&options.path).ok_or_else(|| {
+      config_error(
+        &format!(
+          "The module '{}' doesn't know the format of the file '{}'. Set its 'regex', 'json_path' or 'toml_path'.",
+          NAME, options.path
+        ),
+        None,
+      )
+    }),
+    (Some(regex), None, None) => Regex::new(regex)
+      .map(Replacement::Regex)
+      .map_err(|_| invalid("regex")),
+    (None, Some(path), None) => parse_path(path)
+      .map(|v: Vec<PathSegment>| Replacement::Json(vec![v]))
+      .ok_or_else(|| invalid("JSON path")),
+    (None, None, Some(path)) => parse_path(path)
+      .map(|v: Vec<PathSegment>| Replacement::Toml(vec![v]))
+      .ok_or_else(|| invalid("TOML path")),
+    _ => Err(config_error(
+      &format!(
+        "The file '{}' of the module '{}' can only have one of 'regex', 'json_path' or 'toml_path'.",
+        options.path, NAME
+      ),
+      None,
+    )),
+  }
+}
+
+// Replaces the 'version' group, the first group or the whole match
+fn replace_regex(content: &str, regex: &Regex, version: &str) -> Option<String> {
+  if !regex.is_match(content) {
+    return None;
+  }
+
+  let result: String = regex
+    .replace_all(content, |captures: &Captures| {
+      let full: Match = captures.get(0).unwrap();
+      let target: Match = captures
+        .name("version")
+        .or_else(|| captures.get(1))
+        .unwrap_or(full);
+      format!(
+        "{}{}{}",
+        &full.as_str()[..(target.start() - full.start())],
+        version,
+        &full.as_str()[(target.end() - full.start())..]
+      )
+    })
+    .to_string();
+
+  Some(result)
+}
+
+fn set_json(value: &mut serde_json::Value, path: &[PathSegment], version: &str) -> bool {
+  let mut current: &mut serde_json::Value = value;
+  for segment in path {
+    let next: Option<&mut serde_json::Value> = match segment {
+      PathSegment::Key(key) => current.get_mut(key.as_str()),
+      PathSegment::Index(index) => current.get_mut(*index),
+    };
+    current = match next {
+      Some(next) => next,
+      None => return false,
+    };
+  }
+
+  if !current.is_string() {
+    return false;
+  }
+  *current = serde_json::Value::String(version.to_string());
+
+  true
+}
+
+// Keeps the indentation of the file, which is two spaces on the files written by npm
+fn replace_json(
+  content: &str,
+  paths: &[Vec<PathSegment>],
+  version: &str,
+) -> Result<Option<String>, Error> {
+  let mut value: serde_json::Value = serde_json::from_str(content).map_err(Error::other)?;
+
+  let mut found: bool = false;
+  for path in paths {
+    found |= set_json(&mut value, path, version);
+  }
+  if !found {
+    return Ok(None);
+  }
+
+  let indent: String = content
+    .lines()
+    .nth(1)
+    .map(|v: &str| v.chars().take_while(|c: &char| c.is_whitespace()).collect())
+    .filter(|v: &String| !v.is_empty())
+    .unwrap_or_else(|| "  ".into());
+
+  let mut buffer: Vec<u8> = Vec::new();
+  let mut serializer: serde_json::Serializer<&mut Vec<u8>, PrettyFormatter> =
+    serde_json::Serializer::with_formatter(
+      &mut buffer,
+      PrettyFormatter::with_indent(indent.as_bytes()),
+    );
+  value.serialize(&mut serializer).map_err(Error::other)?;
+
+  let mut result: String = String::from_utf8(buffer).map_err(Error::other)?;
+  if content.ends_with('\n') {
+    result.push('\n');
+  }
+
+  Ok(Some(result))
+}
+
+fn get_toml<'a>(item: &'a Item, segment: &PathSegment) -> Option<&'a Item> {
+  match segment {
+    PathSegment::Key(key) => item.get(key.as_str()),
+    PathSegment::Index(index) => item.get(*index),
+  }
+}
+
+// The lookup goes first because indexing a missing key of a table inserts it
+fn set_toml(document: &mut DocumentMut, path: &[PathSegment], version: &str) -> bool {
+  let found: bool = path
+    .iter()
+    .try_fold(document.as_item(), get_toml)
+    .is_some_and(|v: &Item| v.is_str());
+  if !found {
+    return false;
+  }
+
+  let mut current: &mut Item = document.as_item_mut();
+  for segment in path {
+    let next: Option<&mut Item> = match segment {
+      PathSegment::Key(key) => current.get_mut(key.as_str()),
+      PathSegment::Index(index) => current.get_mut(*index),
+    };
+    current = match next {
+      Some(next) => next,
+      None => return false,
+    };
+  }
+
+  set_string(current, version);
+
+  true
+}
+
+fn replace_toml(
+  content: &str,
+  paths: &[Vec<PathSegment>],
+  version: &str,
+) -> Result<Option<String>, Error> {
+  let mut document: DocumentMut = content.parse::<DocumentMut>().map_err(Error::other)?;
+
+  let mut found: bool = false;
+  for path in paths {
+    found |= set_toml(&mut document, path, version);
+  }
+
+  Ok(found.then(|| document.to_string()))
+}
+
+fn replace_text(content: &str, version: &str) -> String {
+  let new_line: &str = match content.ends_with('\n') || content.is_empty() {
+    true => "\n",
+    false => "",
+  };
+
+  format!("{}{}", version, new_line)
+}
+
+impl VersionFilesModule {
+  pub fn create(module: &ConfigModule) -> Result<Box<dyn Module>, FastReleaseError> {
+    let options: VersionFilesOptions = get_options(module)?;
+
+    let mut files: Vec<VersionFile> = Vec::new();
+    for file in &options.files {
+      files.push(match file {
+        FileConfig::Path(path) => VersionFile {
+          path: path.clone(),
+          replacement: get_replacement(&FileOptions {
+            path: path.clone(),
+            regex: None,
+            json_path: None,
+            toml_path: None,
+          })?,
+        },
+        FileConfig::File(file) => VersionFile {
+          path: file.path.clone(),
+          replacement: get_replacement(file)?,
+        },
+      });
+    }
+
+    Ok(Box::new(Self {
+      files,
+      lockfile: options.lockfile,
+    }))
+  }
+
+  // The lockfiles of npm are updated along with their 'package.json'
+  fn get_files(&self, project_path: &Path) -> Vec<(PathBuf, Replacement)> {
+    let mut result: Vec<(PathBuf, Replacement)> = Vec::new();
+
+    for file in &self.files {
+      let path: PathBuf = project_path.join(&file.path);
+      result.push((path.clone(), file.replacement.clone()));
+
+      if !self.lockfile
+        || path.file_name().and_then(|v: &std::ffi::OsStr| v.to_str()) != Some(PACKAGE_JSON)
+      {
+        continue;
+      }
+      for lockfile in PACKAGE_LOCKFILES {
+        let lockfile_path: PathBuf = path.with_file_name(lockfile);
+        let configured: bool = result
+          .iter()
+          .any(|(v, _): &(PathBuf, Replacement)| *v == lockfile_path);
+        if lockfile_path.is_file() && !configured {
+          result.extend(
+            get_builtin_replacement(lockfile).map(|v: Replacement| (lockfile_path.clone(), v)),
+          );
+        }
+      }
+    }
+
+    result
+  }
+
+  fn update_file(
+    &self,
+    path: &Path,
+    replacement: &Replacement,
+    version: &str,
+  ) -> Result<(), FastReleaseError> {
+    let content: String = fs::read_to_string(path).map_err(|v: Error| {
+      error(
+        &format!("Failed to read the file '{}'.", path.display()),
+        Some(v),
+      )
+    })?;
+
+    let result: Option<String> = match replacement {
+      Replacement::Regex(regex) => replace_regex(&content, regex, version),
+      Replacement::Json(paths) => replace_json(&content, paths, version).map_err(|v: Error| {
+        error(
+          &format!("Failed to parse the JSON file '{}'.", path.display()),
+          Some(v),
+        )
+      })?,
+      Replacement::Toml(paths) => replace_toml(&content, paths, version).map_err(|v: Error| {
+        error(
+          &format!("Failed to parse the TOML file '{}'.", path.display()),
+          Some(v),
+        )
+      })?,
+      Replacement::Text => Some(replace_text(&content, version)),
+    };
+
+    let result: String = match result {
+      Some(result) => result,
+      None => {
+        return Err(error(
+          &format!(
+            "The version to replace wasn't found on the file '{}'.",
+            path.display()
+          ),
+          None,
+        ))
+      }
+    };
+
+    fs::write(path, result).map_err(|v: Error| {
+      error(
+        &format!("Failed to write the file '{}'.", path.display()),
+        Some(v),
+      )
+    })?;
+
+    debug!(
+      message = format!(
+        "Updated the version on '{}' to '{}'.",
+        path.display(),
+        version
+      ),
+      category = "VERSION_FILES"
+    );
+
+    Ok(())
+  }
+}
+
+impl Module for VersionFilesModule {
+  fn name(&self) -> &str {
+    NAME
+  }
+
+  fn verify_conditions(&mut self, context: &Context) -> Result<(), FastReleaseError> {
+    let project_path: PathBuf = context.project_path();
+
+    for file in &self.files {
+      let path: PathBuf = project_path.join(&file.path);
+      if !path.is_file() {
+        return Err(error(
+          &format!(
+            "The version file '{}' of the project '{}' doesn't exist.",
+            path.display(),
+            context.project.name
+          ),
+          None,
+        ));
+      }
+    }
+
+    Ok(())
+  }
+
+  fn prepare(&mut self, context: &Context) -> Result<(), FastReleaseError> {
+    let next_release: &NextRelease = context.get_next_release(NAME)?;
+    let version: String = next_release.version.to_string();
+
+    let files: Vec<(PathBuf, Replacement)> = self.get_files(&context.project_path());
+    for (path, replacement) in &files {
+      self.update_file(path, replacement, &version)?;
+    }
+
+    info!(
+      "Updated the version on {} files of the project '{}'.",
+      files.len(),
+      context.project.name
+    );
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{config::Config, testing};
+  use tempfile::TempDir;
+
+  fn key(value: &str) -> PathSegment {
+    PathSegment::Key(value.to_string())
+  }
+
+  fn set_toml_path(content: &str, path: &str) -> (bool, String) {
+    let mut document: DocumentMut = content.parse::<DocumentMut>().unwrap();
+    let found: bool = set_toml(&mut document, &parse_path(path).unwrap(), "1.1.0");
+
+    (found, document.to_string())
+  }
+
+  #[test]
+  fn parses_the_paths() {
+    assert_eq!(parse_path("$.version"), Some(vec![key("version")]));
+    assert_eq!(
+      parse_path("tool.poetry.version"),
+      Some(vec![key("tool"), key("poetry"), key("version")])
+    );
+    assert_eq!(
+      parse_path("$.packages[\"\"].version"),
+      Some(vec![key("packages"), key(""), key("version")])
+    );
+    assert_eq!(
+      parse_path("$['a.b'][ 2 ]"),
+      Some(vec![key("a.b"), PathSegment::Index(2)])
+    );
+
+    assert_eq!(parse_path("$"), None);
+    assert_eq!(parse_path("a..b"), None);
+    assert_eq!(parse_path("a["), None);
+    assert_eq!(parse_path("a[b]"), None);
+  }
+
+  #[test]
+  fn replaces_the_regex_matches() {
+    let replace = |content: &str, regex: &str| -> Option<String> {
+      replace_regex(content, &Regex::new(regex).unwrap(), "1.1.0")
+    };
+
+    assert_eq!(
+      replace("VERSION = '1.0.0'", r"VERSION = '(?P<version>[^']+)'").as_deref(),
+      Some("VERSION = '1.1.0'")
+    );
+    assert_eq!(
+      replace("v1.0.0 and v1.0.0", r"v(\d+\.\d+\.\d+)").as_deref(),
+      Some("v1.1.0 and v1.1.0")
+    );
+    assert_eq!(replace("1.0.0", r"\d+\.\d+\.\d+").as_deref(), Some("1.1.0"));
+    assert_eq!(replace("no version", r"\d+\.\d+\.\d+"), None);
+    assert_eq!(
+      replace("name: app\nversion: \"1.0.0\" # The chart\n", CHART_REGEX).as_deref(),
+      Some("name: app\nversion: \"1.1.0\" # The chart\n")
+    );
+  }
+
+  #[test]
+  fn keeps_the_indentation_of_the_json_files() {
+    let paths: Vec<Vec<PathSegment>> = vec![parse_path("$.version").unwrap()];
+
+    assert_eq!(
+      replace_json(
+        "{\n    \"name\": \"app\",\n    \"version\": \"1.0.0\"\n}\n",
+        &paths,
+        "1.1.0"
+      )
+      .unwrap()
+      .as_deref(),
+      Some("{\n    \"name\": \"app\",\n    \"version\": \"1.1.0\"\n}\n")
+    );
+    assert_eq!(
+      replace_json(
+        "{\n\t\"version\": \"1.0.0\",\n\t\"private\": true\n}",
+        &paths,
+        "1.1.0"
+      )
+      .unwrap()
+      .as_deref(),
+      Some("{\n\t\"version\": \"1.1.0\",\n\t\"private\": true\n}")
+    );
+    assert_eq!(
+      replace_json("{\"version\":\"1.0.0\"}", &paths, "1.1.0")
+        .unwrap()
+        .as_deref(),
+      Some("{\n  \"version\": \"1.1.0\"\n}")
+    );
+    assert_eq!(
+      replace_json("{\"version\":1}", &paths, "1.1.0").unwrap(),
+      None
+    );
+    assert!(replace_json("{", &paths, "1.1.0").is_err());
+  }
+
+  #[test]
+  fn sets_the_toml_values() {
+    let content: &str = "[tool.poetry]\nname = \"app\"\nversion = \"1.0.0\" # The version\n";
+
+    assert_eq!(
+      set_toml_path(content, "tool.poetry.version"),
+      (
+        true,
+        "[tool.poetry]\nname = \"app\"\nversion = \"1.1.0\" # The version\n".to_string()
+      )
+    );
+    // The missing keys aren't added to the document
+    assert_eq!(
+      set_toml_path(content, "project.version"),
+      (false, content.to_string())
+    );
+    assert_eq!(
+      set_toml_path(content, "tool.poetry.name.version"),
+      (false, content.to_string())
+    );
+    assert_eq!(
+      set_toml_path("versions = [\"1.0.0\", 2]\n", "versions[1]"),
+      (false, "versions = [\"1.0.0\", 2]\n".to_string())
+    );
+    assert_eq!(
+      set_toml_path("versions = [\"1.0.0\", 2]\n", "versions[0]").1,
+      "versions = [\"1.1.0\", 2]\n"
+    );
+  }
+
+  #[test]
+  fn updates_the_package_json_along_with_its_lockfile() {
+    let cwd: TempDir = TempDir::new().unwrap();
+    fs::write(
+      cwd.path().join("package.json"),
+      "{\n  \"version\": \"1.0.0\"\n}\n",
+    )
+    .unwrap();
+    fs::write(
+      cwd.path().join("package-lock.json"),
+      "{\n  \"version\": \"1.0.0\",\n  \"packages\": {\n    \"\": {\n      \"version\": \"1.0.0\"\n    }\n  }\n}\n",
+    )
+    .unwrap();
+    fs::write(cwd.path().join("VERSION"), "1.0.0\n").unwrap();
+
+    let config: Config = testing::get_config(
+      "
+version: 1
+tag_format: v${version}
+branches: [main]
+projects:
+  - name: app
+    path: .
+",
+    );
+    let mut context: Context = testing::get_context(&config, cwd.path());
+    context.next_release = Some(testing::get_next_release("v1.1.0", "1.1.0"));
+    let mut module: Box<dyn Module> = VersionFilesModule::create(&testing::get_module(
+      "{ name: version-files, files: [package.json, VERSION] }",
+    ))
+    .unwrap();
+
+    module.verify_conditions(&context).unwrap();
+    module.prepare(&context).unwrap();
+
+    let read = |path: &str| -> String { fs::read_to_string(cwd.path().join(path)).unwrap() };
+    assert_eq!(read("package.json"), "{\n  \"version\": \"1.1.0\"\n}\n");
+    assert_eq!(
+      read("package-lock.json"),
+      "{\n  \"version\": \"1.1.0\",\n  \"packages\": {\n    \"\": {\n      \"version\": \"1.1.0\"\n    }\n  }\n}\n"
+    );
+    assert_eq!(read("VERSION"), "1.1.0\n");
+  }
+
+  #[test]
+  fn requires_a_replacement_for_the_unknown_files() {
+    let create = |files: &str| -> Result<Box<dyn Module>, FastReleaseError> {
+      VersionFilesModule::create(&testing::get_module(&format!(
+        "{{ name: version-files, files: {} }}",
+        files
+      )))
+    };
+
+    assert!(create("[VERSION, app/version.txt, Chart.yaml]").is_ok());
+    assert!(
+      create("[{ path: src/app.py, regex: \"__version__ = '(?P<version>[^']+)'\" }]").is_ok()
+    );
+
+    let result: Result<Box<dyn Module>, FastReleaseError> = create("[src/app.py]");
+    assert!(result.is_err_and(|v: FastReleaseError| v.message.contains("'src/app.py'")));
+  }
+}
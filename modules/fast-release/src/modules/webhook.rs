@@ -0,0 +1,662 @@
+// Copyright (c) Toolbi Software. All rights reserved.
+// Check the README file in the project root for more information.
+
+use super::{get_options, Context, Module, NextRelease, ReleaseInfo};
+use crate::{
+  config::ConfigModule,
+  error::{FastReleaseError, FastReleaseErrorBuilder},
+  http,
+  notify::{self, Comment},
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tracing::{debug, info, warn};
+use ureq::Agent;
+use url::Url;
+
+pub const NAME: &str = "webhook";
+
+const DEFAULT_SUCCESS_MESSAGE: &str = "Released the version ${version} of ${project} as ${tag}";
+const DEFAULT_FAIL_MESSAGE: &str = "The release of ${project} from the branch ${branch} failed";
+const DEFAULT_NOTES_LENGTH: usize = 1000;
+const SUCCESS_COLOR: u32 = 0x2eb67d;
+const FAIL_COLOR: u32 = 0xe01e5a;
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum WebhookFormat {
+  #[default]
+  Json,
+  Slack,
+  Discord,
+  Teams,
+}
+
+// Every webhook is an entry of the module, so the same module can post to several chats
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WebhookOptions {
+  // The environment variable with the URL, as the URL is the secret of the webhook
+  url_variable: String,
+  #[serde(default)]
+  format: WebhookFormat,
+  #[serde(default)]
+  success_message: Comment,
+  #[serde(default)]
+  fail_message: Comment,
+  // The release notes are cut to this many characters, where zero leaves them out
+  #[serde(default = "default_notes_length")]
+  notes_length: usize,
+}
+
+fn default_notes_length() -> usize {
+  DEFAULT_NOTES_LENGTH
+}
+
+// What the payloads of every format are built from
+struct Event<'a> {
+  success: bool,
+  message: String,
+  notes: Option<String>,
+  context: &'a Context,
+  errors: &'a [FastReleaseError],
+}
+
+impl Event<'_> {
+  fn build_url(&self) -> Option<&str> {
+    self.context.ci_env.build_url.as_deref()
+  }
+
+  fn color(&self) -> u32 {
+    match self.success {
+      true => SUCCESS_COLOR,
+      false => FAIL_COLOR,
+    }
+  }
+
+  // The summary of the notes of a release, or the list of the errors of a failure with what its
+  // rollback did
+  fn details(&self) -> Option<String> {
+    if self.success {
+      return self.notes.clone();
+    }
+
+    let mut sections: Vec<String> = vec![self
+      .errors
+      .iter()
+      .map(|v: &FastReleaseError| format!("- {}", v.message))
+      .collect::<Vec<String>>()
+      .join("\n")];
+    if let Some(rollback) = &self.context.rollback {
+      sections.push(notify::rollback_summary(rollback));
+    }
+
+    Some(
+      sections
+        .into_iter()
+        .filter(|v: &String| !v.is_empty())
+        .collect::<Vec<String>>()
+        .join("\n\n"),
+    )
+    .filter(|v: &String| !v.is_empty())
+  }
+}
+
+pub struct WebhookModule {
+  options: WebhookOptions,
+  url: Option<String>,
+  agent: Agent,
+}
+
+fn error(message: &str) -> FastReleaseError {
+  FastReleaseErrorBuilder::new(message)
+    .category("WEBHOOK")
+    .get()
+}
+
+// Cuts the notes on a line, so the Markdown of the lines that are left stays valid
+fn summarize(notes: &str, max_length: usize) -> Option<String> {
+  let notes: &str = notes.trim();
+  if notes.is_empty() || max_length == 0 {
+    return None;
+  }
+  if notes.chars().count() <= max_length {
+    return Some(notes.to_string());
+  }
+
+  let mut result: String = String::new();
+  for line in notes.lines() {
+    if result.chars().count() + line.chars().count() + 1 > max_length {
+      break;
+    }
+    result.push_str(line);
+    result.push('\n');
+  }
+  if result.is_empty() {
+    result = notes.chars().take(max_length).collect();
+  }
+
+  Some(format!("{}\n…", result.trim_end()))
+}
+
+fn json_payload(event: &Event) -> Value {
+  let next_release: Option<&NextRelease> = event.context.next_release.as_ref();
+
+  json!({
+    "event": match event.success {
+      true => "success",
+      false => "fail",
+    },
+    "message": event.message,
+    "project": event.context.project.name,
+    "branch": event.context.branch.name,
+    "version": next_release.map(|v: &NextRelease| v.version.to_string()),
+    "tag": next_release.map(|v: &NextRelease| v.tag.clone()),
+    "channel": next_release.and_then(|v: &NextRelease| v.channel.clone()),
+    "notes": event.notes,
+    "build_url": event.build_url(),
+    "releases": event.context.releases.iter().map(|v: &ReleaseInfo| json!({
+      "name": v.name,
+      "url": v.url,
+    })).collect::<Vec<Value>>(),
+    "errors": event.errors.iter().map(|v: &FastReleaseError| json!({
+      "message": v.message,
+      "category": v.category,
+      "details": v.error.as_ref().map(|v: &std::io::Error| v.to_string()),
+    })).collect::<Vec<Value>>(),
+    "rollback": event.context.rollback,
+  })
+}
+
+fn slack_payload(event: &Event) -> Value {
+  let mut blocks: Vec<Value> = vec![json!({
+    "type": "section",
+    "text": { "type": "mrkdwn", "text": format!("*{}*", event.message) },
+  })];
+
+  if let Some(details) = event.details() {
+    blocks.push(json!({
+      "type": "section",
+      "text": { "type": "mrkdwn", "text": details },
+    }));
+  }
+
+  let links: Vec<String> = event
+    .context
+    .releases
+    .iter()
+    .filter_map(|v: &ReleaseInfo| {
+      v.url
+        .as_ref()
+        .map(|url: &String| format!("<{}|{}>", url, v.name))
+    })
+    .chain(event.build_url().map(|v: &str| format!("<{}|Build>", v)))
+    .collect();
+  if !links.is_empty() {
+    blocks.push(json!({
+      "type": "context",
+      "elements": [{ "type": "mrkdwn", "text": links.join(" · ") }],
+    }));
+  }
+
+  json!({ "text": event.message, "blocks": blocks })
+}
+
+fn discord_payload(event: &Event) -> Value {
+  let mut fields: Vec<Value> = event
+    .context
+    .releases
+    .iter()
+    .filter_map(|v: &ReleaseInfo| {
+      v.url
+        .as_ref()
+        .map(|url: &String| json!({ "name": v.name, "value": url, "inline": true }))
+    })
+    .collect();
+  if let Some(build_url) = event.build_url() {
+    fields.push(json!({ "name": "Build", "value": build_url, "inline": true }));
+  }
+
+  json!({
+    "content": null,
+    "embeds": [{
+      "title": event.message,
+      "description": event.details(),
+      "url": event.context.releases.iter().find_map(|v: &ReleaseInfo| v.url.clone()),
+      "color": event.color(),
+      "fields": fields,
+    }],
+  })
+}
+
+// An Adaptive Card, which the Workflows webhooks of Teams take
+fn teams_payload(event: &Event) -> Value {
+  let mut body: Vec<Value> = vec![json!({
+    "type": "TextBlock",
+    "text": event.message,
+    "weight": "Bolder",
+    "size": "Medium",
+    "color": match event.success {
+      true => "Good",
+      false => "Attention",
+    },
+    "wrap": true,
+  })];
+
+  if let Some(details) = event.details() {
+    body.push(json!({ "type": "TextBlock", "text": details, "wrap": true }));
+  }
+
+  let actions: Vec<Value> = event
+    .context
+    .releases
+    .iter()
+    .filter_map(|v: &ReleaseInfo| {
+      v.url
+        .as_ref()
+        .map(|url: &String| json!({ "type": "Action.OpenUrl", "title": v.name, "url": url }))
+    })
+    .chain(
+      event
+        .build_url()
+        .map(|v: &str| json!({ "type": "Action.OpenUrl", "title": "Build", "url": v })),
+    )
+    .collect();
+
+  json!({
+    "type": "message",
+    "attachments": [{
+      "contentType": "application/vnd.microsoft.card.adaptive",
+      "content": {
+        "$schema": "http://adaptivecards.io/schemas/adaptive-card.json",
+        "type": "AdaptiveCard",
+        "version": "1.4",
+        "body": body,
+        "actions": actions,
+      },
+    }],
+  })
+}
+
+impl WebhookModule {
+  pub fn create(module: &ConfigModule) -> Result<Box<dyn Module>, FastReleaseError> {
+    let options: WebhookOptions = get_options(module)?;
+
+    Ok(Box::new(Self {
+      options,
+      url: None,
+      agent: http::agent(),
+    }))
+  }
+
+  fn get_url(&self) -> Result<String, FastReleaseError> {
+    let url: String = http::get_env(&[&self.options.url_variable]).ok_or_else(|| {
+      error(&format!(
+        "The module '{}' needs the URL of the webhook on '{}'.",
+        NAME, self.options.url_variable
+      ))
+    })?;
+
+    if Url::parse(&url).is_err() {
+      return Err(error(&format!(
+        "The URL of the webhook on '{}' is not valid.",
+        self.options.url_variable
+      )));
+    }
+
+    Ok(url)
+  }
+
+  fn post(&self, url: &str, event: &Event) -> Result<(), FastReleaseError> {
+    let payload: Value = match self.options.format {
+      WebhookFormat::Json => json_payload(event),
+      WebhookFormat::Slack => slack_payload(event),
+      WebhookFormat::Discord => discord_payload(event),
+      WebhookFormat::Teams => teams_payload(event),
+    };
+
+    self
+      .agent
+      .post(url)
+      .send_json(payload)
+      .map_err(|v: ureq::Error| {
+        http::error(
+          &format!(
+            "Failed to post to the webhook on '{}'.",
+            self.options.url_variable
+          ),
+          "WEBHOOK",
+          v,
+        )
+      })?;
+
+    debug!(
+      message = format!(
+        "Posted to the webhook on '{}' as {:?}.",
+        self.options.url_variable, self.options.format
+      ),
+      category = "WEBHOOK"
+    );
+
+    Ok(())
+  }
+}
+
+impl Module for WebhookModule {
+  fn name(&self) -> &str {
+    NAME
+  }
+
+  fn verify_conditions(&mut self, _context: &Context) -> Result<(), FastReleaseError> {
+    self.url = Some(self.get_url()?);
+
+    Ok(())
+  }
+
+  // The release is already out, so a webhook that fails is only logged
+  fn success(&mut self, context: &Context) -> Result<(), FastReleaseError> {
+    let template: &str = match self.options.success_message.get(DEFAULT_SUCCESS_MESSAGE) {
+      Some(template) => template,
+      None => return Ok(()),
+    };
+    let url: &str = match &self.url {
+      Some(url) => url,
+      None => return Ok(()),
+    };
+
+    let event: Event = Event {
+      success: true,
+      message: context.render(template),
+      notes: context
+        .next_release
+        .as_ref()
+        .and_then(|v: &NextRelease| summarize(&v.notes, self.options.notes_length)),
+      context,
+      errors: &[],
+    };
+
+    match self.post(url, &event) {
+      Ok(_) => info!(
+        "Announced the release on the webhook of '{}'.",
+        self.options.url_variable
+      ),
+      Err(error) => warn!("{}", error),
+    }
+
+    Ok(())
+  }
+
+  fn fail(
+    &mut self,
+    context: &Context,
+    errors: &[FastReleaseError],
+  ) -> Result<(), FastReleaseError> {
+    let template: &str = match self.options.fail_message.get(DEFAULT_FAIL_MESSAGE) {
+      Some(template) => template,
+      None => return Ok(()),
+    };
+    // The release can fail before this module verifies its conditions
+    let url: String = match &self.url {
+      Some(url) => url.clone(),
+      None => self.get_url()?,
+    };
+
+    let event: Event = Event {
+      success: false,
+      message: context.render(template),
+      notes: None,
+      context,
+      errors,
+    };
+    self.post(&url, &event)?;
+
+    info!(
+      "Announced the failure on the webhook of '{}'.",
+      self.options.url_variable
+    );
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    modules::Rollback,
+    testing::{self, StubRequest, StubResponse, StubServer},
+  };
+  use std::path::Path;
+
+  const CONFIG: &str = "
+version: 1
+tag_format: v${version}
+branches: [main]
+projects:
+  - name: app
+    path: .
+";
+
+  // A release on GitHub and crates.io, where only the first has a link
+  fn get_context() -> Context {
+    let mut context: Context =
+      testing::get_context(&testing::get_config(CONFIG), Path::new("/tmp"));
+    context.next_release = Some(testing::get_next_release("v1.2.0", "1.2.0"));
+    context.ci_env.build_url = Some("https://ci.example.com/builds/7".to_string());
+    context.releases = vec![
+      ReleaseInfo {
+        module: "github".to_string(),
+        name: "GitHub release".to_string(),
+        url: Some("https://github.com/acme/app/releases/v1.2.0".to_string()),
+        channel: None,
+      },
+      ReleaseInfo {
+        module: "crates".to_string(),
+        name: "crates.io package".to_string(),
+        url: None,
+        channel: None,
+      },
+    ];
+    context
+  }
+
+  fn success_event(context: &Context) -> Event<'_> {
+    Event {
+      success: true,
+      message: "Released 1.2.0".to_string(),
+      notes: Some("* add the option".to_string()),
+      context,
+      errors: &[],
+    }
+  }
+
+  #[test]
+  fn summarizes_the_notes() {
+    assert_eq!(
+      summarize("  * first\n* second\n", 100).as_deref(),
+      Some("* first\n* second")
+    );
+    assert_eq!(summarize("* first", 0), None);
+    assert_eq!(summarize(" \n ", 100), None);
+
+    // Cut on the last line that fits
+    assert_eq!(
+      summarize("## 1.0.0\n\n* first\n* second", 18).as_deref(),
+      Some("## 1.0.0\n\n* first\n…")
+    );
+    // Cut inside the first line when it doesn't fit
+    assert_eq!(summarize("abcdef", 3).as_deref(), Some("abc\n…"));
+    // Counted in characters, not in bytes
+    assert_eq!(summarize("ééé\nààà", 5).as_deref(), Some("ééé\n…"));
+    assert_eq!(summarize("éééééé", 3).as_deref(), Some("ééé\n…"));
+  }
+
+  #[test]
+  fn builds_the_json_payload() {
+    let mut context: Context = get_context();
+    context.releases.truncate(1);
+    context.rollback = Some(Rollback {
+      done: vec!["Deleted the tag 'v1.2.0'.".to_string()],
+      manual: Vec::new(),
+    });
+    let errors: Vec<FastReleaseError> = vec![FastReleaseErrorBuilder::new("Failed to publish.")
+      .category("CARGO")
+      .error(std::io::Error::other("timeout"))
+      .get()];
+    let event: Event = Event {
+      success: false,
+      message: "The release failed".to_string(),
+      notes: None,
+      context: &context,
+      errors: &errors,
+    };
+
+    assert_eq!(
+      json_payload(&event),
+      json!({
+        "event": "fail",
+        "message": "The release failed",
+        "project": "app",
+        "branch": "main",
+        "version": "1.2.0",
+        "tag": "v1.2.0",
+        "channel": null,
+        "notes": null,
+        "build_url": "https://ci.example.com/builds/7",
+        "releases": [
+          { "name": "GitHub release", "url": "https://github.com/acme/app/releases/v1.2.0" },
+        ],
+        "errors": [{ "message": "Failed to publish.", "category": "CARGO", "details": "timeout" }],
+        "rollback": { "done": ["Deleted the tag 'v1.2.0'."], "manual": [] },
+      })
+    );
+  }
+
+  #[test]
+  fn builds_the_slack_payload() {
+    let context: Context = get_context();
+
+    assert_eq!(
+      slack_payload(&success_event(&context)),
+      json!({
+        "text": "Released 1.2.0",
+        "blocks": [
+          { "type": "section", "text": { "type": "mrkdwn", "text": "*Released 1.2.0*" } },
+          { "type": "section", "text": { "type": "mrkdwn", "text": "* add the option" } },
+          {
+            "type": "context",
+            "elements": [{
+              "type": "mrkdwn",
+              "text": "<https://github.com/acme/app/releases/v1.2.0|GitHub release> · <https://ci.example.com/builds/7|Build>",
+            }],
+          },
+        ],
+      })
+    );
+  }
+
+  #[test]
+  fn builds_the_discord_payload() {
+    let context: Context = get_context();
+
+    assert_eq!(
+      discord_payload(&success_event(&context)),
+      json!({
+        "content": null,
+        "embeds": [{
+          "title": "Released 1.2.0",
+          "description": "* add the option",
+          "url": "https://github.com/acme/app/releases/v1.2.0",
+          "color": SUCCESS_COLOR,
+          "fields": [
+            {
+              "name": "GitHub release",
+              "value": "https://github.com/acme/app/releases/v1.2.0",
+              "inline": true,
+            },
+            { "name": "Build", "value": "https://ci.example.com/builds/7", "inline": true },
+          ],
+        }],
+      })
+    );
+  }
+
+  #[test]
+  fn builds_the_teams_payload() {
+    let context: Context = get_context();
+    let errors: Vec<FastReleaseError> = vec![error("Failed to publish.")];
+    let event: Event = Event {
+      success: false,
+      message: "The release failed".to_string(),
+      notes: None,
+      context: &context,
+      errors: &errors,
+    };
+
+    let payload: Value = teams_payload(&event);
+
+    let content: &Value = &payload["attachments"][0]["content"];
+    assert_eq!(payload["type"], json!("message"));
+    assert_eq!(content["type"], json!("AdaptiveCard"));
+    assert_eq!(
+      content["body"],
+      json!([
+        {
+          "type": "TextBlock",
+          "text": "The release failed",
+          "weight": "Bolder",
+          "size": "Medium",
+          "color": "Attention",
+          "wrap": true,
+        },
+        { "type": "TextBlock", "text": "- Failed to publish.", "wrap": true },
+      ])
+    );
+    assert_eq!(
+      content["actions"],
+      json!([
+        {
+          "type": "Action.OpenUrl",
+          "title": "GitHub release",
+          "url": "https://github.com/acme/app/releases/v1.2.0",
+        },
+        { "type": "Action.OpenUrl", "title": "Build", "url": "https://ci.example.com/builds/7" },
+      ])
+    );
+  }
+
+  #[test]
+  fn posts_the_release_to_the_webhook() {
+    let server: StubServer = StubServer::start(|_: &StubRequest| StubResponse::new(200, "ok"));
+    let mut context: Context = get_context();
+    if let Some(next_release) = context.next_release.as_mut() {
+      next_release.notes = "## 1.2.0\n\n* add the option\n* fix the crash".to_string();
+    }
+    let mut module: Box<dyn Module> = WebhookModule::create(&testing::get_module(
+      "{ name: webhook, url_variable: CHAT_WEBHOOK, format: discord, notes_length: 27 }",
+    ))
+    .unwrap();
+
+    testing::with_env(
+      &[("CHAT_WEBHOOK", Some(&format!("{}/hooks/1", server.url)))],
+      || {
+        module.verify_conditions(&context)?;
+        module.success(&context)
+      },
+    )
+    .unwrap();
+
+    let requests: Vec<StubRequest> = server.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].method, "POST");
+    assert_eq!(requests[0].path, "/hooks/1");
+    assert_eq!(
+      requests[0].json()["embeds"][0]["title"],
+      json!("Released the version 1.2.0 of app as v1.2.0")
+    );
+    assert_eq!(
+      requests[0].json()["embeds"][0]["description"],
+      json!("## 1.2.0\n\n* add the option\n…")
+    );
+  }
+}
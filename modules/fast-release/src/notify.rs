@@ -0,0 +1,272 @@
+// Copyright (c) Toolbi Software. All rights reserved.
+// Check the README file in the project root for more information.
+
+use crate::{
+  analyzer::commit::Commit,
+  error::FastReleaseError,
+  modules::{Context, ReleaseInfo, Rollback},
+  tag_format,
+};
+use regex::{Captures, Regex};
+use serde::Deserialize;
+use std::collections::BTreeSet;
+
+pub const DEFAULT_SUCCESS_COMMENT: &str =
+  ":tada: This is included in the version `${version}` of `${project}`, released as `${tag}`.";
+pub const DEFAULT_FAIL_TITLE: &str = "The automated release of '${project}' is failing";
+pub const DEFAULT_FAIL_COMMENT: &str =
+  "## :rotating_light: The automated release from the `${branch}` branch failed :rotating_light:
+
+The errors are below. Once they are fixed and a release succeeds, this issue is closed.
+
+${errors}";
+
+// The comments of the 'success' and 'fail' steps can be turned off, or be a custom template
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Comment {
+  Enabled(bool),
+  Template(String),
+}
+
+impl Default for Comment {
+  fn default() -> Self {
+    Comment::Enabled(true)
+  }
+}
+
+impl Comment {
+  pub fn get<'a>(&'a self, default: &'a str) -> Option<&'a str> {
+    match self {
+      Comment::Enabled(true) => Some(default),
+      Comment::Enabled(false) => None,
+      Comment::Template(template) => Some(template),
+    }
+  }
+}
+
+pub fn default_released_labels() -> Vec<String> {
+  vec!["released".into()]
+}
+
+pub fn default_fail_labels() -> Vec<String> {
+  vec!["fast-release".into()]
+}
+
+// The numbers referenced on the messages of the commits with a prefix, like '#12' or '!4'
+pub fn get_references(commits: &[Commit], prefix: &str) -> BTreeSet<u64> {
+  let regex: Regex = Regex::new(&format!(r"(^|[\s(]){}(\d+)\b", regex::escape(prefix))).unwrap();
+
+  commits
+    .iter()
+    .flat_map(|v: &Commit| {
+      regex
+        .captures_iter(&v.message)
+        .filter_map(|v: Captures| v[2].parse::<u64>().ok())
+        .collect::<Vec<u64>>()
+    })
+    .collect()
+}
+
+// The comment ends with the releases published by the modules before this one
+pub fn success_comment(context: &Context, template: &str) -> String {
+  let mut comment: String = context.render(template);
+
+  let releases: Vec<String> = context
+    .releases
+    .iter()
+    .map(|v: &ReleaseInfo| match &v.url {
+      Some(url) => format!("- [{}]({})", v.name, url),
+      None => format!("- {}", v.name),
+    })
+    .collect();
+  if !releases.is_empty() {
+    comment.push_str("\n\nThe release is available on:\n");
+    comment.push_str(&releases.join("\n"));
+  }
+
+  comment
+}
+
+// What a rollback took back and what is left, as Markdown lists
+pub fn rollback_summary(rollback: &Rollback) -> String {
+  let mut sections: Vec<String> = Vec::new();
+  for (title, items) in [
+    ("Rolled back", &rollback.done),
+    ("To clean up by hand", &rollback.manual),
+  ] {
+    if !items.is_empty() {
+      let items: Vec<String> = items.iter().map(|v: &String| format!("- {}", v)).collect();
+      sections.push(format!("**{}:**\n{}", title, items.join("\n")));
+    }
+  }
+
+  sections.join("\n\n")
+}
+
+// Besides the placeholders of the release, the template takes '${errors}'. The comment ends with
+// the summary of the rollback, when there was one.
+pub fn fail_comment(context: &Context, template: &str, errors: &[FastReleaseError]) -> String {
+  let errors: String = errors
+    .iter()
+    .map(|v: &FastReleaseError| {
+      let mut result: String = match &v.category {
+        Some(category) => format!("### {}\n\nCategory: `{}`", v.message, category),
+        None => format!("### {}", v.message),
+      };
+      if let Some(error) = &v.error {
+        result.push_str(&format!("\n\n```\n{}\n```", error));
+      }
+      result
+    })
+    .collect::<Vec<String>>()
+    .join("\n\n");
+
+  let mut comment: String =
+    tag_format::render_placeholders(&context.render(template), &[("errors", &errors)]);
+  if let Some(summary) = context
+    .rollback
+    .as_ref()
+    .map(rollback_summary)
+    .filter(|v: &String| !v.is_empty())
+  {
+    comment.push_str("\n\n");
+    comment.push_str(&summary);
+  }
+
+  comment
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{error::FastReleaseErrorBuilder, testing};
+  use std::{io::Error, path::Path};
+
+  const CONFIG: &str = "
+version: 1
+tag_format: v${version}
+branches: [main]
+projects:
+  - name: app
+    path: .
+";
+
+  fn get_commits(messages: &[&str]) -> Vec<Commit> {
+    messages
+      .iter()
+      .map(|v: &&str| Commit::new(&"0".repeat(40), v, "Tester", "tester@example.com", 0))
+      .collect()
+  }
+
+  fn get_context() -> Context {
+    let mut context: Context =
+      testing::get_context(&testing::get_config(CONFIG), Path::new("/tmp"));
+    context.next_release = Some(testing::get_next_release("v1.2.0", "1.2.0"));
+    context
+  }
+
+  #[test]
+  fn gets_the_references_of_the_commits() {
+    let commits: Vec<Commit> = get_commits(&[
+      "fix: stop the crash (#12)",
+      "feat: add the option\n\nCloses #13, refs #12",
+      "#14 at the start",
+      "chore: merge !4 into main",
+      "docs: mention a#1, #x and #15a",
+    ]);
+
+    assert_eq!(
+      get_references(&commits, "#")
+        .into_iter()
+        .collect::<Vec<u64>>(),
+      [12, 13, 14]
+    );
+    assert_eq!(
+      get_references(&commits, "!")
+        .into_iter()
+        .collect::<Vec<u64>>(),
+      [4]
+    );
+  }
+
+  #[test]
+  fn renders_the_success_comment() {
+    let mut context: Context = get_context();
+    assert_eq!(
+      success_comment(&context, DEFAULT_SUCCESS_COMMENT),
+      ":tada: This is included in the version `1.2.0` of `app`, released as `v1.2.0`."
+    );
+
+    context.releases = vec![
+      ReleaseInfo {
+        module: "github".to_string(),
+        name: "GitHub release".to_string(),
+        url: Some("https://github.com/acme/app/releases/v1.2.0".to_string()),
+        channel: None,
+      },
+      ReleaseInfo {
+        module: "crates".to_string(),
+        name: "crates.io package".to_string(),
+        url: None,
+        channel: None,
+      },
+    ];
+    assert_eq!(
+      success_comment(&context, "Released `${tag}`."),
+      "Released `v1.2.0`.\n\nThe release is available on:\n- [GitHub release](https://github.com/acme/app/releases/v1.2.0)\n- crates.io package"
+    );
+  }
+
+  #[test]
+  fn renders_the_rollback_summary() {
+    assert_eq!(rollback_summary(&Rollback::default()), "");
+    assert_eq!(
+      rollback_summary(&Rollback {
+        done: vec!["Deleted the tag 'v1.2.0'.".to_string()],
+        manual: Vec::new(),
+      }),
+      "**Rolled back:**\n- Deleted the tag 'v1.2.0'."
+    );
+    assert_eq!(
+      rollback_summary(&Rollback {
+        done: vec![
+          "Deleted the GitHub release 'v1.2.0'.".to_string(),
+          "Deleted the tag 'v1.2.0'.".to_string(),
+        ],
+        manual: vec!["The packages 'app@1.2.0' stay on 'crates-io'.".to_string()],
+      }),
+      "**Rolled back:**\n- Deleted the GitHub release 'v1.2.0'.\n- Deleted the tag 'v1.2.0'.\n\n**To clean up by hand:**\n- The packages 'app@1.2.0' stay on 'crates-io'."
+    );
+  }
+
+  #[test]
+  fn renders_the_fail_comment() {
+    let mut context: Context = get_context();
+    let errors: Vec<FastReleaseError> = vec![
+      FastReleaseErrorBuilder::new("Failed to publish the package.")
+        .category("CRATES")
+        .error(Error::other("error: 403 Forbidden"))
+        .get(),
+      FastReleaseErrorBuilder::new("Something else failed.").get(),
+    ];
+
+    assert_eq!(
+      fail_comment(&context, "Failed on `${branch}` for `${tag}`.\n\n${errors}", &errors),
+      "Failed on `main` for `v1.2.0`.\n\n### Failed to publish the package.\n\nCategory: `CRATES`\n\n```\nerror: 403 Forbidden\n```\n\n### Something else failed."
+    );
+
+    context.rollback = Some(Rollback {
+      done: vec!["Deleted the tag 'v1.2.0'.".to_string()],
+      manual: Vec::new(),
+    });
+    assert_eq!(
+      fail_comment(&context, "Failed.", &[]),
+      "Failed.\n\n**Rolled back:**\n- Deleted the tag 'v1.2.0'."
+    );
+
+    // An empty rollback adds nothing
+    context.rollback = Some(Rollback::default());
+    assert_eq!(fail_comment(&context, "Failed.", &[]), "Failed.");
+  }
+}
@@ -0,0 +1,329 @@
+// Copyright (c) Toolbi Software. All rights reserved.
+// Check the README file in the project root for more information.
+
+use crate::{
+  analyzer::{self, commit::Commit, rules::ReleaseType},
+  branches::{self, Branch, BranchRange},
+  config::{Config, ConfigModule, ConfigProject},
+  dependencies::{self, CascadeRule, DependencyGraph},
+  error::FastReleaseError,
+  git::{self, notes::Journal},
+  release::{self, LastRelease},
+};
+use git2::Repository;
+use semver::Version;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info};
+
+// Everything a release depends on besides the project itself
+pub struct ReleaseScope<'a> {
+  pub repo: &'a Repository,
+  pub config: &'a Config,
+  pub branches: &'a [Branch],
+  pub branch: &'a Branch,
+  pub head: &'a str,
+}
+
+// A release that failed after its tag was created, which resumes from the steps left on its
+// journal
+#[derive(Debug, Clone)]
+pub struct ReleaseToResume {
+  pub release: LastRelease,
+  pub journal: Journal,
+  pub commits: Vec<Commit>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ProjectRelease {
+  pub project: ConfigProject,
+  pub last_release: Option<LastRelease>,
+  pub release_to_resume: Option<ReleaseToResume>,
+  pub release_to_add: Option<LastRelease>,
+  pub commits: Vec<Commit>,
+  pub release_type: ReleaseType,
+  pub next_version: Option<Version>,
+  pub next_tag: Option<String>,
+}
+
+// Returns the path of the project, or none when the project is the whole repository
+pub fn get_path(project: &ConfigProject) -> Option<&Path> {
+  (project.path != ".").then(|| Path::new(&project.path))
+}
+
+// The modules of the configuration run for every project, followed by the ones of the project.
+// A project module with the same name as a configuration one replaces its options.
+pub fn get_modules(config: &Config, project: &ConfigProject) -> Vec<ConfigModule> {
+  let mut result: Vec<ConfigModule> = config.modules.clone();
+  for module in &project.modules {
+    match result
+      .iter_mut()
+      .find(|v: &&mut ConfigModule| v.name == module.name)
+    {
+      Some(existing) => *existing = module.clone(),
+      None => result.push(module.clone()),
+    }
+  }
+
+  result
+}
+
+// Analyzes the commits of a project on top of the release rules
+pub type AnalyzeCommits<'a> = dyn FnMut(&ConfigProject, &[Commit], Option<&LastRelease>) -> Result<ReleaseType, FastReleaseError>
+  + 'a;
+
+// The release type of a project is at least the one cascaded from the projects it depends on
+fn analyze(
+  scope: &ReleaseScope,
+  project: &ConfigProject,
+  cascaded: ReleaseType,
+  analyze_commits: &mut AnalyzeCommits,
+) -> Result<ProjectRelease, FastReleaseError> {
+  let ReleaseScope {
+    repo,
+    config,
+    branches,
+    branch,
+    head,
+  } = *scope;
+  let name: Option<&str> = Some(project.name.as_str());
+
+  let last_release: Option<LastRelease> = release::get_last_release(repo, head, name, branch)?;
+  let last_stable_release: Option<LastRelease> =
+    release::get_last_stable_release(repo, head, name, branch)?;
+
+  let release_to_resume: Option<ReleaseToResume> =
+    match last_release.as_ref().and_then(|v: &LastRelease| {
+      git::notes::get_journal(repo, &v.tag, &v.hash).map(|journal: Journal| (v, journal))
+    }) {
+      Some((release, journal)) => {
+        info!(
+          "The release '{}' of the project '{}' didn't complete, so it's resumed.",
+          release.tag, project.name
+        );
+        Some(ReleaseToResume {
+          commits: analyzer::get_commits(
+            repo,
+            &release.hash,
+            journal
+              .last_release
+              .as_ref()
+              .map(|v: &LastRelease| v.hash.as_str()),
+            get_path(project),
+          )?,
+          release: release.clone(),
+          journal,
+        })
+      }
+      None => None,
+    };
+
+  let release_to_add: Option<LastRelease> =
+    release::get_release_to_add(repo, branch, last_stable_release.as_ref());
+  if let Some(release_to_add) = &release_to_add {
+    info!(
+      "The release '{}' of the project '{}' was merged from another channel, so it's added to the channel '{}'.",
+      release_to_add.tag,
+      project.name,
+      branch.channel.as_deref().unwrap_or("default")
+    );
+  }
+
+  let commits: Vec<Commit> = analyzer::get_commits(
+    repo,
+    head,
+    last_release.as_ref().map(|v: &LastRelease| v.hash.as_str()),
+    get_path(project),
+  )?;
+  let release_type: ReleaseType = {
+    let analyzed: ReleaseType = analyzer::analyze(&commits, &config.release_rules)
+      .max(analyze_commits(project, &commits, last_release.as_ref())?);
+    if cascaded > analyzed {
+      debug!(
+        message = format!(
+          "The project '{}' gets a '{}' release because of its dependencies.",
+          project.name, cascaded
+        ),
+        category = "PROJECT"
+      );
+    }
+    analyzed.max(cascaded)
+  };
+
+  let next_version: Option<Version> = release::get_next_version(
+    branch,
+    release_type,
+    last_release.as_ref(),
+    last_stable_release.as_ref(),
+  );
+
+  if let Some(next_version) = &next_version {
+    let range: Option<BranchRange> = branches::get_range(
+      repo,
+      &config.git.remote,
+      branches,
+      branch,
+      head,
+      name,
+      last_stable_release
+        .as_ref()
+        .map(|v: &LastRelease| &v.version),
+    )?;
+    release::verify_next_version(branch, next_version, range.as_ref())?;
+  }
+
+  let next_tag: Option<String> = next_version
+    .as_ref()
+    .map(|v: &Version| branch.tag_format.render(v, name));
+
+  match (&next_version, &next_tag) {
+    (Some(version), Some(tag)) => info!(
+      "The next release version of the project '{}' is '{}' with the tag '{}'.",
+      project.name, version, tag
+    ),
+    _ => info!(
+      "There are no relevant changes on the project '{}', so no new version is released.",
+      project.name
+    ),
+  }
+
+  Ok(ProjectRelease {
+    project: project.clone(),
+    last_release,
+    release_to_resume,
+    release_to_add,
+    commits,
+    release_type,
+    next_version,
+    next_tag,
+  })
+}
+
+pub fn analyze_all(
+  scope: &ReleaseScope,
+  analyze_commits: &mut AnalyzeCommits,
+) -> Result<Vec<ProjectRelease>, FastReleaseError> {
+  let config: &Config = scope.config;
+  let root: PathBuf = git::repo::get_root(scope.repo);
+  let graph: DependencyGraph = dependencies::get_graph(Some(&root), &config.projects)?;
+
+  let mut result: Vec<ProjectRelease> = Vec::new();
+  for project in dependencies::sort(&config.projects, &graph)? {
+    let cascade: CascadeRule = project.cascade.unwrap_or(config.cascade);
+    let cascaded: ReleaseType = graph
+      .get(&project.name)
+      .map(|v: &Vec<String>| v.as_slice())
+      .unwrap_or_default()
+      .iter()
+      .filter_map(|v: &String| {
+        result
+          .iter()
+          .find(|r: &&ProjectRelease| &r.project.name == v)
+      })
+      .map(|v: &ProjectRelease| cascade.apply(v.release_type))
+      .max()
+      .unwrap_or(ReleaseType::None);
+
+    result.push(analyze(scope, project, cascaded, analyze_commits)?);
+  }
+
+  Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    config,
+    testing::{self, TestRepo},
+  };
+
+  const CONFIG: &str = "
+version: 1
+tag_format: ${project}-v${version}
+branches: [main]
+modules:
+  - name: changelog
+  - name: git
+projects:
+  - name: core
+    path: ./crates/core/
+  - name: app
+    path: crates/app
+    modules:
+      - name: git
+        message: 'chore(release): ${tag}'
+";
+
+  fn analyze_projects(test_repo: &TestRepo, config: &Config) -> Vec<ProjectRelease> {
+    testing::with_scope(test_repo, config, |scope: &ReleaseScope| {
+      analyze_all(
+        scope,
+        &mut |_: &ConfigProject, _: &[Commit], _: Option<&LastRelease>| Ok(ReleaseType::None),
+      )
+    })
+    .unwrap()
+  }
+
+  #[test]
+  fn gets_the_paths_and_the_modules_of_the_projects() -> Result<(), FastReleaseError> {
+    let config: Config = config::parse(CONFIG)?;
+    let core: &ConfigProject = &config.projects[0];
+    let app: &ConfigProject = &config.projects[1];
+
+    assert_eq!(get_path(core), Some(Path::new("crates/core")));
+    assert_eq!(get_path(app), Some(Path::new("crates/app")));
+
+    let names = |modules: Vec<ConfigModule>| -> Vec<String> {
+      modules.into_iter().map(|v: ConfigModule| v.name).collect()
+    };
+    assert_eq!(names(get_modules(&config, core)), ["changelog", "git"]);
+    assert_eq!(names(get_modules(&config, app)), ["changelog", "git"]);
+    assert_eq!(
+      get_modules(&config, app)[1].options.get("message"),
+      Some(&serde_yaml::Value::from("chore(release): ${tag}"))
+    );
+    assert!(get_modules(&config, core)[1]
+      .options
+      .get("message")
+      .is_none());
+
+    Ok(())
+  }
+
+  // Only the project with changes under its path is released, with a tag of its own
+  #[test]
+  fn releases_the_projects_with_changes() -> Result<(), FastReleaseError> {
+    let config: Config = config::parse(CONFIG)?;
+    let test_repo: TestRepo = TestRepo::new();
+    test_repo.write("crates/core/lib.rs", "core");
+    test_repo.write("crates/app/main.rs", "app");
+    let hash: String = test_repo.commit("feat: add the projects");
+    git::repo::create_tag(&test_repo.repo, "core-v1.0.0", &hash, None)?;
+    git::repo::create_tag(&test_repo.repo, "app-v1.0.0", &hash, None)?;
+
+    test_repo.write("crates/core/lib.rs", "core 2");
+    test_repo.commit("fix: change the core");
+
+    let releases: Vec<ProjectRelease> = analyze_projects(&test_repo, &config);
+
+    let core: &ProjectRelease = &releases[0];
+    assert_eq!(core.project.name, "core");
+    assert_eq!(
+      core
+        .last_release
+        .as_ref()
+        .map(|v: &LastRelease| v.tag.as_str()),
+      Some("core-v1.0.0")
+    );
+    assert_eq!(core.release_type, ReleaseType::Patch);
+    assert_eq!(core.next_tag.as_deref(), Some("core-v1.0.1"));
+
+    let app: &ProjectRelease = &releases[1];
+    assert_eq!(app.project.name, "app");
+    assert!(app.commits.is_empty());
+    assert_eq!(app.next_version, None);
+    assert_eq!(app.next_tag, None);
+
+    Ok(())
+  }
+}
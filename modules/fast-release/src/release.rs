@@ -0,0 +1,379 @@
+// Copyright (c) Toolbi Software. All rights reserved.
+// Check the README file in the project root for more information.
+
+use crate::{
+  analyzer::rules::ReleaseType,
+  branches::{Branch, BranchRange, BranchType},
+  constants::FIRST_RELEASE_VERSION,
+  error::{FastReleaseError, FastReleaseErrorBuilder},
+  git::{
+    notes,
+    repo::{self, Tag},
+  },
+  tag_format::TagFormat,
+};
+use git2::Repository;
+use semver::{Prerelease, Version};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LastRelease {
+  pub version: Version,
+  pub tag: String,
+  pub hash: String,
+}
+
+fn find(
+  repo: &Repository,
+  tag_format: &TagFormat,
+  head: &str,
+  project: Option<&str>,
+  filter: impl Fn(&Version) -> bool,
+) -> Result<Option<LastRelease>, FastReleaseError> {
+  let mut result: Option<LastRelease> = None;
+
+  for tag in repo::get_tags(repo)? {
+    let Tag { name, hash } = tag;
+
+    let version: Version = match tag_format.parse(&name, project) {
+      Some(version) if filter(&version) => version,
+      _ => continue,
+    };
+
+    if !repo::is_ancestor(repo, &hash, head) {
+      continue;
+    }
+
+    if result
+      .as_ref()
+      .is_none_or(|v: &LastRelease| version > v.version)
+    {
+      result = Some(LastRelease {
+        version,
+        tag: name,
+        hash,
+      });
+    }
+  }
+
+  Ok(result)
+}
+
+fn is_in_range(version: &Version, branch: &Branch) -> bool {
+  branch
+    .range
+    .as_ref()
+    .is_none_or(|v: &BranchRange| v.contains(version))
+}
+
+fn is_branch_pre_release(version: &Version, branch: &Branch) -> bool {
+  branch.pre_release.as_ref().is_some_and(|v: &String| {
+    version
+      .pre
+      .as_str()
+      .strip_prefix(v.as_str())
+      .is_some_and(|v: &str| v.starts_with('.'))
+  })
+}
+
+// Finds the release with the highest version among the tags reachable from 'head'. On
+// pre-release branches, the pre-releases of the branch are taken into account too.
+pub fn get_last_release(
+  repo: &Repository,
+  head: &str,
+  project: Option<&str>,
+  branch: &Branch,
+) -> Result<Option<LastRelease>, FastReleaseError> {
+  let result: Option<LastRelease> =
+    find(repo, &branch.tag_format, head, project, |v: &Version| {
+      (v.pre.is_empty() || is_branch_pre_release(v, branch)) && is_in_range(v, branch)
+    })?;
+
+  match &result {
+    Some(last_release) => debug!(
+      message = format!(
+        "Found the last release '{}' on the tag '{}'.",
+        last_release.version, last_release.tag
+      ),
+      category = "RELEASE"
+    ),
+    None => debug!(
+      message = "There are no previous releases.",
+      category = "RELEASE"
+    ),
+  }
+
+  Ok(result)
+}
+
+pub fn get_last_stable_release(
+  repo: &Repository,
+  head: &str,
+  project: Option<&str>,
+  branch: &Branch,
+) -> Result<Option<LastRelease>, FastReleaseError> {
+  find(repo, &branch.tag_format, head, project, |v: &Version| {
+    v.pre.is_empty() && is_in_range(v, branch)
+  })
+}
+
+// On release and maintenance branches, the last release merged from a branch of another channel
+// is added to the channel of the branch, like when 'next' is merged into 'main'
+pub fn get_release_to_add(
+  repo: &Repository,
+  branch: &Branch,
+  last_stable_release: Option<&LastRelease>,
+) -> Option<LastRelease> {
+  if branch.pre_release.is_some() {
+    return None;
+  }
+
+  last_stable_release
+    .filter(|v: &&LastRelease| {
+      !notes::get_channels(repo, &v.tag, &v.hash).contains(&branch.channel)
+    })
+    .cloned()
+}
+
+// Every stable release of a project reachable from 'head', from the oldest to the newest
+pub fn get_releases(
+  repo: &Repository,
+  head: &str,
+  project: Option<&str>,
+  tag_format: &TagFormat,
+) -> Result<Vec<LastRelease>, FastReleaseError> {
+  let mut result: Vec<LastRelease> = Vec::new();
+
+  for tag in repo::get_tags(repo)? {
+    let Tag { name, hash } = tag;
+
+    let version: Version = match tag_format.parse(&name, project) {
+      Some(version) if version.pre.is_empty() => version,
+      _ => continue,
+    };
+
+    if repo::is_ancestor(repo, &hash, head) {
+      result.push(LastRelease {
+        version,
+        tag: name,
+        hash,
+      });
+    }
+  }
+
+  result.sort_by(|a: &LastRelease, b: &LastRelease| a.version.cmp(&b.version));
+  Ok(result)
+}
+
+fn with_pre_release(version: Version, identifier: &str, number: u64) -> Version {
+  let mut version: Version = version;
+  version.pre = Prerelease::new(&format!("{}.{}", identifier, number)).unwrap();
+  version
+}
+
+fn next_pre_release(version: &Version) -> Version {
+  let mut parts: Vec<String> = version
+    .pre
+    .as_str()
+    .split('.')
+    .map(|v: &str| v.to_string())
+    .collect();
+  match parts.last().and_then(|v: &String| v.parse::<u64>().ok()) {
+    Some(number) => *parts.last_mut().unwrap() = (number + 1).to_string(),
+    None => parts.push("1".into()),
+  }
+
+  let mut result: Version = Version::new(version.major, version.minor, version.patch);
+  result.pre = Prerelease::new(&parts.join(".")).unwrap();
+  result
+}
+
+pub fn get_next_version(
+  branch: &Branch,
+  release_type: ReleaseType,
+  last_release: Option<&LastRelease>,
+  last_stable_release: Option<&LastRelease>,
+) -> Option<Version> {
+  if release_type == ReleaseType::None {
+    return None;
+  }
+
+  let last_version: Option<&Version> = last_release.map(|v: &LastRelease| &v.version);
+
+  let first_version: Version = match &branch.range {
+    Some(range) => range.min.clone(),
+    None => Version::parse(FIRST_RELEASE_VERSION).unwrap(),
+  };
+
+  match (&branch.pre_release, last_version) {
+    (Some(identifier), Some(last_version)) => {
+      if is_branch_pre_release(last_version, branch) {
+        // Keep counting the current pre-release unless the changes require a higher version
+        let next: Version = next_pre_release(last_version);
+        let base: Version = match last_stable_release {
+          Some(stable) => release_type.bump(&stable.version)?,
+          None => first_version,
+        };
+        let from_base: Version = with_pre_release(base, identifier, 1);
+
+        Some(next.max(from_base))
+      } else {
+        Some(with_pre_release(
+          release_type.bump(last_version)?,
+          identifier,
+          1,
+        ))
+      }
+    }
+    (Some(identifier), None) => Some(with_pre_release(first_version, identifier, 1)),
+    (None, Some(last_version)) => release_type.bump(last_version),
+    (None, None) => Some(first_version),
+  }
+}
+
+pub fn verify_next_version(
+  branch: &Branch,
+  version: &Version,
+  range: Option<&BranchRange>,
+) -> Result<(), FastReleaseError> {
+  let range: &BranchRange = match range {
+    Some(range) => range,
+    None => return Ok(()),
+  };
+
+  if range.contains(version) {
+    return Ok(());
+  }
+
+  let hint: &str = match branch.branch_type {
+    BranchType::Maintenance => "Maintenance branches can only publish fixes and features inside their range. Merge the changes into a release branch instead.",
+    _ => "A later release branch has already published this version. Merge the changes into that branch instead.",
+  };
+
+  Err(
+    FastReleaseErrorBuilder::new(&format!(
+      "The release '{}' on the branch '{}' is outside of its range '{}'. {}",
+      version, branch.name, range, hint
+    ))
+    .category("RELEASE")
+    .get(),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{branches, config::Config, testing};
+
+  fn get_branch(name: &str) -> Branch {
+    let config: Config = testing::get_config(
+      "
+version: 1
+tag_format: v${version}
+branches: [main, '1.2.x', { name: beta, prerelease: true }]
+projects:
+  - name: app
+    path: .
+",
+    );
+
+    branches::expand(&config, &[])
+      .unwrap()
+      .into_iter()
+      .find(|v: &Branch| v.name == name)
+      .unwrap()
+  }
+
+  fn get_release(version: &str) -> LastRelease {
+    LastRelease {
+      version: Version::parse(version).unwrap(),
+      tag: format!("v{}", version),
+      hash: "0".repeat(40),
+    }
+  }
+
+  fn next_version(
+    branch: &str,
+    release_type: ReleaseType,
+    last_release: Option<&str>,
+    last_stable_release: Option<&str>,
+  ) -> Option<String> {
+    get_next_version(
+      &get_branch(branch),
+      release_type,
+      last_release.map(get_release).as_ref(),
+      last_stable_release.map(get_release).as_ref(),
+    )
+    .map(|v: Version| v.to_string())
+  }
+
+  #[test]
+  fn bumps_the_version_of_a_release_branch() {
+    assert_eq!(next_version("main", ReleaseType::None, None, None), None);
+    assert_eq!(
+      next_version("main", ReleaseType::Patch, None, None).as_deref(),
+      Some(FIRST_RELEASE_VERSION)
+    );
+    assert_eq!(
+      next_version("main", ReleaseType::Minor, Some("1.2.3"), Some("1.2.3")).as_deref(),
+      Some("1.3.0")
+    );
+  }
+
+  #[test]
+  fn starts_a_maintenance_branch_at_its_range() {
+    assert_eq!(
+      next_version("1.2.x", ReleaseType::Patch, None, None).as_deref(),
+      Some("1.2.0")
+    );
+    assert_eq!(
+      next_version("1.2.x", ReleaseType::Patch, Some("1.2.4"), Some("1.2.4")).as_deref(),
+      Some("1.2.5")
+    );
+  }
+
+  #[test]
+  fn counts_the_pre_releases() {
+    assert_eq!(
+      next_version("beta", ReleaseType::Minor, None, None).as_deref(),
+      Some("1.0.0-beta.1")
+    );
+    assert_eq!(
+      next_version("beta", ReleaseType::Minor, Some("1.2.3"), Some("1.2.3")).as_deref(),
+      Some("1.3.0-beta.1")
+    );
+    assert_eq!(
+      next_version(
+        "beta",
+        ReleaseType::Patch,
+        Some("1.3.0-beta.1"),
+        Some("1.2.3")
+      )
+      .as_deref(),
+      Some("1.3.0-beta.2")
+    );
+    // A breaking change moves the pre-release to the next major version
+    assert_eq!(
+      next_version(
+        "beta",
+        ReleaseType::Major,
+        Some("1.3.0-beta.2"),
+        Some("1.2.3")
+      )
+      .as_deref(),
+      Some("2.0.0-beta.1")
+    );
+  }
+
+  #[test]
+  fn verifies_the_range_of_the_next_version() {
+    let branch: Branch = get_branch("1.2.x");
+
+    assert!(verify_next_version(&branch, &Version::new(1, 2, 5), branch.range.as_ref()).is_ok());
+    let error: FastReleaseError =
+      verify_next_version(&branch, &Version::new(1, 3, 0), branch.range.as_ref()).unwrap_err();
+    assert!(error.message.contains("Maintenance branches"));
+    assert!(verify_next_version(&branch, &Version::new(1, 3, 0), None).is_ok());
+  }
+}
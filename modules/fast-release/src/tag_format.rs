@@ -0,0 +1,233 @@
+// Copyright (c) Toolbi Software. All rights reserved.
+// Check the README file in the project root for more information.
+
+use crate::error::{FastReleaseError, FastReleaseErrorBuilder};
+use lazy_static::lazy_static;
+use regex::Regex;
+use semver::Version;
+
+lazy_static! {
+  static ref PLACEHOLDER_REGEX: Regex = Regex::new(r"\$\{\s*(?P<name>[^}]*?)\s*\}").unwrap();
+}
+
+pub const VERSION_PLACEHOLDER: &str = "version";
+pub const PROJECT_PLACEHOLDER: &str = "project";
+
+// Git refuses the tag names containing these
+const FORBIDDEN_CHARACTERS: [char; 9] = [' ', '~', '^', ':', '?', '*', '[', '\\', '\x7f'];
+
+// Replaces the placeholders of a template, leaving the unknown ones as they are
+pub fn render_placeholders(template: &str, values: &[(&str, &str)]) -> String {
+  PLACEHOLDER_REGEX
+    .replace_all(template, |captures: &regex::Captures| {
+      match values
+        .iter()
+        .find(|(name, _): &&(&str, &str)| *name == &captures["name"])
+      {
+        Some((_, value)) => value.to_string(),
+        None => captures[0].to_string(),
+      }
+    })
+    .to_string()
+}
+
+#[derive(Debug, Clone)]
+pub struct TagFormat {
+  template: String,
+  regex: Regex,
+  has_project: bool,
+}
+
+impl TagFormat {
+  pub fn new(template: &str) -> Result<Self, FastReleaseError> {
+    let error = |message: String| -> FastReleaseError {
+      FastReleaseErrorBuilder::new(&message)
+        .category("CONFIG")
+        .get()
+    };
+
+    let mut version_count: u8 = 0;
+    let mut has_project: bool = false;
+    for captures in PLACEHOLDER_REGEX.captures_iter(template) {
+      match &captures["name"] {
+        VERSION_PLACEHOLDER => version_count += 1,
+        PROJECT_PLACEHOLDER => has_project = true,
+        name => {
+          return Err(error(format!(
+            "The tag format '{}' has an unknown placeholder '${{{}}}'.",
+            template, name
+          )))
+        }
+      }
+    }
+
+    if version_count != 1 {
+      return Err(error(format!(
+        "The tag format '{}' must contain the '${{version}}' placeholder exactly once.",
+        template
+      )));
+    }
+
+    // The placeholders never render to an empty value, so the literals are checked one by one and
+    // only the start and the end of the template can produce an invalid start or end
+    let is_invalid = |literal: &str| -> bool {
+      literal.contains(FORBIDDEN_CHARACTERS)
+        || literal.chars().any(|v: char| v.is_control())
+        || literal.contains("..")
+        || literal.contains("@{")
+    };
+    if PLACEHOLDER_REGEX.split(template).any(is_invalid)
+      || template.starts_with(['/', '.', '-'])
+      || template.ends_with(['/', '.'])
+      || template.ends_with(".lock")
+    {
+      return Err(error(format!(
+        "The tag format '{}' doesn't produce valid git tag names.",
+        template
+      )));
+    }
+
+    // Each placeholder becomes a named group, the rest of the template must match literally
+    let mut pattern: String = String::from("^");
+    let mut last: usize = 0;
+    for captures in PLACEHOLDER_REGEX.captures_iter(template) {
+      let placeholder: regex::Match = captures.get(0).unwrap();
+      pattern.push_str(&regex::escape(&template[last..placeholder.start()]));
+      pattern.push_str(match &captures["name"] {
+        VERSION_PLACEHOLDER => {
+          r"(?P<version>\d+\.\d+\.\d+(?:-[0-9A-Za-z.-]+)?(?:\+[0-9A-Za-z.-]+)?)"
+        }
+        _ => "(?P<project>.+?)",
+      });
+      last = placeholder.end();
+    }
+    pattern.push_str(&regex::escape(&template[last..]));
+    pattern.push('$');
+
+    Ok(Self {
+      template: template.to_string(),
+      regex: Regex::new(&pattern).unwrap(),
+      has_project,
+    })
+  }
+
+  pub fn template(&self) -> &str {
+    &self.template
+  }
+
+  pub fn has_project(&self) -> bool {
+    self.has_project
+  }
+
+  pub fn render(&self, version: &Version, project: Option<&str>) -> String {
+    PLACEHOLDER_REGEX
+      .replace_all(
+        &self.template,
+        |captures: &regex::Captures| match &captures["name"] {
+          VERSION_PLACEHOLDER => version.to_string(),
+          _ => project.unwrap_or("").to_string(),
+        },
+      )
+      .to_string()
+  }
+
+  // Returns the version of a tag created with this format. When the format has a project
+  // placeholder, only the tags of the given project match.
+  pub fn parse(&self, tag: &str, project: Option<&str>) -> Option<Version> {
+    let captures: regex::Captures = self.regex.captures(tag)?;
+
+    if self.has_project {
+      let tag_project: &str = captures.name("project")?.as_str();
+      if project.is_some_and(|v: &str| v != tag_project) {
+        return None;
+      }
+    }
+
+    Version::parse(&captures["version"]).ok()
+  }
+}
+
+impl std::fmt::Display for TagFormat {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}", self.template)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn renders_and_parses_the_tags() {
+    let format: TagFormat = TagFormat::new("v${version}").unwrap();
+    let version: Version = Version::parse("1.2.0-beta.1").unwrap();
+
+    assert_eq!(format.render(&version, Some("app")), "v1.2.0-beta.1");
+    assert_eq!(format.parse("v1.2.0-beta.1", Some("app")), Some(version));
+    assert_eq!(format.parse("v1.2", None), None);
+    assert_eq!(format.parse("1.2.0", None), None);
+    assert_eq!(format.parse("app-v1.2.0", None), None);
+  }
+
+  #[test]
+  fn parses_only_the_tags_of_the_project() {
+    let format: TagFormat = TagFormat::new("${project}-v${version}").unwrap();
+    let version: Version = Version::new(2, 0, 0);
+
+    let tag: String = format.render(&version, Some("core-lib"));
+    assert_eq!(tag, "core-lib-v2.0.0");
+    assert_eq!(format.parse(&tag, Some("core-lib")), Some(version.clone()));
+    assert_eq!(format.parse(&tag, None), Some(version));
+    assert_eq!(format.parse(&tag, Some("core")), None);
+    assert_eq!(format.parse("-v2.0.0", None), None);
+  }
+
+  #[test]
+  fn round_trips_the_formats() {
+    let version: Version = Version::parse("1.0.0+build.5").unwrap();
+
+    for template in [
+      "${version}",
+      "v${ version }",
+      "${project}@${version}",
+      "${project}/v${version}",
+      "release.${version}.final",
+      "${version}-${project}",
+    ] {
+      let format: TagFormat = TagFormat::new(template).unwrap();
+      let tag: String = format.render(&version, Some("app"));
+      assert_eq!(
+        format.parse(&tag, Some("app")),
+        Some(version.clone()),
+        "{}",
+        template
+      );
+    }
+  }
+
+  #[test]
+  fn rejects_the_invalid_formats() {
+    for template in [
+      "v",
+      "${version}-${version}",
+      "${name}-${version}",
+      "v ${version}",
+      "v..${version}",
+      "-v${version}",
+      ".${version}",
+      "${version}/",
+      "${version}.lock",
+      "@{${version}",
+    ] {
+      assert!(TagFormat::new(template).is_err(), "{}", template);
+    }
+  }
+
+  #[test]
+  fn leaves_the_unknown_placeholders() {
+    assert_eq!(
+      render_placeholders("${project} ${version} ${other}", &[("version", "1.0.0")]),
+      "${project} 1.0.0 ${other}"
+    );
+  }
+}
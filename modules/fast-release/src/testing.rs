@@ -0,0 +1,381 @@
+// Copyright (c) Toolbi Software. All rights reserved.
+// Check the README file in the project root for more information.
+
+// Helpers shared by the tests: repositories with a local remote, configurations from YAML and a
+// stub HTTP server that records the requests it gets
+
+use crate::{
+  analyzer::rules::ReleaseType,
+  branches::{self, Branch},
+  config::{self, Config, ConfigModule},
+  git,
+  modules::{Context, NextRelease},
+  project::ReleaseScope,
+};
+use env_ci::{CiEnv, CiServices};
+use git2::{IndexAddOption, Repository, RepositoryInitOptions, Signature};
+use semver::Version;
+use std::{
+  collections::HashMap,
+  fs,
+  io::{BufRead, BufReader, Read, Write},
+  net::{TcpListener, TcpStream},
+  path::{Path, PathBuf},
+  sync::{Arc, Mutex, MutexGuard, PoisonError},
+  thread,
+};
+use tempfile::TempDir;
+
+pub fn get_config(content: &str) -> Config {
+  config::parse(content).unwrap()
+}
+
+pub fn get_module(content: &str) -> ConfigModule {
+  serde_yaml::from_str(content).unwrap()
+}
+
+// The context of the first project of the configuration on the branch 'main'
+pub fn get_context(config: &Config, cwd: &Path) -> Context {
+  let branches: Vec<Branch> = branches::expand(config, &["main".to_string()]).unwrap();
+
+  Context {
+    cwd: cwd.to_path_buf(),
+    dry_run: false,
+    ci_env: get_ci_env(),
+    git: config.git.clone(),
+    branch: branches[0].clone(),
+    project: config.projects[0].clone(),
+    commits: Vec::new(),
+    last_release: None,
+    next_release: None,
+    releases: Vec::new(),
+    rollback: None,
+  }
+}
+
+pub fn get_next_release(tag: &str, version: &str) -> NextRelease {
+  NextRelease {
+    version: Version::parse(version).unwrap(),
+    tag: tag.to_string(),
+    channel: None,
+    release_type: ReleaseType::Minor,
+    hash: "0".repeat(40),
+    notes: "The notes of the release.".to_string(),
+  }
+}
+
+pub fn get_ci_env() -> CiEnv {
+  CiEnv {
+    name: "Git".to_string(),
+    service: CiServices::Git,
+    slug: None,
+    root: None,
+    commit: None,
+    tag: None,
+    branch: None,
+    pull_request: None,
+    pull_request_branch: None,
+    job: None,
+    job_url: None,
+    build: None,
+    build_url: None,
+    server_url: None,
+    api_url: None,
+    is_ci: false,
+    is_pull_request: false,
+  }
+}
+
+//
+
+// A working repository on the branch 'main', with a bare repository as its 'origin' remote
+pub struct TestRepo {
+  pub dir: TempDir,
+  pub repo: Repository,
+}
+
+impl TestRepo {
+  pub fn new() -> Self {
+    let dir: TempDir = TempDir::new().unwrap();
+
+    let mut options: RepositoryInitOptions = RepositoryInitOptions::new();
+    options.initial_head("main");
+    Repository::init_opts(dir.path().join("remote.git"), options.bare(true)).unwrap();
+
+    let mut options: RepositoryInitOptions = RepositoryInitOptions::new();
+    options.initial_head("main");
+    let repo: Repository = Repository::init_opts(dir.path().join("work"), &options).unwrap();
+    repo
+      .remote("origin", dir.path().join("remote.git").to_str().unwrap())
+      .unwrap();
+
+    let mut git_config: git2::Config = repo.config().unwrap();
+    git_config.set_str("user.name", "Tester").unwrap();
+    git_config
+      .set_str("user.email", "tester@example.com")
+      .unwrap();
+
+    Self { dir, repo }
+  }
+
+  pub fn path(&self) -> PathBuf {
+    self.dir.path().join("work")
+  }
+
+  pub fn remote(&self) -> Repository {
+    Repository::open_bare(self.dir.path().join("remote.git")).unwrap()
+  }
+
+  pub fn write(&self, path: &str, content: &str) {
+    let path: PathBuf = self.path().join(path);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+  }
+
+  pub fn read(&self, path: &str) -> Option<String> {
+    fs::read_to_string(self.path().join(path)).ok()
+  }
+
+  // Commits every change of the working tree and returns the hash of the commit
+  pub fn commit(&self, message: &str) -> String {
+    let mut index: git2::Index = self.repo.index().unwrap();
+    index
+      .add_all(["*"].iter(), IndexAddOption::DEFAULT, None)
+      .unwrap();
+    index.write().unwrap();
+    let tree: git2::Tree = self.repo.find_tree(index.write_tree().unwrap()).unwrap();
+
+    let signature: Signature = Signature::now("Tester", "tester@example.com").unwrap();
+    let parent: Option<git2::Commit> = self
+      .repo
+      .head()
+      .ok()
+      .and_then(|v: git2::Reference| v.peel_to_commit().ok());
+    let parents: Vec<&git2::Commit> = parent.iter().collect();
+
+    self
+      .repo
+      .commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        message,
+        &tree,
+        &parents,
+      )
+      .unwrap()
+      .to_string()
+  }
+
+  pub fn push(&self) {
+    git::repo::push(&self.repo, "origin", &["HEAD:refs/heads/main".to_string()]).unwrap();
+  }
+
+  pub fn head(&self) -> String {
+    git::repo::get_head(&self.repo).unwrap()
+  }
+
+  pub fn remote_head(&self) -> Option<String> {
+    self
+      .remote()
+      .revparse_single("refs/heads/main")
+      .ok()
+      .map(|v: git2::Object| v.id().to_string())
+  }
+
+  pub fn has_tag(&self, name: &str) -> bool {
+    git::repo::get_tag_hash(&self.repo, name).is_some()
+  }
+
+  pub fn has_remote_tag(&self, name: &str) -> bool {
+    self
+      .remote()
+      .revparse_single(&format!("refs/tags/{}", name))
+      .is_ok()
+  }
+
+  pub fn get_branches(&self, config: &Config) -> Vec<Branch> {
+    branches::expand(config, &["main".to_string()]).unwrap()
+  }
+}
+
+// Runs the closure with the scope of a release on the branch 'main' at the current HEAD
+pub fn with_scope<T>(
+  test_repo: &TestRepo,
+  config: &Config,
+  run: impl FnOnce(&ReleaseScope) -> T,
+) -> T {
+  let branches: Vec<Branch> = test_repo.get_branches(config);
+  let head: String = test_repo.head();
+
+  run(&ReleaseScope {
+    repo: &test_repo.repo,
+    config,
+    branches: &branches,
+    branch: &branches[0],
+    head: &head,
+  })
+}
+
+//
+
+#[derive(Debug, Clone)]
+pub struct StubRequest {
+  pub method: String,
+  // The path of the request along with its query
+  pub path: String,
+  pub headers: HashMap<String, String>,
+  pub body: Vec<u8>,
+}
+
+impl StubRequest {
+  pub fn header(&self, name: &str) -> Option<&str> {
+    self
+      .headers
+      .get(&name.to_lowercase())
+      .map(|v: &String| v.as_str())
+  }
+
+  pub fn json(&self) -> serde_json::Value {
+    serde_json::from_slice(&self.body).unwrap()
+  }
+}
+
+pub struct StubResponse {
+  pub status: u16,
+  pub body: String,
+  pub headers: Vec<(String, String)>,
+}
+
+impl StubResponse {
+  pub fn new(status: u16, body: &str) -> Self {
+    Self {
+      status,
+      body: body.to_string(),
+      headers: Vec::new(),
+    }
+  }
+
+  pub fn header(mut self, name: &str, value: &str) -> Self {
+    self.headers.push((name.to_string(), value.to_string()));
+    self
+  }
+}
+
+type StubHandler = dyn Fn(&StubRequest) -> StubResponse + Send + Sync;
+
+// Answers every request with the handler until the test ends
+pub struct StubServer {
+  pub url: String,
+  requests: Arc<Mutex<Vec<StubRequest>>>,
+}
+
+impl StubServer {
+  pub fn start(handler: impl Fn(&StubRequest) -> StubResponse + Send + Sync + 'static) -> Self {
+    let listener: TcpListener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url: String = format!("http://{}", listener.local_addr().unwrap());
+    let requests: Arc<Mutex<Vec<StubRequest>>> = Arc::new(Mutex::new(Vec::new()));
+
+    let handler: Arc<StubHandler> = Arc::new(handler);
+    let thread_requests: Arc<Mutex<Vec<StubRequest>>> = requests.clone();
+    thread::spawn(move || {
+      for stream in listener.incoming().flatten() {
+        if let Some(request) = Self::read(&stream) {
+          let response: StubResponse = handler(&request);
+          thread_requests.lock().unwrap().push(request);
+          Self::write(stream, response);
+        }
+      }
+    });
+
+    Self { url, requests }
+  }
+
+  fn read(stream: &TcpStream) -> Option<StubRequest> {
+    let mut reader: BufReader<&TcpStream> = BufReader::new(stream);
+
+    let mut line: String = String::new();
+    reader.read_line(&mut line).ok()?;
+    let mut parts: std::str::SplitWhitespace = line.split_whitespace();
+    let method: String = parts.next()?.to_string();
+    let path: String = parts.next()?.to_string();
+
+    let mut headers: HashMap<String, String> = HashMap::new();
+    loop {
+      let mut line: String = String::new();
+      reader.read_line(&mut line).ok()?;
+      let line: &str = line.trim_end();
+      if line.is_empty() {
+        break;
+      }
+      if let Some((name, value)) = line.split_once(':') {
+        headers.insert(name.trim().to_lowercase(), value.trim().to_string());
+      }
+    }
+
+    let length: usize = headers
+      .get("content-length")
+      .and_then(|v: &String| v.parse().ok())
+      .unwrap_or(0);
+    let mut body: Vec<u8> = vec![0; length];
+    reader.read_exact(&mut body).ok()?;
+
+    Some(StubRequest {
+      method,
+      path,
+      headers,
+      body,
+    })
+  }
+
+  fn write(mut stream: TcpStream, response: StubResponse) {
+    let headers: String = response
+      .headers
+      .iter()
+      .map(|(name, value): &(String, String)| format!("{}: {}\r\n", name, value))
+      .collect();
+    let _ = write!(
+      stream,
+      "HTTP/1.1 {} Stub\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n{}",
+      response.status,
+      response.body.len(),
+      headers,
+      response.body
+    );
+  }
+
+  pub fn requests(&self) -> Vec<StubRequest> {
+    self.requests.lock().unwrap().clone()
+  }
+}
+
+// Sets the environment variables for the closure, and restores them afterwards. The tests that
+// change the environment hold the lock, so they don't see each other's variables.
+pub fn with_env<T>(variables: &[(&str, Option<&str>)], run: impl FnOnce() -> T) -> T {
+  static LOCK: Mutex<()> = Mutex::new(());
+  let _guard: MutexGuard<()> = LOCK
+    .lock()
+    .unwrap_or_else(|v: PoisonError<MutexGuard<()>>| v.into_inner());
+
+  let previous: Vec<(String, Option<String>)> = variables
+    .iter()
+    .map(|(name, _): &(&str, Option<&str>)| (name.to_string(), std::env::var(name).ok()))
+    .collect();
+  for (name, value) in variables {
+    match value {
+      Some(value) => std::env::set_var(name, value),
+      None => std::env::remove_var(name),
+    }
+  }
+
+  let result: T = run();
+
+  for (name, value) in previous {
+    match value {
+      Some(value) => std::env::set_var(&name, value),
+      None => std::env::remove_var(&name),
+    }
+  }
+
+  result
+}
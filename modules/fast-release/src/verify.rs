@@ -0,0 +1,347 @@
+// Copyright (c) Toolbi Software. All rights reserved.
+// Check the README file in the project root for more information.
+
+use crate::{
+  branches::{self, Branch},
+  config::{Config, ConfigProject},
+  error::{FastReleaseError, FastReleaseErrorBuilder},
+  git::{self, repo::Tag},
+};
+use git2::{Reference, Repository, StatusEntry, StatusOptions, Statuses};
+use semver::{Prerelease, Version};
+use tracing::{debug, info};
+
+// The changed files listed on the error of a dirty worktree
+const MAX_LISTED_FILES: usize = 5;
+
+fn error(message: &str) -> FastReleaseError {
+  FastReleaseErrorBuilder::new(message)
+    .category("VERIFY")
+    .get()
+}
+
+pub fn get_branch<'a>(branches: &'a [Branch], name: &str) -> Result<&'a Branch, FastReleaseError> {
+  match branches::get_current(branches, name) {
+    Some(branch) => Ok(branch),
+    None => Err(error(&format!(
+      "The branch '{}' isn't configured to publish releases. The configured branches are {}.",
+      name,
+      branches
+        .iter()
+        .map(|v: &Branch| format!("'{}'", v.name))
+        .collect::<Vec<String>>()
+        .join(", ")
+    ))),
+  }
+}
+
+// The tag format is checked when loading the configuration, but the project names and the
+// pre-release identifiers also end up on the tags
+fn verify_tag_format(config: &Config, branch: &Branch) -> Result<(), FastReleaseError> {
+  let mut version: Version = Version::new(1, 0, 0);
+  if let Some(pre_release) = &branch.pre_release {
+    version.pre = Prerelease::new(&format!("{}.1", pre_release)).unwrap_or(Prerelease::EMPTY);
+  }
+
+  let projects: Vec<Option<&str>> = match branch.tag_format.has_project() {
+    true => config
+      .projects
+      .iter()
+      .map(|v: &ConfigProject| Some(v.name.as_str()))
+      .collect(),
+    false => vec![None],
+  };
+
+  for project in projects {
+    let tag: String = branch.tag_format.render(&version, project);
+    if !Reference::is_valid_name(&format!("refs/tags/{}", tag)) {
+      return Err(error(&format!(
+        "The tag format '{}' of the branch '{}' produces the tag '{}', which isn't a valid git tag name.",
+        branch.tag_format.template(),
+        branch.name,
+        tag
+      )));
+    }
+  }
+
+  Ok(())
+}
+
+// Untracked files are left out, as builds usually leave some behind
+fn verify_worktree(repo: &Repository) -> Result<(), FastReleaseError> {
+  let mut options: StatusOptions = StatusOptions::new();
+  options.include_untracked(false).include_ignored(false);
+
+  let statuses: Statuses = repo
+    .statuses(Some(&mut options))
+    .map_err(|v: git2::Error| git::error("Failed to read the status of the worktree.", v))?;
+  if statuses.is_empty() {
+    return Ok(());
+  }
+
+  let mut files: Vec<String> = statuses
+    .iter()
+    .take(MAX_LISTED_FILES)
+    .filter_map(|v: StatusEntry| v.path().map(|v: &str| format!("'{}'", v)))
+    .collect();
+  if statuses.len() > MAX_LISTED_FILES {
+    files.push(format!("and {} more", statuses.len() - MAX_LISTED_FILES));
+  }
+
+  Err(error(&format!(
+    "The worktree has uncommitted changes on {}. Commit or stash them before releasing.",
+    files.join(", ")
+  )))
+}
+
+// A release that fails after the module 'git' pushed its commit leaves its tag and journal on
+// the tip of the remote branch. Returns the tag when the tip is such a release on top of HEAD.
+fn get_release_to_resume(
+  repo: &Repository,
+  remote: &str,
+  branch: &Branch,
+  head: &str,
+  remote_hash: &str,
+) -> Result<Option<String>, FastReleaseError> {
+  git::repo::fetch(
+    repo,
+    remote,
+    &[
+      format!("+refs/heads/{0}:refs/remotes/{1}/{0}", branch.name, remote),
+      "refs/tags/*:refs/tags/*".to_string(),
+    ],
+  )?;
+  if !git::repo::is_ancestor(repo, head, remote_hash) {
+    return Ok(None);
+  }
+
+  git::notes::fetch(repo, remote)?;
+  Ok(
+    git::repo::get_tags(repo)?
+      .into_iter()
+      .find(|v: &Tag| {
+        v.hash == remote_hash && git::notes::get_journal(repo, &v.name, &v.hash).is_some()
+      })
+      .map(|v: Tag| v.name),
+  )
+}
+
+// Returns the commit of a release to resume when the remote is ahead of HEAD because of it. The
+// dry runs only list the tags of the remote, so they don't move the branch nor the local tags.
+fn verify_remote(
+  repo: &Repository,
+  config: &Config,
+  branch: &Branch,
+) -> Result<Option<String>, FastReleaseError> {
+  let remote: &str = &config.git.remote;
+  let behind: FastReleaseError = error(&format!(
+    "The local branch '{}' is behind the remote '{}'. Pull the new commits, or make sure the CI checks out the latest commit of the branch.",
+    branch.name, remote
+  ));
+
+  let mut result: Option<String> = None;
+  let head: String = git::repo::get_head(repo)?;
+  if let Some(remote_hash) = git::repo::get_remote_hash(repo, remote, &branch.name)? {
+    if !git::repo::is_ancestor(repo, &remote_hash, &head) {
+      if config.dry_run {
+        match git::repo::get_remote_tags(repo, remote, &remote_hash)?.first() {
+          Some(tag) => info!(
+            "The remote '{}' is at the commit of the release '{}'. A run would resume it if it didn't complete, but the dry run leaves the repository as it is.",
+            remote, tag
+          ),
+          None => return Err(behind),
+        }
+      } else {
+        match get_release_to_resume(repo, remote, branch, &head, &remote_hash)? {
+          // The rerun started from the commit before the release commit
+          Some(tag) => {
+            info!(
+              "The release '{}' on the remote '{}' didn't complete, so it's resumed from its commit '{}'.",
+              tag, remote, remote_hash
+            );
+            result = Some(remote_hash);
+          }
+          None => return Err(behind),
+        }
+      }
+    }
+  }
+
+  git::repo::verify_remote_access(repo, remote)?;
+  debug!(
+    message = format!("The remote '{}' is reachable for pushing.", remote),
+    category = "VERIFY"
+  );
+
+  Ok(result)
+}
+
+// Runs before any work, so a release doesn't stop halfway because of something that could be
+// known from the start. Returns the commit of a release to resume, which the run has to move to.
+pub fn run(
+  repo: &Repository,
+  config: &Config,
+  branch: &Branch,
+) -> Result<Option<String>, FastReleaseError> {
+  verify_tag_format(config, branch)?;
+  verify_worktree(repo)?;
+
+  let mut result: Option<String> = None;
+  if config.git.push {
+    result = verify_remote(repo, config, branch)?;
+  } else {
+    debug!(
+      message = "Pushing is disabled, so the remote isn't verified.",
+      category = "VERIFY"
+    );
+  }
+
+  info!("The repository is ready to release.");
+
+  Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    constants::NOTES_REF,
+    lifecycle,
+    project::{ProjectRelease, ReleaseScope},
+    testing::{self, TestRepo},
+  };
+  use std::path::PathBuf;
+
+  fn release(test_repo: &TestRepo, config: &Config) -> Result<(), FastReleaseError> {
+    let branches: Vec<Branch> = test_repo.get_branches(config);
+    if let Some(hash) = run(&test_repo.repo, config, &branches[0])? {
+      lifecycle::move_to_release(&test_repo.repo, &hash)?;
+    }
+
+    testing::with_scope(test_repo, config, |scope: &ReleaseScope| {
+      lifecycle::run(scope, &testing::get_ci_env())
+    })
+    .map(|_: Vec<ProjectRelease>| ())
+  }
+
+  #[test]
+  fn resumes_a_release_from_the_commit_before_the_release_commit() {
+    let test_repo: TestRepo = TestRepo::new();
+    test_repo.write("lib.rs", "lib");
+    let start: String = test_repo.commit("feat: add the library");
+    test_repo.push();
+
+    // Publishing fails until the file exists
+    let published: PathBuf = test_repo.dir.path().join("published");
+    let config: Config = testing::get_config(&format!(
+      r#"
+version: 1
+tag_format: v${{version}}
+branches: [main]
+modules:
+  - changelog
+  - name: git
+    assets: [CHANGELOG.md]
+  - name: exec
+    publish: test -e '{}'
+projects:
+  - name: app
+    path: .
+"#,
+      published.display()
+    ));
+
+    assert!(release(&test_repo, &config).is_err());
+    let release_commit: String = test_repo.head();
+    assert_ne!(release_commit, start);
+    assert_eq!(test_repo.remote_head(), Some(release_commit.clone()));
+    assert!(test_repo.has_remote_tag("v1.0.0"));
+
+    // A rerun of the CI checks out the commit the release started from, without the tag
+    git::repo::reset(&test_repo.repo, &start).unwrap();
+    git::repo::delete_tag(&test_repo.repo, "v1.0.0").unwrap();
+    test_repo
+      .repo
+      .find_reference(NOTES_REF)
+      .unwrap()
+      .delete()
+      .unwrap();
+
+    std::fs::write(&published, "").unwrap();
+    release(&test_repo, &config).unwrap();
+
+    assert_eq!(test_repo.head(), release_commit);
+    assert_eq!(test_repo.remote_head(), Some(release_commit.clone()));
+    assert!(git::notes::get_journal(&test_repo.repo, "v1.0.0", &release_commit).is_none());
+    assert!(!test_repo.has_tag("v1.0.1"));
+  }
+
+  #[test]
+  fn leaves_the_repository_alone_when_a_dry_run_would_resume_a_release() {
+    let test_repo: TestRepo = TestRepo::new();
+    test_repo.write("lib.rs", "lib");
+    let start: String = test_repo.commit("feat: add the library");
+    test_repo.push();
+
+    let mut config: Config = testing::get_config(
+      r#"
+version: 1
+tag_format: v${version}
+branches: [main]
+modules:
+  - name: git
+  - name: exec
+    publish: "false"
+projects:
+  - name: app
+    path: .
+"#,
+    );
+
+    assert!(release(&test_repo, &config).is_err());
+    assert!(test_repo.has_remote_tag("v1.0.0"));
+
+    git::repo::reset(&test_repo.repo, &start).unwrap();
+    git::repo::delete_tag(&test_repo.repo, "v1.0.0").unwrap();
+    test_repo
+      .repo
+      .find_reference(NOTES_REF)
+      .unwrap()
+      .delete()
+      .unwrap();
+
+    config.dry_run = true;
+    let branches: Vec<Branch> = test_repo.get_branches(&config);
+    assert_eq!(run(&test_repo.repo, &config, &branches[0]).unwrap(), None);
+
+    assert_eq!(test_repo.head(), start);
+    assert!(!test_repo.has_tag("v1.0.0"));
+    assert!(test_repo.repo.find_reference(NOTES_REF).is_err());
+  }
+
+  #[test]
+  fn fails_when_a_dry_run_is_behind_the_remote() {
+    let test_repo: TestRepo = TestRepo::new();
+    test_repo.write("lib.rs", "lib");
+    let start: String = test_repo.commit("feat: add the library");
+    test_repo.write("lib.rs", "lib 2");
+    test_repo.commit("fix: change the library");
+    test_repo.push();
+    git::repo::reset(&test_repo.repo, &start).unwrap();
+
+    let mut config: Config = testing::get_config(
+      r#"
+version: 1
+tag_format: v${version}
+branches: [main]
+projects:
+  - name: app
+    path: .
+"#,
+    );
+    config.dry_run = true;
+
+    let branches: Vec<Branch> = test_repo.get_branches(&config);
+    assert!(run(&test_repo.repo, &config, &branches[0]).is_err());
+  }
+}